@@ -0,0 +1,190 @@
+//! 时间轴上的注释，例如部署的时间，便于将统计信息的变化与事件对应起来。
+
+use std::{
+    fs::{rename, File},
+    io::{self, BufReader, BufWriter, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::stats_history::{create_history_dir, history_file_options};
+
+/// 启用持久化时，保存注释的文件的名称。位于统计历史目录中。
+const ANNOTATIONS_FILE_NAME: &str = "annotations.json";
+
+/// 时间轴上的一个注释
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    /// 注释的标签，例如部署的版本
+    pub label: String,
+    /// 注释对应的时间
+    pub timestamp: DateTime<Local>,
+}
+
+/// 添加注释的请求
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewAnnotation {
+    /// 注释的标签
+    pub label: String,
+    /// 注释对应的时间。省略时使用当前时间。
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Local>>,
+}
+
+/// 所有注释，按时间从旧到新排列
+pub struct Annotations {
+    /// 注释
+    annotations: Mutex<Vec<Annotation>>,
+    /// 保存注释的文件。不持久化时为 `None`。
+    file_path: Option<PathBuf>,
+    /// 是否只允许当前用户访问创建的目录和文件
+    restrict_permissions: bool,
+    /// 最多保存的注释数量。超过时删除最旧的注释。
+    max_count: NonZeroUsize,
+}
+
+impl Annotations {
+    /// 创建一个 `Annotations`。如果提供了 `dir`，则从中加载之前保存的注释，并在每次修改后保存到其中。
+    ///
+    /// # 参数
+    /// * `dir` - 保存注释的目录。为 `None` 时只在内存中保存注释。
+    /// * `restrict_permissions` - 是否只允许当前用户访问创建的目录和文件，与统计历史文件相同。
+    /// * `max_count` - 最多保存的注释数量
+    pub fn new(
+        dir: Option<&Path>,
+        restrict_permissions: bool,
+        max_count: NonZeroUsize,
+    ) -> Annotations {
+        let file_path = dir.map(|x| x.join(ANNOTATIONS_FILE_NAME));
+        let mut annotations = match &file_path {
+            Some(path) if path.exists() => match load_annotations(path) {
+                Ok(x) => x,
+                Err(e) => {
+                    println!("从 {:?} 加载注释时出错: {}", path, e);
+                    Vec::new()
+                }
+            },
+            _ => Vec::new(),
+        };
+        truncate_to_newest(&mut annotations, max_count);
+
+        Annotations {
+            annotations: Mutex::new(annotations),
+            file_path,
+            restrict_permissions,
+            max_count,
+        }
+    }
+
+    /// 添加一个注释。如果注释数量超过上限，则删除最旧的注释。
+    ///
+    /// # 参数
+    /// * `annotation` - 要添加的注释
+    pub fn add(&self, annotation: Annotation) -> io::Result<()> {
+        let mut annotations = self.annotations.lock().unwrap();
+        let index = annotations.partition_point(|x| x.timestamp <= annotation.timestamp);
+        annotations.insert(index, annotation);
+        truncate_to_newest(&mut annotations, self.max_count);
+        self.save(&annotations)
+    }
+
+    /// 获取所有注释，从旧到新。
+    pub fn list(&self) -> Vec<Annotation> {
+        self.annotations.lock().unwrap().clone()
+    }
+
+    /// 删除早于 `oldest` 的注释，即已经不在保留的统计历史的时间范围内的注释。
+    ///
+    /// # 参数
+    /// * `oldest` - 保留的统计历史中最早的收集时间
+    pub fn prune_before(&self, oldest: DateTime<Local>) {
+        let mut annotations = self.annotations.lock().unwrap();
+        let count = annotations.partition_point(|x| x.timestamp < oldest);
+        if count == 0 {
+            return;
+        }
+        annotations.drain(..count);
+        if let Err(e) = self.save(&annotations) {
+            println!("保存注释时出错: {}", e);
+        }
+    }
+
+    /// 如果启用了持久化，则将注释写入文件，覆盖之前的内容。
+    ///
+    /// # 参数
+    /// * `annotations` - 所有注释
+    fn save(&self, annotations: &[Annotation]) -> io::Result<()> {
+        let path = match &self.file_path {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            if !dir.exists() {
+                create_history_dir(dir, self.restrict_permissions)?;
+            }
+        }
+
+        // 先写入临时文件再重命名，避免写入途中出错时留下不完整的文件
+        let temp_path = path.with_extension("json.tmp");
+        let temp_file = history_file_options(self.restrict_permissions)
+            .write(true)
+            .truncate(true)
+            .open(&temp_path)?;
+        let mut writer = BufWriter::new(temp_file);
+        serde_json::to_writer(&mut writer, annotations)?;
+        writer.flush()?;
+        drop(writer);
+
+        rename(temp_path, path)
+    }
+}
+
+/// 删除最旧的注释，只保留最新的 `max_count` 个。
+///
+/// # 参数
+/// * `annotations` - 所有注释，从旧到新
+/// * `max_count` - 最多保留的注释数量
+fn truncate_to_newest(annotations: &mut Vec<Annotation>, max_count: NonZeroUsize) {
+    let excess = annotations.len().saturating_sub(max_count.get());
+    annotations.drain(..excess);
+}
+
+/// 从文件加载注释。
+///
+/// # 参数
+/// * `path` - 保存注释的文件
+fn load_annotations(path: &Path) -> io::Result<Vec<Annotation>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut annotations: Vec<Annotation> = serde_json::from_reader(reader)?;
+    annotations.sort_by_key(|x| x.timestamp);
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn add_keeps_newest_annotations() {
+        let annotations = Annotations::new(None, false, NonZeroUsize::new(2).unwrap());
+        let now = Local::now();
+        for (label, minutes) in [("b", 2), ("c", 3), ("a", 1)] {
+            annotations
+                .add(Annotation {
+                    label: label.to_string(),
+                    timestamp: now + Duration::minutes(minutes),
+                })
+                .unwrap();
+        }
+
+        let labels: Vec<_> = annotations.list().into_iter().map(|x| x.label).collect();
+        assert_eq!(vec!["b", "c"], labels);
+    }
+}
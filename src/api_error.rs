@@ -0,0 +1,54 @@
+//! API 的 JSON 错误响应。
+
+use rocket::http::Status;
+use rocket::response::{self, status, Responder};
+use rocket::serde::json::Json;
+use rocket::Request;
+use serde::Serialize;
+
+/// 一个 API 错误。响应体为 `{"error": "..."}` 形式的 JSON，以便客户端不必只依靠状态码判断出错原因。
+#[derive(Debug)]
+pub struct ApiError {
+    /// 响应的状态码。
+    status: Status,
+    /// 错误消息。
+    message: String,
+}
+
+/// API 错误的响应体。
+#[derive(Serialize)]
+struct ApiErrorBody {
+    /// 错误消息。
+    error: String,
+}
+
+impl ApiError {
+    /// 创建一个 `ApiError`。
+    ///
+    /// # 参数
+    /// * `status` - 响应的状态码。
+    /// * `message` - 错误消息。
+    pub fn new(status: Status, message: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// 还没有收集到任何统计信息。这是暂时的，因此返回 503。
+    pub fn not_collected() -> ApiError {
+        ApiError::new(Status::ServiceUnavailable, "no stats collected yet")
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        status::Custom(
+            self.status,
+            Json(ApiErrorBody {
+                error: self.message,
+            }),
+        )
+        .respond_to(request)
+    }
+}
@@ -0,0 +1,121 @@
+//! 综合 CPU 负载、内存压力和 I/O 等待的系统繁忙程度评分
+
+use serde::{Deserialize, Serialize};
+
+use crate::stats::AllStats;
+
+/// 各项指标在繁忙程度评分中的权重。权重不需要加起来等于 1，计算时会在可用的指标之间重新归一化。负的权重视为 0。
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BusyScoreWeights {
+    /// CPU 总负载的权重
+    pub cpu: f32,
+    /// 内存压力（实际使用的内存占总内存的百分比）的权重
+    pub memory: f32,
+    /// I/O 等待时间占 CPU 时间的百分比的权重
+    pub io: f32,
+}
+
+impl Default for BusyScoreWeights {
+    fn default() -> BusyScoreWeights {
+        BusyScoreWeights {
+            cpu: 0.5,
+            memory: 0.3,
+            io: 0.2,
+        }
+    }
+}
+
+/// 系统繁忙程度评分
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusyScore {
+    /// 综合评分，从 0（空闲）到 100（非常繁忙）。没有任何可用且权重大于 0 的指标时为 `None`。
+    pub score: Option<f32>,
+    /// 参与评分的各项指标
+    pub components: Vec<BusyScoreComponent>,
+}
+
+/// 繁忙程度评分中的一项指标
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusyScoreComponent {
+    /// 指标的名称：`cpu`、`memory` 或 `io`
+    pub name: &'static str,
+    /// 指标的值，从 0 到 100。此平台不提供或未收集时为 `None`。
+    pub value: Option<f32>,
+    /// 配置的权重
+    pub weight: f32,
+    /// 在可用的指标之间重新归一化后的权重。所有可用指标的此权重加起来等于 1，不可用的指标为 0。
+    pub effective_weight: f32,
+}
+
+impl BusyScore {
+    /// 根据最近收集的统计信息计算繁忙程度评分。
+    ///
+    /// 评分是可用指标的加权平均值。某项指标不可用时（例如平台不提供 I/O 等待时间），将其权重从总权重中去掉，
+    /// 其余指标的权重按比例放大，因此评分始终在 0 到 100 之间。
+    ///
+    /// # 参数
+    /// * `stats` - 最近收集的统计信息
+    /// * `weights` - 各项指标的权重
+    pub fn from(stats: &AllStats, weights: &BusyScoreWeights) -> BusyScore {
+        let memory_pressure = stats.memory.as_ref().and_then(|x| {
+            if x.total_bytes == 0 {
+                None
+            } else {
+                Some((x.effective_used_bytes() as f64 / x.total_bytes as f64 * 100.0) as f32)
+            }
+        });
+        let iowait = stats
+            .cpu
+            .load_breakdown
+            .as_ref()
+            .and_then(|x| x.iowait_percent);
+        let mut components = vec![
+            BusyScoreComponent {
+                name: "cpu",
+                value: stats.cpu.aggregate_load_percent,
+                weight: weights.cpu,
+                effective_weight: 0.0,
+            },
+            BusyScoreComponent {
+                name: "memory",
+                value: memory_pressure,
+                weight: weights.memory,
+                effective_weight: 0.0,
+            },
+            BusyScoreComponent {
+                name: "io",
+                value: iowait,
+                weight: weights.io,
+                effective_weight: 0.0,
+            },
+        ];
+
+        let total_weight: f32 = components
+            .iter()
+            .filter(|x| x.value.is_some())
+            .map(|x| x.weight.max(0.0))
+            .sum();
+        if total_weight <= 0.0 {
+            return BusyScore {
+                score: None,
+                components,
+            };
+        }
+
+        let mut score = 0.0;
+        for component in &mut components {
+            if let Some(value) = component.value {
+                component.effective_weight = component.weight.max(0.0) / total_weight;
+                score += value.clamp(0.0, 100.0) * component.effective_weight;
+            }
+        }
+
+        BusyScore {
+            score: Some(score.clamp(0.0, 100.0)),
+            components,
+        }
+    }
+}
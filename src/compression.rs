@@ -0,0 +1,150 @@
+//! 根据 `Accept-Encoding` 请求头压缩响应体的 fairing。
+
+use std::io::{self, Cursor, Write};
+
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Header};
+use rocket::{Request, Response};
+
+/// 小于此大小（以字节为单位）的响应体不值得压缩
+const MIN_COMPRESSED_BODY_SIZE: usize = 1024;
+
+/// 支持的内容编码
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// `Content-Encoding` 头中使用的名称
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    /// 使用此编码压缩数据。
+    ///
+    /// # 参数
+    /// * `data` - 要压缩的数据
+    fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// 如果客户端接受，则用 gzip 或 deflate 压缩 JSON、XML、HTML 和文本响应的 fairing。
+pub struct ResponseCompression;
+
+#[rocket::async_trait]
+impl Fairing for ResponseCompression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if response.headers().contains("Content-Encoding") || !is_compressible(response) {
+            return;
+        }
+        let encoding = match request
+            .headers()
+            .get_one("Accept-Encoding")
+            .and_then(preferred_encoding)
+        {
+            Some(x) => x,
+            None => return,
+        };
+        match response.body().preset_size() {
+            Some(size) if size >= MIN_COMPRESSED_BODY_SIZE => (),
+            _ => return,
+        }
+
+        let body = match response.body_mut().to_bytes().await {
+            Ok(x) => x,
+            Err(e) => {
+                println!("读取要压缩的响应体时出错: {}", e);
+                return;
+            }
+        };
+        match encoding.compress(&body) {
+            Ok(compressed) => {
+                response.set_sized_body(compressed.len(), Cursor::new(compressed));
+                response.set_header(Header::new("Content-Encoding", encoding.name()));
+            }
+            Err(e) => {
+                println!("压缩响应体时出错: {}", e);
+                response.set_sized_body(body.len(), Cursor::new(body));
+            }
+        }
+        // 响应内容取决于 `Accept-Encoding` 头，缓存时需要区分
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+    }
+}
+
+/// 响应的内容类型是否值得压缩
+///
+/// # 参数
+/// * `response` - 要检查的响应
+fn is_compressible(response: &Response) -> bool {
+    match response.content_type() {
+        Some(content_type) => [
+            ContentType::JSON,
+            ContentType::XML,
+            ContentType::HTML,
+            ContentType::Plain,
+            ContentType::CSS,
+            ContentType::JavaScript,
+        ]
+        .iter()
+        .any(|x| x.media_type() == content_type.media_type()),
+        None => false,
+    }
+}
+
+/// 从 `Accept-Encoding` 头中选择一个支持的编码。优先使用 gzip；`q=0` 的编码被视为不接受。
+///
+/// # 参数
+/// * `accept_encoding` - `Accept-Encoding` 头的值
+fn preferred_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let accepted: Vec<&str> = accept_encoding
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let name = parts.next()?;
+            let rejected = parts.any(|param| {
+                param.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0)
+            });
+            if rejected {
+                None
+            } else {
+                Some(name)
+            }
+        })
+        .collect();
+
+    [Encoding::Gzip, Encoding::Deflate]
+        .iter()
+        .copied()
+        .find(|encoding| {
+            accepted
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(encoding.name()) || *x == "*")
+        })
+}
@@ -0,0 +1,113 @@
+//! 允许其他源的页面读取统计信息和仪表板 JSON 的跨源资源共享（CORS）fairing。
+
+use std::io::Cursor;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Header, Method, Status};
+use rocket::{Request, Response};
+
+use crate::history_response::TRUNCATED_HEADER_NAME;
+
+/// 表示允许任何源的 `allowed_origins` 条目
+const ANY_ORIGIN: &str = "*";
+
+/// 总是添加 CORS 头的路径前缀
+const STATS_PATH_PREFIXES: [&str; 2] = ["/stats", "/health"];
+
+/// 只对 JSON 响应添加 CORS 头的路径前缀。仪表板根据 `Accept` 头返回 HTML 或 JSON。
+const DASHBOARD_PATH_PREFIX: &str = "/dashboard";
+
+/// 预检请求的结果可以被浏览器缓存多少秒
+const PREFLIGHT_MAX_AGE_SECONDS: u32 = 3600;
+
+/// 如果请求的 `Origin` 在允许的源中，则为统计信息和仪表板 JSON 的响应添加 CORS 头，并响应预检的 `OPTIONS` 请求的 fairing。
+pub struct Cors {
+    /// 允许的源，例如 `https://status.example.com`。包含 `*` 时允许任何源。
+    allowed_origins: Vec<String>,
+}
+
+impl Cors {
+    /// 创建一个 `Cors`。
+    ///
+    /// # 参数
+    /// * `allowed_origins` - 允许的源。包含 `*` 时允许任何源。
+    pub fn new(allowed_origins: Vec<String>) -> Cors {
+        Cors { allowed_origins }
+    }
+
+    /// 获取要在 `Access-Control-Allow-Origin` 头中返回的值。如果不允许此源，则返回“None”。
+    ///
+    /// # 参数
+    /// * `origin` - 请求的 `Origin` 头的值
+    fn allowed_origin(&self, origin: &str) -> Option<String> {
+        if self.allowed_origins.iter().any(|x| x == ANY_ORIGIN) {
+            Some(ANY_ORIGIN.to_string())
+        } else if self
+            .allowed_origins
+            .iter()
+            .any(|x| x.trim_end_matches('/') == origin)
+        {
+            Some(origin.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let path = request.uri().path();
+        let is_stats_path = STATS_PATH_PREFIXES.iter().any(|x| path.starts_with(x));
+        if !is_stats_path && !path.starts_with(DASHBOARD_PATH_PREFIX) {
+            return;
+        }
+        let allowed_origin = match request
+            .headers()
+            .get_one("Origin")
+            .and_then(|x| self.allowed_origin(x))
+        {
+            Some(x) => x,
+            None => return,
+        };
+
+        let is_preflight = request.method() == Method::Options
+            && request.headers().contains("Access-Control-Request-Method");
+        if is_preflight {
+            // 没有处理 `OPTIONS` 的路由，因此在这里将 404 替换为空的成功响应
+            response.set_status(Status::NoContent);
+            response.set_sized_body(0, Cursor::new(Vec::new()));
+            response.remove_header("Content-Type");
+            response.set_header(Header::new("Access-Control-Allow-Methods", "GET, POST"));
+            if let Some(headers) = request.headers().get_one("Access-Control-Request-Headers") {
+                response.set_header(Header::new(
+                    "Access-Control-Allow-Headers",
+                    headers.to_string(),
+                ));
+            }
+            response.set_header(Header::new(
+                "Access-Control-Max-Age",
+                PREFLIGHT_MAX_AGE_SECONDS.to_string(),
+            ));
+        } else if !is_stats_path && response.content_type() != Some(ContentType::JSON) {
+            return;
+        } else {
+            // 浏览器默认只允许跨源的脚本读取少数几个响应头
+            response.set_header(Header::new(
+                "Access-Control-Expose-Headers",
+                TRUNCATED_HEADER_NAME,
+            ));
+        }
+
+        response.set_header(Header::new("Access-Control-Allow-Origin", allowed_origin));
+        // 响应的 CORS 头取决于 `Origin` 头，缓存时需要区分
+        response.adjoin_header(Header::new("Vary", "Origin"));
+    }
+}
@@ -1,51 +1,355 @@
 //! 仪表板模板的上下文。
 
-use chrono::{DateTime, Local, NaiveDateTime, SecondsFormat, Utc};
-use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    stats::{GeneralStats, MountStats, NetworkStats},
-    stats_history::StatsHistory,
+    annotations::Annotation,
+    busy_score::{BusyScore, BusyScoreWeights},
+    high_water_marks::Records,
+    quiet_hours::QuietHours,
+    stats::{
+        bytes_as_mb, round_bytes_to_mb, AddressFamily, AllStats, GeneralStats, MemoryStats,
+        MountStats, NetworkInterfaceStats, NetworkStats,
+    },
+    stats_history::{align_histories, AlignedStats, HistoryPageOrder, StatsHistory},
+    stuck_metrics::find_stuck_metrics,
 };
 
+/// 跟随浏览器的配色方案，但浏览器没有偏好时是否使用暗模式
+const DEFAULT_DARK_MODE: bool = true;
+
+/// 显示图表至少需要的历史条目数。只有一个条目时图表只有一个点，没有意义。
+const MIN_CHART_ENTRIES: usize = 2;
+
+/// 紧凑的上下文中，绝对值不小于此值的图表值四舍五入为整数，更小的值保留一位小数
+const COMPACT_INTEGER_THRESHOLD: f32 = 100.0;
+
+/// 相邻两个条目之间的时间超过预期间隔的多少倍时，视为数据中断
+const GAP_INTERVAL_MULTIPLIER: u32 = 3;
+
+/// 计算滚动平均值和标准差时使用的前面的值的数量
+const ANOMALY_WINDOW_SIZE: usize = 30;
+/// 计算滚动平均值和标准差至少需要的值的数量。少于此数量的数据集不做异常检测。
+const ANOMALY_MIN_POINTS: usize = 10;
+/// 值与滚动平均值的差超过多少个标准差时，视为异常
+const ANOMALY_Z_SCORE_THRESHOLD: f32 = 3.0;
+/// 最小值/最大值区间的不透明度
+const MIN_MAX_BAND_OPACITY: f32 = 0.27;
+/// 每MB的字节数
+const BYTES_PER_MB: f64 = 1_000_000.0;
+/// 每GB的兆字节数
+const MB_PER_GB: u64 = 1_000;
+/// 每TB的兆字节数
+const MB_PER_TB: u64 = 1_000_000;
+/// 在时间标签前添加的日期的格式
+const DATE_LABEL_FORMAT: &str = "%Y-%m-%d";
+/// CPU 负载直方图中每个区间的宽度，以百分比表示
+const CPU_HISTOGRAM_BUCKET_WIDTH_PERCENT: usize = 10;
+
 const CPU_PER_LOGICAL_CPU_LINE_COLOR_LIGHT_MODE: &str = "#00000044"; // gray
 const CPU_PER_LOGICAL_CPU_LINE_COLOR_DARK_MODE: &str = "#ffffff44"; // gray
+const CPU_PER_LOGICAL_CPU_LINE_COLOR_AUTO_MODE: &str = "#88888866"; // gray
 const CPU_AGGREGATE_LINE_COLOR: &str = "#ffcc00"; // yellow
-const CPU_AGGREGATE_FILL_COLOR: &str = "#ffcc0099"; // yellow
 
 const TEMPERATURE_LINE_COLOR: &str = "#990000"; // red
-const TEMPERATURE_FILL_COLOR: &str = "#99000099"; // red
 
 const MEM_LINE_COLOR: &str = "#0055ff"; // blue
-const MEM_FILL_COLOR: &str = "#0055ff99"; // blue
+const MEM_AVAILABLE_LINE_COLOR: &str = "#00ccff"; // light blue
 
 const SENT_LINE_COLOR: &str = "#44eeaa"; // blue-green
-const SENT_FILL_COLOR: &str = "#44eeaa99"; // blue-green
 const RECEIVED_LINE_COLOR: &str = "#44dd22"; // green
-const RECEIVED_FILL_COLOR: &str = "#44dd2299"; // green
 
 const SEND_ERRORS_LINE_COLOR: &str = "#ff8800"; // yellow-orange
-const SEND_ERRORS_FILL_COLOR: &str = "#ff880099"; // yellow-orange
 const RECEIVE_ERRORS_LINE_COLOR: &str = "#ff4400"; // orange
-const RECEIVE_ERRORS_FILL_COLOR: &str = "#ff440099"; // orange
 
 const TCP_LINE_COLOR: &str = "#44eedd"; // teal
-const TCP_FILL_COLOR: &str = "#44eedd99"; // teal
 const UDP_LINE_COLOR: &str = "#44bbdd"; // light blue
-const UDP_FILL_COLOR: &str = "#44bbdd99"; // light blue
 
 const LOAD_AVERAGE_1_LINE_COLOR: &str = "#ff00ff"; // pink
-const LOAD_AVERAGE_1_FILL_COLOR: &str = "#ff00ff99"; // pink
 const LOAD_AVERAGE_5_LINE_COLOR: &str = "#bb00ff"; // purple
-const LOAD_AVERAGE_5_FILL_COLOR: &str = "#bb00ff99"; // purple
 const LOAD_AVERAGE_15_LINE_COLOR: &str = "#7700ff"; // dark purple
-const LOAD_AVERAGE_15_FILL_COLOR: &str = "#7700ff99"; // dark purple
+
+const FLEET_AGENT_LINE_COLOR_LIGHT_MODE: &str = "#00000044"; // gray
+const FLEET_AGENT_LINE_COLOR_DARK_MODE: &str = "#ffffff44"; // gray
+const FLEET_AGENT_LINE_COLOR_AUTO_MODE: &str = "#88888866"; // gray
+
+const PROCESS_COUNT_LINE_COLOR: &str = "#dd7722"; // brown
+
+const CPU_USER_LINE_COLOR: &str = "#ffcc00"; // yellow
+const CPU_NICE_LINE_COLOR: &str = "#88cc00"; // yellow-green
+const CPU_SYSTEM_LINE_COLOR: &str = "#ff6600"; // orange
+const CPU_INTERRUPT_LINE_COLOR: &str = "#cc00cc"; // purple
+const CPU_IOWAIT_LINE_COLOR: &str = "#0088ff"; // blue
+const CPU_STEAL_LINE_COLOR: &str = "#ff0000"; // red
+
+const CPU_HISTOGRAM_BAR_COLOR: &str = "#ffcc00"; // yellow
+
+const BUSY_SCORE_COLOR: &str = "#ff6600"; // orange
+
+/// 图表的类型，决定模板如何绘制图表
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ChartType {
+    /// 以时间为 X 轴的折线图，不填充线下区域
+    Line,
+    /// 以时间为 X 轴的面积图，主要数据集填充线下区域
+    Area,
+    /// 以时间为 X 轴的堆叠面积图，每个数据集叠加在前一个数据集之上
+    Stacked,
+    /// 柱状图，X 轴上的标记是类别而不是时间
+    Bar,
+    /// 显示单个 0 到 100 之间的值的半圆形仪表，没有 X 轴。唯一的数据集包含该值和剩余部分两个值。
+    Gauge,
+}
+
+/// 图表坐标轴上显示的单位
+#[derive(Clone, Copy)]
+enum Unit {
+    /// 百分比
+    Percent,
+    /// 摄氏度
+    Celsius,
+    /// 兆字节
+    Megabytes,
+}
+
+impl Unit {
+    /// 此单位的符号
+    fn symbol(self) -> &'static str {
+        match self {
+            Unit::Percent => "%",
+            Unit::Celsius => "C",
+            Unit::Megabytes => "MB",
+        }
+    }
+}
+
+/// 图表的时间标签中何时包含日期
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeLabelDates {
+    /// 只在图表跨越多天时，在第一个标签和每天的第一个标签中包含日期
+    DayBoundaries,
+    /// 所有标签都包含日期
+    Always,
+}
+
+/// 内存图表中如何计算已用内存
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryMetric {
+    /// 总内存减去空闲内存。在 Linux 上包括可回收的页面缓存和缓冲区，因此会高估实际使用量。
+    TotalMinusFree,
+    /// 总内存减去可用内存（包括可回收的缓存）。平台不提供可用内存时与 `TotalMinusFree` 相同。
+    TotalMinusAvailable,
+}
+
+impl MemoryMetric {
+    /// 根据此方式计算的已用内存，以字节为单位
+    ///
+    /// # 参数
+    /// * `memory` - 内存统计信息
+    fn used_bytes(self, memory: &MemoryStats) -> u64 {
+        match self {
+            MemoryMetric::TotalMinusFree => memory.used_bytes,
+            MemoryMetric::TotalMinusAvailable => memory.effective_used_bytes(),
+        }
+    }
+
+    /// 根据此方式计算的已用内存在合并的统计信息中的最小值和最大值，以字节为单位。没有最小值和最大值时使用 `used_bytes`。
+    ///
+    /// # 参数
+    /// * `memory` - 内存统计信息
+    fn used_range_bytes(self, memory: &MemoryStats) -> (u64, u64) {
+        let used = self.used_bytes(memory);
+        match self {
+            // 合并时只记录了实际使用的内存的最小值和最大值
+            MemoryMetric::TotalMinusFree => (used, used),
+            MemoryMetric::TotalMinusAvailable => (
+                memory.min_effective_used_bytes.unwrap_or(used),
+                memory.max_effective_used_bytes.unwrap_or(used),
+            ),
+        }
+    }
+}
+
+/// 平均负载的时间窗口
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum LoadAverageWindow {
+    /// 1 分钟
+    #[serde(rename = "1")]
+    OneMinute,
+    /// 5 分钟
+    #[serde(rename = "5")]
+    FiveMinutes,
+    /// 15 分钟
+    #[serde(rename = "15")]
+    FifteenMinutes,
+}
+
+impl LoadAverageWindow {
+    /// 时间窗口的分钟数
+    fn minutes(self) -> u32 {
+        match self {
+            LoadAverageWindow::OneMinute => 1,
+            LoadAverageWindow::FiveMinutes => 5,
+            LoadAverageWindow::FifteenMinutes => 15,
+        }
+    }
+}
+
+/// 机群仪表板中合并多个远程代理的统计信息的方式
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FleetAggregation {
+    /// 取平均值
+    Average,
+    /// 求和
+    Sum,
+}
+
+impl FleetAggregation {
+    /// 合并提供的值。如果没有任何值，则返回 `NaN`，表示数据中断。
+    ///
+    /// # 参数
+    /// * `values` - 要合并的值
+    fn combine(self, values: &[f32]) -> f32 {
+        if values.is_empty() {
+            return f32::NAN;
+        }
+        let sum: f32 = values.iter().sum();
+        match self {
+            FleetAggregation::Average => sum / values.len() as f32,
+            FleetAggregation::Sum => sum,
+        }
+    }
+
+    /// 在图表标题中显示的名称
+    fn label(self) -> &'static str {
+        match self {
+            FleetAggregation::Average => "平均",
+            FleetAggregation::Sum => "合计",
+        }
+    }
+}
+
+/// 仪表板的配置
+#[derive(Clone)]
+pub struct DashboardConfig {
+    /// 统计历史中相邻两个条目之间的预期间隔（安静时段之外）
+    pub expected_sample_interval: Duration,
+    /// 安静时段，以及安静时段内相邻两个条目之间的预期间隔。为 `None` 时没有安静时段。
+    pub quiet_hours: Option<(QuietHours, Duration)>,
+    /// 显示百分比和速率等数值时保留的小数位数
+    pub display_precision: usize,
+    /// 是否为每个网络接口单独创建使用量图表，而不是将所有接口合计到一个图表中
+    pub network_per_interface_charts: bool,
+    /// 是否将网络使用量图表中的发送和接收量堆叠显示，使两者叠加后的高度为总使用量，而不是显示为两条线
+    pub network_stacked: bool,
+    /// 为每个网络接口单独创建图表时，最多创建的图表数量
+    pub max_interface_charts: usize,
+    /// 网络图表是否包含环回接口。不包含时，环回接口仍会显示在网络部分中。
+    pub include_loopback: bool,
+    /// 在网络部分和网络图表中代替接口名称显示的别名，以接口名称为键
+    pub interface_aliases: BTreeMap<String, String>,
+    /// 是否在图表中标出异常值
+    pub anomaly_detection: bool,
+    /// 优先显示的图表的 ID，按显示顺序排列。其他图表按默认顺序排在后面。
+    pub chart_order: Vec<String>,
+    /// 图表中每个值与前面多少个值一起求平均以平滑曲线。为 1 时不平滑。
+    pub chart_smoothing_window: usize,
+    /// 单独设置的图表平滑窗口，以图表 ID 为键。没有单独设置的图表使用 `chart_smoothing_window`。
+    pub chart_smoothing_windows: BTreeMap<String, usize>,
+    /// 分页查看历史统计信息时各页的顺序
+    pub history_page_order: HistoryPageOrder,
+    /// 是否在 CPU 和内存图表中显示每个合并窗口内的最小值和最大值之间的区间
+    pub min_max_bands: bool,
+    /// 通过反向代理访问时的路径前缀，不带末尾的 `/`（例如 `/monitor`）。直接访问时为空。
+    pub base_path: String,
+    /// 图表中线下区域的不透明度，从 0.0 到 1.0
+    pub chart_fill_opacity: f32,
+    /// 文件系统使用百分比达到多少时显示为警告
+    pub filesystem_warning_percent: f64,
+    /// 文件系统使用百分比达到多少时显示为严重
+    pub filesystem_critical_percent: f64,
+    /// 是否根据文件系统的大小以 MB、GB 或 TB 显示使用量，而不是总是以 MB 显示
+    pub filesystem_unit_scaling: bool,
+    /// 可用的熵低于多少比特时显示为警告
+    pub entropy_available_min: u64,
+    /// 指标的值在最近多少个历史条目中完全相同时显示为疑似卡住。为 0 时不检测。
+    pub stuck_metric_entries: usize,
+    /// 图表 Y 轴上最多显示的刻度数量
+    pub y_tick_count: usize,
+    /// 机群仪表板中合并多个远程代理的统计信息的方式
+    pub fleet_aggregation: FleetAggregation,
+    /// 是否显示 CPU 负载在各个区间内的样本占比的直方图
+    pub cpu_histogram: bool,
+    /// 系统繁忙程度评分中各项指标的权重
+    pub busy_score_weights: BusyScoreWeights,
+    /// 图表的时间标签中何时包含日期
+    pub time_label_dates: TimeLabelDates,
+    /// 平均负载图表中显示的时间窗口。为空时不显示平均负载图表。
+    pub load_average_windows: Vec<LoadAverageWindow>,
+    /// 内存图表中如何计算已用内存
+    pub memory_metric: MemoryMetric,
+    /// 请求没有指定时，是否为低带宽连接返回紧凑的仪表板上下文
+    pub compact: bool,
+}
+
+impl DashboardConfig {
+    /// 获取网络接口显示的名称。没有别名时使用接口名称。
+    ///
+    /// # 参数
+    /// * `name` - 接口名称
+    fn interface_label<'a>(&'a self, name: &'a str) -> &'a str {
+        self.interface_aliases
+            .get(name)
+            .map(String::as_str)
+            .unwrap_or(name)
+    }
+
+    /// 获取收集时间为 `time` 时相邻两个条目之间的预期间隔。安静时段内收集频率较低，条目之间的间隔也更长。
+    ///
+    /// # 参数
+    /// * `time` - 条目的收集时间
+    fn expected_interval_at(&self, time: DateTime<Local>) -> Duration {
+        match &self.quiet_hours {
+            Some((quiet_hours, interval)) if quiet_hours.contains(time.time()) => *interval,
+            _ => self.expected_sample_interval,
+        }
+    }
+}
 
 /// 仪表板模板的上下文。
 #[derive(Serialize)]
 pub struct DashboardContext {
     title: String,
+    /// 是否使用暗模式。跟随浏览器的配色方案时，这是浏览器没有偏好时使用的模式。
     dark_mode: bool,
+    /// 是否根据浏览器的 `prefers-color-scheme` 选择暗模式或浅色模式
+    dark_mode_auto: bool,
+    /// 图表中显示数值时保留的小数位数
+    display_precision: usize,
+    /// 页面中链接和资源 URL 的路径前缀
+    base_path: String,
+    /// 是否只显示迷你图，不显示图表的标题、图例和坐标轴
+    minimal: bool,
+    /// 是否为低带宽连接缩小了上下文：图表中的值已量化，并省略了空的文本
+    compact: bool,
+    /// 可以选择的远程代理的名称。为空时不显示选择器。
+    machines: Vec<String>,
+    /// 正在查看的远程代理的名称。查看本机时为 `None`。
+    selected_machine: Option<String>,
+    /// 是否正在查看合并所有远程代理的机群仪表板
+    fleet: bool,
+    /// 分页查看历史统计信息时的分页信息。不分页时为 `None`。
+    pagination: Option<PaginationContext>,
     charts: Vec<ChartContext>,
     sections: Vec<DashboardSectionContext>,
     last_update_time: String,
@@ -58,6 +362,8 @@ struct ChartContext {
     id: String,
     /// 此图表的标题。
     title: String,
+    /// 此图表的类型。
+    chart_type: ChartType,
     /// 此图表上显示的数据集。
     datasets: Vec<DatasetContext>,
     /// X 轴的标签。
@@ -66,14 +372,43 @@ struct ChartContext {
     y_label: String,
     /// X 轴上标记的名称。
     x_values: Vec<String>,
+    /// 每个 X 轴标记对应的收集时间，以自 Unix 纪元以来的毫秒数表示，便于使用真正的时间轴。数据中断处为 `None`。
+    x_timestamps: Vec<Option<i64>>,
     /// 此图表预期的最低 Y 值。
     min_y: f32,
     /// 此图表预期的最高可能 Y 值。
     max_y: f32,
+    /// Y 轴上最多显示的刻度（水平网格线）数量。
+    y_tick_count: usize,
+    /// 要在图表上画成竖线的注释。
+    annotations: Vec<ChartAnnotationContext>,
     /// 要在图表旁边显示的第一行文本。
     accompanying_text_1: String,
-    /// 在图表旁边显示的第二行文本。
-    accompanying_text_2: String,
+    /// 在图表旁边显示的第二行文本。紧凑模式下为空时为 `None`，不序列化。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accompanying_text_2: Option<String>,
+}
+
+/// 图表上的一个注释。
+#[derive(Serialize)]
+struct ChartAnnotationContext {
+    /// 注释所在的 X 轴标记的索引，即注释之后的第一个条目。
+    index: usize,
+    /// 注释的标签。
+    label: String,
+}
+
+/// 分页查看历史统计信息时的分页信息。
+#[derive(Serialize)]
+pub struct PaginationContext {
+    /// 当前页码，从 1 开始。
+    pub page: usize,
+    /// 总页数。
+    pub page_count: usize,
+    /// 上一页的 URL。在第一页时为 `None`。
+    pub previous_url: Option<String>,
+    /// 下一页的 URL。在最后一页时为 `None`。
+    pub next_url: Option<String>,
 }
 
 /// 图表中单个数据集的上下文。
@@ -85,10 +420,16 @@ struct DatasetContext {
     line_color_code: String,
     /// 用于线下区域的颜色代码。仅当 `fill` 为 `true` 时才相关。
     fill_color_code: String,
-    /// 此数据集中的值。
+    /// 此数据集中的最新值，即 `values` 的最后一个元素。如果 `values` 为空，则为 `None`。
+    latest_value: Option<f32>,
+    /// 此数据集中的值。数据中断处的值为 `NaN`，序列化为 `null`。
     values: Vec<f32>,
     /// 是否填充线下区域。
     fill: bool,
+    /// 此数据集是否是最小值/最大值区间的边界。区间的上边界要紧接着放在下边界之前，并将 `fill` 设为 `true`，以填充两者之间的区域。
+    band: bool,
+    /// 异常值在 `values` 中的索引。只有启用异常检测时才会计算。
+    anomaly_indices: Vec<usize>,
 }
 
 /// 仪表板部分的上下文。
@@ -98,6 +439,8 @@ struct DashboardSectionContext {
     name: String,
     /// 统计数据
     stats: Vec<String>,
+    /// 统计数据的严重程度，即所有小节中最严重的程度
+    severity: Severity,
     /// 小节
     subsections: Vec<DashboardSubsectionContext>,
 }
@@ -109,6 +452,38 @@ struct DashboardSubsectionContext {
     name: String,
     /// 统计数据
     stats: Vec<String>,
+    /// 统计数据的严重程度。模板根据它为此小节着色。
+    severity: Severity,
+}
+
+/// 统计数据的严重程度。按从轻到重的顺序排列。
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    /// 正常
+    Normal,
+    /// 警告
+    Warning,
+    /// 严重
+    Critical,
+}
+
+impl Severity {
+    /// 根据阈值判断使用百分比的严重程度。
+    ///
+    /// # 参数
+    /// * `used_pct` - 使用百分比
+    /// * `warning_pct` - 达到此百分比时为警告
+    /// * `critical_pct` - 达到此百分比时为严重
+    fn from_usage(used_pct: f64, warning_pct: f64, critical_pct: f64) -> Severity {
+        if used_pct >= critical_pct {
+            Severity::Critical
+        } else if used_pct >= warning_pct {
+            Severity::Warning
+        } else {
+            Severity::Normal
+        }
+    }
 }
 
 impl DashboardContext {
@@ -116,8 +491,16 @@ impl DashboardContext {
     ///
     /// # 参数
     /// * `stats_history` - 用于填充上下文的统计历史记录。
-    /// * `dark_mode` - 是否启用暗模式。
-    pub fn from_history(stats_history: &StatsHistory, dark_mode: bool) -> DashboardContext {
+    /// * `dark_mode` - 是否启用暗模式。为 `None` 时跟随浏览器的配色方案。
+    /// * `config` - 仪表板的配置。
+    /// * `collecting` - 统计历史是否还在收集新的条目。为 `true` 时，条目不足 `MIN_CHART_ENTRIES` 个则不显示图表，
+    ///   而是显示正在收集数据的状态；持久化的历史或其中的一页不会再增加条目，应为 `false`。
+    pub fn from_history(
+        stats_history: &StatsHistory,
+        dark_mode: Option<bool>,
+        config: &DashboardConfig,
+        collecting: bool,
+    ) -> DashboardContext {
         let title = "仪表盘".to_string();
 
         let mut sections = Vec::new();
@@ -126,11 +509,21 @@ impl DashboardContext {
             None => {
                 return DashboardContext {
                     title,
-                    dark_mode,
+                    dark_mode: dark_mode.unwrap_or(DEFAULT_DARK_MODE),
+                    dark_mode_auto: dark_mode.is_none(),
+                    display_precision: config.display_precision,
+                    base_path: config.base_path.clone(),
+                    minimal: false,
+                    compact: false,
+                    machines: Vec::new(),
+                    selected_machine: None,
+                    fleet: false,
+                    pagination: None,
                     charts: Vec::new(),
                     sections: vec![DashboardSectionContext {
                         name: "暂无数据".to_string(),
                         stats: Vec::new(),
+                        severity: Severity::Normal,
                         subsections: Vec::new(),
                     }],
                     last_update_time: "N/A".to_string(),
@@ -138,31 +531,331 @@ impl DashboardContext {
             }
         };
 
-        if let Some(x) = build_general_section(&most_recent_stats.general) {
+        if let Some(x) = build_general_section(&most_recent_stats.general, config) {
             sections.push(x);
         }
-        if let Some(x) = build_network_section(&most_recent_stats.network) {
+        if let Some(x) = build_network_section(&most_recent_stats.network, config) {
             sections.push(x);
         }
         if let Some(x) = &most_recent_stats.filesystems {
-            sections.push(build_filesystems_section(x));
+            sections.push(build_filesystems_section(x, config));
+        }
+        if let Some(x) = build_stuck_metrics_section(stats_history, config) {
+            sections.push(x);
+        }
+
+        let last_update_time = most_recent_stats
+            .collection_time
+            .to_rfc3339_opts(SecondsFormat::Millis, true);
+
+        // 条目不足时不显示图表，而是说明还在收集数据，其他部分仍然显示当前的统计信息
+        let entry_count = stats_history.into_iter().count();
+        if collecting && entry_count < MIN_CHART_ENTRIES {
+            sections.insert(
+                0,
+                DashboardSectionContext {
+                    name: "正在收集数据…".to_string(),
+                    stats: vec![format!(
+                        "图表至少需要 {} 个历史条目，目前有 {} 个。大约每 {} 秒添加一个条目。",
+                        MIN_CHART_ENTRIES,
+                        entry_count,
+                        config.expected_sample_interval.as_secs()
+                    )],
+                    severity: Severity::Normal,
+                    subsections: Vec::new(),
+                },
+            );
+            return DashboardContext {
+                title,
+                dark_mode: dark_mode.unwrap_or(DEFAULT_DARK_MODE),
+                dark_mode_auto: dark_mode.is_none(),
+                display_precision: config.display_precision,
+                base_path: config.base_path.clone(),
+                minimal: false,
+                compact: false,
+                machines: Vec::new(),
+                selected_machine: None,
+                fleet: false,
+                pagination: None,
+                charts: Vec::new(),
+                sections,
+                last_update_time,
+            };
         }
 
+        let entries = entries_with_gaps(stats_history, config);
+
         let mut charts = Vec::new();
-        charts.extend(build_cpu_charts(stats_history, dark_mode));
-        charts.push(build_memory_chart(stats_history));
-        charts.push(build_load_average_chart(stats_history));
-        charts.extend(build_network_charts(stats_history));
+        charts.extend(build_cpu_charts(&entries, dark_mode, config));
+        if let Some(x) = build_cpu_breakdown_chart(&entries, config) {
+            charts.push(x);
+        }
+        if config.cpu_histogram {
+            if let Some(x) = build_cpu_histogram_chart(&entries, config) {
+                charts.push(x);
+            }
+        }
+        charts.push(build_memory_chart(&entries, stats_history, config));
+        if let Some(x) = build_load_average_chart(&entries, config) {
+            charts.push(x);
+        }
+        if let Some(x) = build_process_count_chart(&entries, config) {
+            charts.push(x);
+        }
+        charts.extend(build_network_charts(&entries, config));
+        if let Some(x) = build_busy_score_chart(most_recent_stats, config) {
+            charts.push(x);
+        }
+        sort_charts(&mut charts, &config.chart_order);
+        add_dates_to_time_labels(&mut charts, config.time_label_dates);
+
+        if config.anomaly_detection {
+            mark_anomalies(&mut charts);
+        }
+        smooth_charts(&mut charts, config);
 
         DashboardContext {
             title,
-            dark_mode,
+            dark_mode: dark_mode.unwrap_or(DEFAULT_DARK_MODE),
+            dark_mode_auto: dark_mode.is_none(),
+            display_precision: config.display_precision,
+            base_path: config.base_path.clone(),
+            minimal: false,
+            compact: false,
+            machines: Vec::new(),
+            selected_machine: None,
+            fleet: false,
+            pagination: None,
             charts,
             sections,
-            last_update_time: most_recent_stats
-                .collection_time
-                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            last_update_time,
+        }
+    }
+
+    /// 从多个远程代理的统计历史中构建一个合并显示所有代理的 `DashboardContext`。
+    ///
+    /// # 参数
+    /// * `histories` - 每个远程代理的名称和统计历史。
+    /// * `dark_mode` - 是否启用暗模式。为 `None` 时跟随浏览器的配色方案。
+    /// * `config` - 仪表板的配置。
+    pub fn from_fleet(
+        histories: &[(String, &StatsHistory)],
+        dark_mode: Option<bool>,
+        config: &DashboardConfig,
+    ) -> DashboardContext {
+        let stats_histories: Vec<&StatsHistory> = histories.iter().map(|(_, x)| *x).collect();
+        let aligned = align_histories(&stats_histories, config.expected_sample_interval);
+        let last_update_time = aligned
+            .last()
+            .and_then(|x| x.stats.iter().flatten().map(|x| x.collection_time).max());
+
+        let mut context = DashboardContext {
+            title: "机群仪表盘".to_string(),
+            dark_mode: dark_mode.unwrap_or(DEFAULT_DARK_MODE),
+            dark_mode_auto: dark_mode.is_none(),
+            display_precision: config.display_precision,
+            base_path: config.base_path.clone(),
+            minimal: false,
+            compact: false,
+            machines: Vec::new(),
+            selected_machine: None,
+            fleet: true,
+            pagination: None,
+            charts: Vec::new(),
+            sections: Vec::new(),
+            last_update_time: match last_update_time {
+                Some(x) => x.to_rfc3339_opts(SecondsFormat::Millis, true),
+                None => "N/A".to_string(),
+            },
+        };
+        if aligned.is_empty() {
+            context.sections.push(DashboardSectionContext {
+                name: "暂无数据".to_string(),
+                stats: Vec::new(),
+                severity: Severity::Normal,
+                subsections: Vec::new(),
+            });
+            return context;
+        }
+
+        let names: Vec<&str> = histories.iter().map(|(name, _)| name.as_str()).collect();
+        context.charts = build_fleet_charts(&aligned, &names, dark_mode, config);
+        sort_charts(&mut context.charts, &config.chart_order);
+        add_dates_to_time_labels(&mut context.charts, config.time_label_dates);
+        if config.anomaly_detection {
+            mark_anomalies(&mut context.charts);
+        }
+        smooth_charts(&mut context.charts, config);
+
+        context
+    }
+
+    /// 只保留 ID 与 `chart_id` 匹配的图表，并移除所有部分。如果没有匹配的图表，则返回 `None`。
+    ///
+    /// # 参数
+    /// * `chart_id` - 要保留的图表的 ID。
+    pub fn into_single_chart(mut self, chart_id: &str) -> Option<DashboardContext> {
+        self.charts.retain(|chart| chart.id == chart_id);
+        if self.charts.is_empty() {
+            None
+        } else {
+            self.sections.clear();
+            Some(self)
+        }
+    }
+
+    /// 设置可以选择的远程代理，以便在仪表板中显示选择器。
+    ///
+    /// # 参数
+    /// * `machines` - 可以选择的远程代理的名称
+    /// * `selected_machine` - 正在查看的远程代理的名称。查看本机时为 `None`。
+    pub fn with_machines(
+        mut self,
+        machines: Vec<String>,
+        selected_machine: Option<String>,
+    ) -> DashboardContext {
+        self.machines = machines;
+        self.selected_machine = selected_machine;
+        self
+    }
+
+    /// 设置分页信息，以便在仪表板中显示翻页链接。
+    ///
+    /// # 参数
+    /// * `pagination` - 分页信息
+    pub fn with_pagination(mut self, pagination: PaginationContext) -> DashboardContext {
+        self.pagination = Some(pagination);
+        self
+    }
+
+    /// 添加显示统计历史文件占用的磁盘空间的部分。
+    ///
+    /// # 参数
+    /// * `bytes_used` - 统计历史文件当前占用的字节数
+    /// * `bytes_limit` - 统计历史目录的大小限制，以字节为单位
+    /// * `config` - 仪表板的配置
+    pub fn with_history_usage(
+        mut self,
+        bytes_used: u64,
+        bytes_limit: u64,
+        config: &DashboardConfig,
+    ) -> DashboardContext {
+        let precision = config.display_precision;
+        let used_pct = (bytes_used as f64) / (bytes_limit as f64) * 100.0;
+        self.sections.push(DashboardSectionContext {
+            name: "统计历史文件".to_string(),
+            stats: vec![format!(
+                "使用量: {:.*} / {:.*} MB ({:.*}%)",
+                precision,
+                bytes_used as f64 / BYTES_PER_MB,
+                precision,
+                bytes_limit as f64 / BYTES_PER_MB,
+                precision,
+                used_pct
+            )],
+            // 写满后会丢弃最旧的条目，因此接近大小限制是正常的
+            severity: Severity::Normal,
+            subsections: Vec::new(),
+        });
+        self
+    }
+
+    /// 添加显示各项统计信息的历史最高值的部分。还没有任何最高值时不添加。
+    ///
+    /// # 参数
+    /// * `records` - 历史最高值
+    /// * `config` - 仪表板的配置
+    pub fn with_records(mut self, records: &Records, config: &DashboardConfig) -> DashboardContext {
+        let precision = config.display_precision;
+        let format_time = |time: DateTime<Local>| time.format("%Y-%m-%d %H:%M:%S").to_string();
+        let stats: Vec<String> = [
+            (
+                "CPU 负载",
+                "%",
+                precision,
+                &records.aggregate_cpu_load_percent,
+            ),
+            ("CPU 温度", "°C", precision, &records.cpu_temp_celsius),
+            ("内存使用量", " MB", 0, &records.memory_used_mb),
+        ]
+        .iter()
+        .filter_map(|(name, unit, precision, record)| {
+            record.map(|x| {
+                format!(
+                    "{}最高: {:.*}{}（{}）",
+                    name,
+                    *precision,
+                    x.value,
+                    unit,
+                    format_time(x.time)
+                )
+            })
+        })
+        .collect();
+        if stats.is_empty() {
+            return self;
+        }
+
+        self.sections.push(DashboardSectionContext {
+            name: format!("历史最高值（自 {} 起）", format_time(records.since)),
+            stats,
+            severity: Severity::Normal,
+            subsections: Vec::new(),
+        });
+        self
+    }
+
+    /// 将注释添加到图表中。只添加在图表时间范围内的注释。
+    ///
+    /// # 参数
+    /// * `annotations` - 所有注释，从旧到新
+    pub fn with_annotations(mut self, annotations: &[Annotation]) -> DashboardContext {
+        for chart in &mut self.charts {
+            let first = chart.x_timestamps.iter().flatten().next().copied();
+            let last = chart.x_timestamps.iter().flatten().last().copied();
+            let (first, last) = match (first, last) {
+                (Some(first), Some(last)) => (first, last),
+                _ => continue,
+            };
+            chart.annotations = annotations
+                .iter()
+                .filter_map(|annotation| {
+                    let timestamp = annotation.timestamp.timestamp_millis();
+                    if timestamp < first || timestamp > last {
+                        return None;
+                    }
+                    let index = chart
+                        .x_timestamps
+                        .iter()
+                        .position(|x| matches!(x, Some(x) if *x >= timestamp))?;
+                    Some(ChartAnnotationContext {
+                        index,
+                        label: annotation.label.clone(),
+                    })
+                })
+                .collect();
+        }
+        self
+    }
+
+    /// 转换为只显示迷你图的上下文，并移除所有部分，以便嵌入到其他页面中。
+    pub fn into_minimal(mut self) -> DashboardContext {
+        self.minimal = true;
+        self.sections.clear();
+        self
+    }
+
+    /// 转换为适合低带宽连接的紧凑上下文：量化图表中的值（见 `quantize`），并省略空的第二行文本，以缩小序列化后的大小。
+    pub fn into_compact(mut self) -> DashboardContext {
+        self.compact = true;
+        for chart in &mut self.charts {
+            for dataset in &mut chart.datasets {
+                dataset.values.iter_mut().for_each(|x| *x = quantize(*x));
+                dataset.latest_value = dataset.latest_value.map(quantize);
+            }
+            chart.accompanying_text_2 = chart.accompanying_text_2.take().filter(|x| !x.is_empty());
         }
+        self
     }
 }
 
@@ -170,7 +863,11 @@ impl DashboardContext {
 ///
 /// # 参数
 /// * `stats` - 系统信息
-fn build_general_section(stats: &GeneralStats) -> Option<DashboardSectionContext> {
+/// * `config` - 仪表板的配置
+fn build_general_section(
+    stats: &GeneralStats,
+    config: &DashboardConfig,
+) -> Option<DashboardSectionContext> {
     let mut stat_strings = Vec::new();
     if let Some(x) = stats.uptime_seconds {
         stat_strings.push(format!("正常运行时间: {} 秒", x))
@@ -182,23 +879,93 @@ fn build_general_section(stats: &GeneralStats) -> Option<DashboardSectionContext
             parsed_time.with_timezone(&Local).to_rfc3339()
         ))
     }
+    if let Some(x) = stats.process_count {
+        stat_strings.push(format!("进程数: {}", x))
+    }
+    if let Some(x) = stats.thread_count {
+        stat_strings.push(format!("线程数: {}", x))
+    }
+    if let Some(x) = stats.logged_in_users {
+        stat_strings.push(format!("已登录用户数: {}", x))
+    }
+    if let Some(x) = stats.user_sessions {
+        stat_strings.push(format!("登录会话数: {}", x))
+    }
+
+    // 单独作为小节，这样熵不足时只有这一项显示为警告
+    let mut subsections = Vec::new();
+    if let Some(x) = stats.entropy_available {
+        subsections.push(DashboardSubsectionContext {
+            name: "随机数熵池".to_string(),
+            stats: vec![format!("可用的熵: {} 比特", x)],
+            severity: if x < config.entropy_available_min {
+                Severity::Warning
+            } else {
+                Severity::Normal
+            },
+        });
+    }
 
-    if stat_strings.is_empty() {
+    if stat_strings.is_empty() && subsections.is_empty() {
         None
     } else {
         Some(DashboardSectionContext {
             name: "系统信息".to_string(),
             stats: stat_strings,
-            subsections: Vec::new(),
+            severity: Severity::Normal,
+            subsections,
         })
     }
 }
 
+/// 创建疑似卡住的指标小节。没有检测到卡住的指标或未启用检测时返回 `None`。
+///
+/// # 参数
+/// * `stats_history` - 统计历史记录
+/// * `config` - 仪表板的配置
+fn build_stuck_metrics_section(
+    stats_history: &StatsHistory,
+    config: &DashboardConfig,
+) -> Option<DashboardSectionContext> {
+    if config.stuck_metric_entries == 0 {
+        return None;
+    }
+
+    let stuck = find_stuck_metrics(stats_history, config.stuck_metric_entries);
+    if stuck.is_empty() {
+        return None;
+    }
+
+    let precision = config.display_precision;
+    Some(DashboardSectionContext {
+        name: "疑似卡住的指标".to_string(),
+        stats: stuck
+            .iter()
+            .map(|x| {
+                format!(
+                    "{}: 自 {} 起一直为 {:.*}（{} 个条目）",
+                    x.name,
+                    x.since.format("%Y-%m-%d %H:%M:%S"),
+                    precision,
+                    x.value,
+                    x.entries
+                )
+            })
+            .collect(),
+        severity: Severity::Warning,
+        subsections: Vec::new(),
+    })
+}
+
 /// 创建网络小节
 ///
 /// # 参数
 /// * `network_stats` - 网络统计信息
-fn build_network_section(network_stats: &NetworkStats) -> Option<DashboardSectionContext> {
+/// * `config` - 仪表板的配置
+fn build_network_section(
+    network_stats: &NetworkStats,
+    config: &DashboardConfig,
+) -> Option<DashboardSectionContext> {
     let mut subsections = Vec::new();
     match &network_stats.sockets {
         Some(socket_stats) => subsections.push(DashboardSubsectionContext {
@@ -213,6 +980,7 @@ fn build_network_section(network_stats: &NetworkStats) -> Option<DashboardSectio
                     socket_stats.udp_in_use, socket_stats.udp6_in_use
                 ),
             ],
+            severity: Severity::Normal,
         }),
         None => (),
     }
@@ -220,21 +988,36 @@ fn build_network_section(network_stats: &NetworkStats) -> Option<DashboardSectio
     match &network_stats.interfaces {
         Some(x) => {
             for interface in x {
+                let mut stats = vec![
+                    format!("IP addresses: {}", format_addresses(interface)),
+                    format!(
+                        "Sent: {} packets, {} MB, {} errors",
+                        interface.sent_packets, interface.sent_mb, interface.send_errors
+                    ),
+                    format!(
+                        "Received: {} packets, {} MB, {} errors",
+                        interface.received_packets, interface.received_mb, interface.receive_errors
+                    ),
+                ];
+                // 链路速率未知时无法计算利用率
+                if let (Some(link_speed), Some((sent, received))) =
+                    (interface.link_speed_mbps, interface.utilization_percent())
+                {
+                    let format_percent = |x: Option<f32>| match x {
+                        Some(x) => format!("{:.*}%", config.display_precision, x),
+                        None => "--%".to_string(),
+                    };
+                    stats.push(format!(
+                        "Utilization: {} sent, {} received of {} Mbps",
+                        format_percent(sent),
+                        format_percent(received),
+                        link_speed
+                    ));
+                }
                 subsections.push(DashboardSubsectionContext {
-                    name: interface.name.clone(),
-                    stats: vec![
-                        format!("IP addresses: {}", interface.addresses.join(", ")),
-                        format!(
-                            "Sent: {} packets, {} MB, {} errors",
-                            interface.sent_packets, interface.sent_mb, interface.send_errors
-                        ),
-                        format!(
-                            "Received: {} packets, {} MB, {} errors",
-                            interface.received_packets,
-                            interface.received_mb,
-                            interface.receive_errors
-                        ),
-                    ],
+                    name: config.interface_label(&interface.name).to_string(),
+                    stats,
+                    severity: Severity::Normal,
                 })
             }
         }
@@ -247,215 +1030,847 @@ fn build_network_section(network_stats: &NetworkStats) -> Option<DashboardSectio
         Some(DashboardSectionContext {
             name: "Network".to_string(),
             stats: Vec::new(),
+            severity: Severity::Normal,
             subsections,
         })
     }
 }
 
+/// 格式化网络接口的 IP 地址，并标出每个地址的地址族。没有地址族信息时（例如来自旧版本的远程代理），只显示地址。
+///
+/// # 参数
+/// * `interface` - 网络接口的统计信息
+fn format_addresses(interface: &NetworkInterfaceStats) -> String {
+    if interface.interface_addresses.is_empty() {
+        return interface.addresses.join(", ");
+    }
+
+    interface
+        .interface_addresses
+        .iter()
+        .map(|x| match x.family {
+            AddressFamily::V4 => format!("{} (IPv4)", x.address),
+            AddressFamily::V6 => format!("{} (IPv6)", x.address),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// 创建文件系统小节
 ///
 /// # 参数
 /// * `mount_stats` - 文件系统信息
-fn build_filesystems_section(mount_stats: &[MountStats]) -> DashboardSectionContext {
+/// * `config` - 仪表板的配置
+fn build_filesystems_section(
+    mount_stats: &[MountStats],
+    config: &DashboardConfig,
+) -> DashboardSectionContext {
+    let precision = config.display_precision;
     let mut total_used_mb = 0;
     let mut total_total_mb = 0;
+    let mut worst_severity = Severity::Normal;
     let mut subsections = Vec::new();
     for mount in mount_stats {
         total_used_mb += mount.used_mb;
         total_total_mb += mount.total_mb;
-        let used_pct = ((mount.used_mb as f64) / (mount.total_mb as f64)) * 100.0;
+        let used_pct = mount.used_percent();
+        let severity = Severity::from_usage(
+            used_pct,
+            config.filesystem_warning_percent,
+            config.filesystem_critical_percent,
+        );
+        worst_severity = worst_severity.max(severity);
+        let mut stats = vec![
+            format!("类型: {}", mount.fs_type),
+            format!("挂载点: {}", mount.mounted_from),
+            format!(
+                "使用量: {} ({:.*}%)",
+                format_storage_usage(mount.used_mb, mount.total_mb, config),
+                precision,
+                used_pct
+            ),
+        ];
+        if let Some(options) = &mount.mount_options {
+            let access = if mount.read_only { "只读" } else { "读写" };
+            stats.push(format!("访问: {} ({})", access, options));
+        }
         subsections.push(DashboardSubsectionContext {
             name: mount.mounted_on.clone(),
-            stats: vec![
-                format!("类型: {}", mount.fs_type),
-                format!("挂载点: {}", mount.mounted_from),
-                format!(
-                    "使用量: {} / {} MB ({:.2}%)",
-                    mount.used_mb, mount.total_mb, used_pct
-                ),
-            ],
+            stats,
+            severity,
         });
     }
 
-    let total_used_pct = ((total_used_mb as f64) / (total_total_mb as f64)) * 100.0;
+    // 只有总大小为 0 的文件系统（例如 `/proc`）时没有使用百分比可言
+    let total_used_pct = if total_total_mb == 0 {
+        0.0
+    } else {
+        ((total_used_mb as f64) / (total_total_mb as f64)) * 100.0
+    };
     DashboardSectionContext {
         name: "文件系统".to_string(),
         stats: vec![format!(
-            "总使用量: {} / {} MB ({:.2}%)",
-            total_used_mb, total_total_mb, total_used_pct
+            "总使用量: {} ({:.*}%)",
+            format_storage_usage(total_used_mb, total_total_mb, config),
+            precision,
+            total_used_pct
         )],
+        severity: worst_severity,
         subsections,
     }
 }
 
+/// 将存储使用量格式化为 `已用 / 总量 单位`。启用 `filesystem_unit_scaling` 时，根据总量选择 MB、GB 或 TB，
+/// 例如 `1.9 / 4.0 TB`；否则总是以 MB 显示。
+///
+/// # 参数
+/// * `used_mb` - 已用的兆字节数
+/// * `total_mb` - 总兆字节数
+/// * `config` - 仪表板的配置
+fn format_storage_usage(used_mb: u64, total_mb: u64, config: &DashboardConfig) -> String {
+    if !config.filesystem_unit_scaling || total_mb < MB_PER_GB {
+        return format!("{} / {} MB", used_mb, total_mb);
+    }
+    let (divisor, unit) = if total_mb >= MB_PER_TB {
+        (MB_PER_TB, "TB")
+    } else {
+        (MB_PER_GB, "GB")
+    };
+    let precision = config.display_precision;
+    format!(
+        "{:.*} / {:.*} {}",
+        precision,
+        used_mb as f64 / divisor as f64,
+        precision,
+        total_mb as f64 / divisor as f64,
+        unit
+    )
+}
+
+/// 创建合并多个远程代理的统计信息的机群图表
+///
+/// # 参数
+/// * `aligned` - 按收集时间对齐的每个远程代理的统计信息
+/// * `names` - 每个远程代理的名称，顺序与 `aligned` 中的统计信息相同
+/// * `dark_mode` - 是否启用暗模式。为 `None` 时跟随浏览器的配色方案。
+/// * `config` - 仪表板的配置
+fn build_fleet_charts(
+    aligned: &[AlignedStats],
+    names: &[&str],
+    dark_mode: Option<bool>,
+    config: &DashboardConfig,
+) -> Vec<ChartContext> {
+    let precision = config.display_precision;
+    let aggregation = config.fleet_aggregation;
+    let mut per_agent_cpu_values = vec![Vec::new(); names.len()];
+    let mut cpu_values = Vec::new();
+    let mut memory_values = Vec::new();
+    let mut memory_total_values = Vec::new();
+    let mut x_values = Vec::new();
+    let mut x_timestamps = Vec::new();
+    for point in aligned {
+        let mut cpu_loads = Vec::new();
+        let mut memory_used = Vec::new();
+        let mut memory_total = Vec::new();
+        for (i, stats) in point.stats.iter().enumerate() {
+            let load = stats.and_then(|x| x.cpu.aggregate_load_percent);
+            per_agent_cpu_values[i].push(load.unwrap_or(f32::NAN));
+            cpu_loads.extend(load);
+            if let Some(memory) = stats.and_then(|x| x.memory.as_ref()) {
+                memory_used.push(bytes_as_mb(config.memory_metric.used_bytes(memory)) as f32);
+                memory_total.push(memory.total_mb() as f32);
+            }
+        }
+        cpu_values.push(aggregation.combine(&cpu_loads));
+        memory_values.push(aggregation.combine(&memory_used));
+        memory_total_values.push(aggregation.combine(&memory_total));
+        x_values.push(format_time(point.time));
+        x_timestamps.push(Some(point.time.timestamp_millis()));
+    }
+
+    let agent_line_color = match dark_mode {
+        Some(true) => FLEET_AGENT_LINE_COLOR_DARK_MODE,
+        Some(false) => FLEET_AGENT_LINE_COLOR_LIGHT_MODE,
+        // 不知道背景的颜色，使用在两种背景上都可见的颜色
+        None => FLEET_AGENT_LINE_COLOR_AUTO_MODE,
+    };
+    let cpu_accompanying_text = match cpu_values.last() {
+        Some(x) if !x.is_nan() => format!("{:.*}%", precision, x),
+        _ => "--%".to_string(),
+    };
+    let mut cpu_datasets = vec![DatasetContext {
+        name: aggregation.label().to_string(),
+        line_color_code: CPU_AGGREGATE_LINE_COLOR.to_string(),
+        fill_color_code: with_opacity(CPU_AGGREGATE_LINE_COLOR, config.chart_fill_opacity),
+        latest_value: cpu_values.last().copied(),
+        values: cpu_values,
+        fill: true,
+        band: false,
+        anomaly_indices: Vec::new(),
+    }];
+    for (name, values) in names.iter().zip(per_agent_cpu_values) {
+        cpu_datasets.push(DatasetContext {
+            name: name.to_string(),
+            line_color_code: agent_line_color.to_string(),
+            fill_color_code: "".to_string(),
+            latest_value: values.last().copied(),
+            values,
+            fill: false,
+            band: false,
+            anomaly_indices: Vec::new(),
+        });
+    }
+
+    let memory_accompanying_text = match (memory_values.last(), memory_total_values.last()) {
+        (Some(used), Some(total)) if !used.is_nan() => format!("{:.0} / {:.0} MB", used, total),
+        _ => "-- / -- MB".to_string(),
+    };
+    let max_memory_total = memory_total_values
+        .iter()
+        .copied()
+        .filter(|x| !x.is_nan())
+        .fold(0.0, f32::max);
+
+    vec![
+        ChartContext {
+            id: "fleet-cpu-usage-chart".to_string(),
+            title: format!("CPU使用率（{}）", aggregation.label()),
+            chart_type: ChartType::Area,
+            datasets: cpu_datasets,
+            x_label: "时间".to_string(),
+            y_label: axis_label("使用率", Some(Unit::Percent), false),
+            x_values: x_values.clone(),
+            x_timestamps: x_timestamps.clone(),
+            min_y: 0.0,
+            max_y: match aggregation {
+                FleetAggregation::Average => 100.0,
+                FleetAggregation::Sum => 100.0 * names.len() as f32,
+            },
+            y_tick_count: config.y_tick_count,
+            annotations: Vec::new(),
+            accompanying_text_1: cpu_accompanying_text,
+            accompanying_text_2: Some(format!("{} 台机器", names.len())),
+        },
+        ChartContext {
+            id: "fleet-ram-chart".to_string(),
+            title: format!("内存使用量（{}）", aggregation.label()),
+            chart_type: ChartType::Area,
+            datasets: vec![DatasetContext {
+                name: "已用内存".to_string(),
+                line_color_code: MEM_LINE_COLOR.to_string(),
+                fill_color_code: with_opacity(MEM_LINE_COLOR, config.chart_fill_opacity),
+                latest_value: memory_values.last().copied(),
+                values: memory_values,
+                fill: true,
+                band: false,
+                anomaly_indices: Vec::new(),
+            }],
+            x_label: "时间".to_string(),
+            y_label: axis_label("使用量", Some(Unit::Megabytes), false),
+            x_values,
+            x_timestamps,
+            min_y: 0.0,
+            max_y: max_memory_total,
+            y_tick_count: config.y_tick_count,
+            annotations: Vec::new(),
+            accompanying_text_1: memory_accompanying_text,
+            accompanying_text_2: Some("".to_string()),
+        },
+    ]
+}
+
 /// 创建CPU图表
 ///
 /// # 参数
-/// * `stats_history` - 历史统计信息
-fn build_cpu_charts(stats_history: &StatsHistory, dark_mode: bool) -> Vec<ChartContext> {
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `dark_mode` - 是否启用暗模式。为 `None` 时跟随浏览器的配色方案。
+/// * `config` - 仪表板的配置
+fn build_cpu_charts(
+    entries: &[Option<&AllStats>],
+    dark_mode: Option<bool>,
+    config: &DashboardConfig,
+) -> Vec<ChartContext> {
+    let precision = config.display_precision;
     let mut charts = Vec::new();
     let mut cpu_datasets = Vec::new();
     let mut aggregate_values = Vec::new();
+    let mut aggregate_min_values = Vec::new();
+    let mut aggregate_max_values = Vec::new();
     let mut per_logical_cpu_values = Vec::new();
     let mut temp_values = Vec::new();
     let mut x_values = Vec::new();
+    let mut x_timestamps = Vec::new();
     let empty_vec = Vec::new();
-    for stats in stats_history.into_iter() {
-        aggregate_values.push(stats.cpu.aggregate_load_percent.unwrap_or(0.0));
-        per_logical_cpu_values.push(
+    for entry in entries {
+        let stats = match entry {
+            Some(x) => x,
+            None => {
+                aggregate_values.push(f32::NAN);
+                aggregate_min_values.push(f32::NAN);
+                aggregate_max_values.push(f32::NAN);
+                per_logical_cpu_values.push(None);
+                temp_values.push(f32::NAN);
+                x_values.push(String::new());
+                x_timestamps.push(None);
+                continue;
+            }
+        };
+        let aggregate = stats.cpu.aggregate_load_percent.unwrap_or(0.0);
+        aggregate_values.push(aggregate);
+        // 还没有合并的条目没有最小值和最大值，此时区间收缩为平均值
+        aggregate_min_values.push(stats.cpu.min_aggregate_load_percent.unwrap_or(aggregate));
+        aggregate_max_values.push(stats.cpu.peak_aggregate_load_percent.unwrap_or(aggregate));
+        per_logical_cpu_values.push(Some(
             stats
                 .cpu
                 .per_logical_cpu_load_percent
                 .as_ref()
                 .unwrap_or(&empty_vec),
-        );
+        ));
         temp_values.push(stats.cpu.temp_celsius.unwrap_or(0.0));
         x_values.push(format_time(stats.collection_time));
+        x_timestamps.push(Some(stats.collection_time.timestamp_millis()));
     }
 
-    let usage_accompanying_text = format!("{:.2}%", aggregate_values.last().unwrap_or(&0.0));
+    let usage_accompanying_text =
+        format!("{:.*}%", precision, aggregate_values.last().unwrap_or(&0.0));
+    // 合并后的负载是平均值，同时显示最近一次合并的峰值以免遗漏短暂的高负载。
+    // 最新的条目可能还没有合并，因此查找最近一个有峰值的条目。
+    let latest_peak_load = entries
+        .iter()
+        .rev()
+        .flatten()
+        .find_map(|x| x.cpu.peak_aggregate_load_percent);
+    let usage_peak_text = match latest_peak_load {
+        Some(peak) => format!("峰值 {:.*}%", precision, peak),
+        None => "".to_string(),
+    };
 
     cpu_datasets.push(DatasetContext {
         name: "总计".to_string(),
         line_color_code: CPU_AGGREGATE_LINE_COLOR.to_string(),
-        fill_color_code: CPU_AGGREGATE_FILL_COLOR.to_string(),
+        fill_color_code: with_opacity(CPU_AGGREGATE_LINE_COLOR, config.chart_fill_opacity),
+        latest_value: aggregate_values.last().copied(),
         values: aggregate_values,
         fill: true,
+        band: false,
+        anomaly_indices: Vec::new(),
     });
+    if config.min_max_bands {
+        cpu_datasets.extend(band_datasets(
+            aggregate_min_values,
+            aggregate_max_values,
+            &with_opacity(CPU_AGGREGATE_LINE_COLOR, MIN_MAX_BAND_OPACITY),
+        ));
+    }
 
     // TODO 必须有更好的方法来做到这一点
     let num_logical_cpus = match per_logical_cpu_values.first() {
-        Some(x) => x.len(),
-        None => 0,
+        Some(Some(x)) => x.len(),
+        _ => 0,
     };
     let mut per_logical_cpu_values_flipped: Vec<Vec<f32>> = Vec::new();
     for _ in 0..num_logical_cpus {
         per_logical_cpu_values_flipped.push(Vec::new());
     }
-    for vec in per_logical_cpu_values {
-        for (i, x) in vec.iter().enumerate() {
-            per_logical_cpu_values_flipped[i].push(*x);
+    for entry in per_logical_cpu_values {
+        match entry {
+            Some(vec) => {
+                for (i, x) in vec.iter().enumerate() {
+                    per_logical_cpu_values_flipped[i].push(*x);
+                }
+            }
+            None => {
+                for values in per_logical_cpu_values_flipped.iter_mut() {
+                    values.push(f32::NAN);
+                }
+            }
         }
     }
 
-    let per_logical_cpu_line_color = if dark_mode {
-        CPU_PER_LOGICAL_CPU_LINE_COLOR_DARK_MODE
-    } else {
-        CPU_PER_LOGICAL_CPU_LINE_COLOR_LIGHT_MODE
+    let per_logical_cpu_line_color = match dark_mode {
+        Some(true) => CPU_PER_LOGICAL_CPU_LINE_COLOR_DARK_MODE,
+        Some(false) => CPU_PER_LOGICAL_CPU_LINE_COLOR_LIGHT_MODE,
+        // 不知道背景的颜色，使用在两种背景上都可见的颜色
+        None => CPU_PER_LOGICAL_CPU_LINE_COLOR_AUTO_MODE,
     };
     for (i, values) in per_logical_cpu_values_flipped.into_iter().enumerate() {
         cpu_datasets.push(DatasetContext {
             name: format!("CPU {}", i),
             line_color_code: per_logical_cpu_line_color.to_string(),
             fill_color_code: "".to_string(),
+            latest_value: values.last().copied(),
             values,
             fill: false,
+            band: false,
+            anomaly_indices: Vec::new(),
         });
     }
 
     charts.push(ChartContext {
         id: "cpu-usage-chart".to_string(),
         title: "CPU使用率".to_string(),
+        chart_type: ChartType::Area,
         datasets: cpu_datasets,
         x_label: "时间".to_string(),
-        y_label: "使用率 (%)".to_string(),
+        y_label: axis_label("使用率", Some(Unit::Percent), false),
         x_values: x_values.clone(),
+        x_timestamps: x_timestamps.clone(),
         min_y: 0.0,
         max_y: 100.0,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
         accompanying_text_1: usage_accompanying_text,
-        accompanying_text_2: "".to_string(),
+        accompanying_text_2: Some(usage_peak_text),
     });
 
-    let temp_accompanying_text = format!("{:.2}°C", temp_values.last().unwrap_or(&0.0));
+    let temp_accompanying_text = format!("{:.*}°C", precision, temp_values.last().unwrap_or(&0.0));
+    let latest_peak_temp = entries
+        .iter()
+        .rev()
+        .flatten()
+        .find_map(|x| x.cpu.peak_temp_celsius);
+    let temp_peak_text = match latest_peak_temp {
+        Some(peak) => format!("峰值 {:.*}°C", precision, peak),
+        None => "".to_string(),
+    };
     charts.push(ChartContext {
         id: "cpu-temp-chart".to_string(),
         title: "温度".to_string(),
+        chart_type: ChartType::Area,
         datasets: vec![DatasetContext {
             name: "摄氏度".to_string(),
             line_color_code: TEMPERATURE_LINE_COLOR.to_string(),
-            fill_color_code: TEMPERATURE_FILL_COLOR.to_string(),
+            fill_color_code: with_opacity(TEMPERATURE_LINE_COLOR, config.chart_fill_opacity),
+            latest_value: temp_values.last().copied(),
             values: temp_values,
             fill: true,
+            band: false,
+            anomaly_indices: Vec::new(),
         }],
         x_label: "时间".to_string(),
-        y_label: "温度 (C)".to_string(),
+        y_label: axis_label("温度", Some(Unit::Celsius), false),
         x_values,
+        x_timestamps,
         min_y: 0.0,
         max_y: 85.0,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
         accompanying_text_1: temp_accompanying_text,
-        accompanying_text_2: "".to_string(),
+        accompanying_text_2: Some(temp_peak_text),
     });
 
     charts
 }
 
-/// 创建存储图表
+/// 创建按用途细分 CPU 时间的堆叠图表。如果没有任何条目有 CPU 时间细分，则返回 `None`。
 ///
 /// # 参数
-/// * `stats_history` - 历史统计信息
-fn build_memory_chart(stats_history: &StatsHistory) -> ChartContext {
-    let mut memory_values = Vec::new();
-    let mut memory_total_mb = 0;
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `config` - 仪表板的配置
+fn build_cpu_breakdown_chart(
+    entries: &[Option<&AllStats>],
+    config: &DashboardConfig,
+) -> Option<ChartContext> {
+    let precision = config.display_precision;
+    let mut user_values = Vec::new();
+    let mut nice_values = Vec::new();
+    let mut system_values = Vec::new();
+    let mut interrupt_values = Vec::new();
+    let mut iowait_values = Vec::new();
+    let mut steal_values = Vec::new();
+    let mut any_breakdown = false;
+    let mut any_iowait = false;
+    let mut any_steal = false;
     let mut x_values = Vec::new();
-    for stats in stats_history.into_iter() {
-        match &stats.memory {
+    let mut x_timestamps = Vec::new();
+    for entry in entries {
+        let stats = match entry {
+            Some(x) => x,
+            None => {
+                for values in [
+                    &mut user_values,
+                    &mut nice_values,
+                    &mut system_values,
+                    &mut interrupt_values,
+                    &mut iowait_values,
+                    &mut steal_values,
+                ] {
+                    values.push(f32::NAN);
+                }
+                x_values.push(String::new());
+                x_timestamps.push(None);
+                continue;
+            }
+        };
+        match &stats.cpu.load_breakdown {
             Some(x) => {
-                if x.total_mb > memory_total_mb {
-                    memory_total_mb = x.total_mb;
+                any_breakdown = true;
+                any_iowait |= x.iowait_percent.is_some();
+                any_steal |= x.steal_percent.is_some();
+                user_values.push(x.user_percent);
+                nice_values.push(x.nice_percent);
+                system_values.push(x.system_percent);
+                interrupt_values.push(x.interrupt_percent);
+                iowait_values.push(x.iowait_percent.unwrap_or(0.0));
+                steal_values.push(x.steal_percent.unwrap_or(0.0));
+            }
+            None => {
+                for values in [
+                    &mut user_values,
+                    &mut nice_values,
+                    &mut system_values,
+                    &mut interrupt_values,
+                    &mut iowait_values,
+                    &mut steal_values,
+                ] {
+                    values.push(0.0);
                 }
-                memory_values.push(x.used_mb as f32)
             }
-            None => memory_values.push(0.0),
         }
+
         x_values.push(format_time(stats.collection_time));
+        x_timestamps.push(Some(stats.collection_time.timestamp_millis()));
     }
 
-    let (accompanying_text_1, accompanying_text_2) = {
-        match stats_history.get_most_recent_stats() {
-            Some(x) => match &x.memory {
-                Some(mem) => {
-                    let used_pct = ((mem.used_mb as f64) / (mem.total_mb as f64)) * 100.0;
-                    (
-                        format!("{} / {} MB", mem.used_mb, mem.total_mb),
-                        format!("{:.2}%", used_pct),
-                    )
-                }
-                None => ("-- / -- MB".to_string(), "--%".to_string()),
+    if !any_breakdown {
+        return None;
+    }
+
+    let iowait_text = match iowait_values.last() {
+        Some(x) if any_iowait => format!("I/O 等待 {:.*}%", precision, x),
+        _ => "".to_string(),
+    };
+    let steal_text = match steal_values.last() {
+        Some(x) if any_steal => format!("steal {:.*}%", precision, x),
+        _ => "".to_string(),
+    };
+
+    let mut series = vec![
+        ("用户", CPU_USER_LINE_COLOR, user_values),
+        ("低优先级", CPU_NICE_LINE_COLOR, nice_values),
+        ("系统", CPU_SYSTEM_LINE_COLOR, system_values),
+        ("中断", CPU_INTERRUPT_LINE_COLOR, interrupt_values),
+    ];
+    if any_iowait {
+        series.push(("I/O 等待", CPU_IOWAIT_LINE_COLOR, iowait_values));
+    }
+    if any_steal {
+        series.push(("steal", CPU_STEAL_LINE_COLOR, steal_values));
+    }
+    let datasets = series
+        .into_iter()
+        .map(|(name, color_code, values)| DatasetContext {
+            name: name.to_string(),
+            line_color_code: color_code.to_string(),
+            fill_color_code: with_opacity(color_code, config.chart_fill_opacity),
+            latest_value: values.last().copied(),
+            values,
+            fill: true,
+            band: false,
+            anomaly_indices: Vec::new(),
+        })
+        .collect();
+
+    Some(ChartContext {
+        id: "cpu-breakdown-chart".to_string(),
+        title: "CPU 时间分布".to_string(),
+        chart_type: ChartType::Stacked,
+        datasets,
+        x_label: "时间".to_string(),
+        y_label: axis_label("占比", Some(Unit::Percent), false),
+        x_values,
+        x_timestamps,
+        min_y: 0.0,
+        max_y: 100.0,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
+        accompanying_text_1: iowait_text,
+        accompanying_text_2: Some(steal_text),
+    })
+}
+
+/// 创建显示 CPU 总负载在各个区间（0-10%、10-20% 等）内的样本占比的直方图。如果没有任何条目有 CPU 总负载，则返回 `None`。
+///
+/// # 参数
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `config` - 仪表板的配置
+fn build_cpu_histogram_chart(
+    entries: &[Option<&AllStats>],
+    config: &DashboardConfig,
+) -> Option<ChartContext> {
+    let bucket_count = 100 / CPU_HISTOGRAM_BUCKET_WIDTH_PERCENT;
+    let mut counts = vec![0_usize; bucket_count];
+    for load in entries
+        .iter()
+        .flatten()
+        .filter_map(|x| x.cpu.aggregate_load_percent)
+    {
+        // 100% 放在最后一个区间中
+        let index =
+            (load.max(0.0) as usize / CPU_HISTOGRAM_BUCKET_WIDTH_PERCENT).min(bucket_count - 1);
+        counts[index] += 1;
+    }
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+
+    let x_values: Vec<String> = (0..bucket_count)
+        .map(|i| {
+            format!(
+                "{}-{}%",
+                i * CPU_HISTOGRAM_BUCKET_WIDTH_PERCENT,
+                (i + 1) * CPU_HISTOGRAM_BUCKET_WIDTH_PERCENT
+            )
+        })
+        .collect();
+    let values: Vec<f32> = counts
+        .iter()
+        .map(|x| *x as f32 / total as f32 * 100.0)
+        .collect();
+    let most_common_text = match counts.iter().enumerate().max_by_key(|(_, x)| **x) {
+        Some((i, _)) => format!("最常见 {}", x_values[i]),
+        None => "".to_string(),
+    };
+
+    Some(ChartContext {
+        id: "cpu-histogram-chart".to_string(),
+        title: "CPU 负载分布".to_string(),
+        chart_type: ChartType::Bar,
+        datasets: vec![DatasetContext {
+            name: "样本占比".to_string(),
+            line_color_code: CPU_HISTOGRAM_BAR_COLOR.to_string(),
+            fill_color_code: with_opacity(CPU_HISTOGRAM_BAR_COLOR, config.chart_fill_opacity),
+            latest_value: None,
+            values,
+            fill: true,
+            band: false,
+            anomaly_indices: Vec::new(),
+        }],
+        x_label: axis_label("CPU 使用率", Some(Unit::Percent), false),
+        y_label: axis_label("样本占比", Some(Unit::Percent), false),
+        x_timestamps: vec![None; x_values.len()],
+        x_values,
+        min_y: 0.0,
+        max_y: 100.0,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
+        accompanying_text_1: most_common_text,
+        accompanying_text_2: Some(format!("{} 个样本", total)),
+    })
+}
+
+/// 创建显示最近的系统繁忙程度评分的仪表图表。如果没有可用的指标，则返回 `None`。
+///
+/// # 参数
+/// * `stats` - 最近收集的统计信息
+/// * `config` - 仪表板的配置
+fn build_busy_score_chart(stats: &AllStats, config: &DashboardConfig) -> Option<ChartContext> {
+    let precision = config.display_precision;
+    let busy_score = BusyScore::from(stats, &config.busy_score_weights);
+    let score = busy_score.score?;
+    let components_text = busy_score
+        .components
+        .iter()
+        .filter_map(|component| {
+            let name = match component.name {
+                "cpu" => "CPU",
+                "memory" => "内存",
+                _ => "I/O",
+            };
+            component
+                .value
+                .map(|value| format!("{} {:.*}%", name, precision, value))
+        })
+        .collect::<Vec<_>>()
+        .join(" / ");
+
+    Some(ChartContext {
+        id: "busy-score-chart".to_string(),
+        title: "繁忙程度".to_string(),
+        chart_type: ChartType::Gauge,
+        datasets: vec![DatasetContext {
+            name: "评分".to_string(),
+            line_color_code: BUSY_SCORE_COLOR.to_string(),
+            fill_color_code: with_opacity(BUSY_SCORE_COLOR, config.chart_fill_opacity),
+            latest_value: Some(score),
+            values: vec![score, 100.0 - score],
+            fill: true,
+            band: false,
+            anomaly_indices: Vec::new(),
+        }],
+        x_label: "".to_string(),
+        y_label: "".to_string(),
+        x_values: vec!["评分".to_string(), "".to_string()],
+        x_timestamps: vec![None, None],
+        min_y: 0.0,
+        max_y: 100.0,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
+        accompanying_text_1: format!("{:.*}", precision, score),
+        accompanying_text_2: Some(components_text),
+    })
+}
+
+/// 创建存储图表
+///
+/// # 参数
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `stats_history` - 历史统计信息
+/// * `config` - 仪表板的配置
+fn build_memory_chart(
+    entries: &[Option<&AllStats>],
+    stats_history: &StatsHistory,
+    config: &DashboardConfig,
+) -> ChartContext {
+    let precision = config.display_precision;
+    let mut memory_values = Vec::new();
+    let mut memory_min_values = Vec::new();
+    let mut memory_max_values = Vec::new();
+    let mut available_values = Vec::new();
+    let mut any_available = false;
+    let mut memory_total_bytes = 0;
+    let mut x_values = Vec::new();
+    let mut x_timestamps = Vec::new();
+    for entry in entries {
+        let stats = match entry {
+            Some(x) => x,
+            None => {
+                memory_values.push(f32::NAN);
+                memory_min_values.push(f32::NAN);
+                memory_max_values.push(f32::NAN);
+                available_values.push(f32::NAN);
+                x_values.push(String::new());
+                x_timestamps.push(None);
+                continue;
+            }
+        };
+        match &stats.memory {
+            Some(x) => {
+                if x.total_bytes > memory_total_bytes {
+                    memory_total_bytes = x.total_bytes;
+                }
+                let (min_used, max_used) = config.memory_metric.used_range_bytes(x);
+                memory_values.push(bytes_as_mb(config.memory_metric.used_bytes(x)) as f32);
+                memory_min_values.push(bytes_as_mb(min_used) as f32);
+                memory_max_values.push(bytes_as_mb(max_used) as f32);
+                match x.available_bytes {
+                    Some(available) => {
+                        any_available = true;
+                        available_values.push(bytes_as_mb(available) as f32);
+                    }
+                    None => available_values.push(0.0),
+                }
+            }
+            None => {
+                memory_values.push(0.0);
+                memory_min_values.push(0.0);
+                memory_max_values.push(0.0);
+                available_values.push(0.0);
+            }
+        }
+        x_values.push(format_time(stats.collection_time));
+        x_timestamps.push(Some(stats.collection_time.timestamp_millis()));
+    }
+
+    let (accompanying_text_1, accompanying_text_2) = {
+        match stats_history.get_most_recent_stats() {
+            Some(x) => match &x.memory {
+                Some(mem) => {
+                    let used_bytes = config.memory_metric.used_bytes(mem);
+                    let used_pct = ((used_bytes as f64) / (mem.total_bytes as f64)) * 100.0;
+                    (
+                        format!(
+                            "{} / {} MB",
+                            round_bytes_to_mb(used_bytes),
+                            round_bytes_to_mb(mem.total_bytes)
+                        ),
+                        format!("{:.*}%", precision, used_pct),
+                    )
+                }
+                None => ("-- / -- MB".to_string(), "--%".to_string()),
             },
             None => ("-- / -- MB".to_string(), "--%".to_string()),
         }
     };
 
+    let mut datasets = vec![DatasetContext {
+        name: "已用内存".to_string(),
+        line_color_code: MEM_LINE_COLOR.to_string(),
+        fill_color_code: with_opacity(MEM_LINE_COLOR, config.chart_fill_opacity),
+        latest_value: memory_values.last().copied(),
+        values: memory_values,
+        fill: true,
+        band: false,
+        anomaly_indices: Vec::new(),
+    }];
+    if config.min_max_bands {
+        datasets.extend(band_datasets(
+            memory_min_values,
+            memory_max_values,
+            &with_opacity(MEM_LINE_COLOR, MIN_MAX_BAND_OPACITY),
+        ));
+    }
+    // 只有在平台提供可用内存信息时才显示可用内存
+    if any_available {
+        datasets.push(DatasetContext {
+            name: "可用内存".to_string(),
+            line_color_code: MEM_AVAILABLE_LINE_COLOR.to_string(),
+            fill_color_code: with_opacity(MEM_AVAILABLE_LINE_COLOR, config.chart_fill_opacity),
+            latest_value: available_values.last().copied(),
+            values: available_values,
+            fill: false,
+            band: false,
+            anomaly_indices: Vec::new(),
+        });
+    }
+
     ChartContext {
         id: "ram-chart".to_string(),
         title: "内存使用量".to_string(),
-        datasets: vec![DatasetContext {
-            name: "已用内存".to_string(),
-            line_color_code: MEM_LINE_COLOR.to_string(),
-            fill_color_code: MEM_FILL_COLOR.to_string(),
-            values: memory_values,
-            fill: true,
-        }],
+        chart_type: ChartType::Area,
+        datasets,
         x_label: "时间".to_string(),
-        y_label: "使用量 (MB)".to_string(),
+        y_label: axis_label("使用量", Some(Unit::Megabytes), false),
         x_values,
+        x_timestamps,
         min_y: 0.0,
-        max_y: memory_total_mb as f32,
+        max_y: bytes_as_mb(memory_total_bytes) as f32,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
         accompanying_text_1,
-        accompanying_text_2,
+        accompanying_text_2: Some(accompanying_text_2),
     }
 }
 
 /// 创建负载图表
 ///
 /// # 参数
-/// * `stats_history` - 历史统计信息
-fn build_load_average_chart(stats_history: &StatsHistory) -> ChartContext {
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `config` - 仪表板的配置
+fn build_load_average_chart(
+    entries: &[Option<&AllStats>],
+    config: &DashboardConfig,
+) -> Option<ChartContext> {
+    let precision = config.display_precision;
     let mut one_min_values = Vec::new();
     let mut five_min_values = Vec::new();
     let mut fifteen_min_values = Vec::new();
     let mut x_values = Vec::new();
-    for stats in stats_history.into_iter() {
+    let mut x_timestamps = Vec::new();
+    for entry in entries {
+        let stats = match entry {
+            Some(x) => x,
+            None => {
+                one_min_values.push(f32::NAN);
+                five_min_values.push(f32::NAN);
+                fifteen_min_values.push(f32::NAN);
+                x_values.push(String::new());
+                x_timestamps.push(None);
+                continue;
+            }
+        };
         match &stats.general.load_averages {
             Some(x) => {
                 one_min_values.push(x.one_minute);
@@ -470,72 +1885,178 @@ fn build_load_average_chart(stats_history: &StatsHistory) -> ChartContext {
         }
 
         x_values.push(format_time(stats.collection_time));
+        x_timestamps.push(Some(stats.collection_time.timestamp_millis()));
     }
 
-    let accompanying_text = format!(
-        "1: {:.2}, 5: {:.2}, 15: {:.2}",
-        one_min_values.last().unwrap_or(&0.0),
-        five_min_values.last().unwrap_or(&0.0),
-        fifteen_min_values.last().unwrap_or(&0.0)
-    );
-    let datasets = vec![
-        DatasetContext {
-            name: "1 分钟".to_string(),
-            line_color_code: LOAD_AVERAGE_1_LINE_COLOR.to_string(),
-            fill_color_code: LOAD_AVERAGE_1_FILL_COLOR.to_string(),
-            values: one_min_values,
-            fill: false,
-        },
-        DatasetContext {
-            name: "5 分钟".to_string(),
-            line_color_code: LOAD_AVERAGE_5_LINE_COLOR.to_string(),
-            fill_color_code: LOAD_AVERAGE_5_FILL_COLOR.to_string(),
-            values: five_min_values,
-            fill: false,
-        },
-        DatasetContext {
-            name: "15 分钟".to_string(),
-            line_color_code: LOAD_AVERAGE_15_LINE_COLOR.to_string(),
-            fill_color_code: LOAD_AVERAGE_15_FILL_COLOR.to_string(),
-            values: fifteen_min_values,
+    let mut accompanying_texts = Vec::new();
+    let mut datasets = Vec::new();
+    for (window, values, color_code) in [
+        (
+            LoadAverageWindow::OneMinute,
+            one_min_values,
+            LOAD_AVERAGE_1_LINE_COLOR,
+        ),
+        (
+            LoadAverageWindow::FiveMinutes,
+            five_min_values,
+            LOAD_AVERAGE_5_LINE_COLOR,
+        ),
+        (
+            LoadAverageWindow::FifteenMinutes,
+            fifteen_min_values,
+            LOAD_AVERAGE_15_LINE_COLOR,
+        ),
+    ] {
+        if !config.load_average_windows.contains(&window) {
+            continue;
+        }
+
+        accompanying_texts.push(format!(
+            "{}: {:.*}",
+            window.minutes(),
+            precision,
+            values.last().unwrap_or(&0.0)
+        ));
+        datasets.push(DatasetContext {
+            name: format!("{} 分钟", window.minutes()),
+            line_color_code: color_code.to_string(),
+            fill_color_code: with_opacity(color_code, config.chart_fill_opacity),
+            latest_value: values.last().copied(),
+            values,
             fill: false,
-        },
-    ];
+            band: false,
+            anomaly_indices: Vec::new(),
+        });
+    }
+    if datasets.is_empty() {
+        return None;
+    }
 
-    ChartContext {
+    Some(ChartContext {
         id: "load-average-chart".to_string(),
         title: "平均负载".to_string(),
+        chart_type: ChartType::Line,
         datasets,
         x_label: "时间".to_string(),
-        y_label: "平均负载".to_string(),
+        y_label: axis_label("平均负载", None, false),
         x_values,
+        x_timestamps,
         min_y: 0.0,
         max_y: 0.0,
-        accompanying_text_1: accompanying_text,
-        accompanying_text_2: "".to_string(),
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
+        accompanying_text_1: accompanying_texts.join(", "),
+        accompanying_text_2: Some("".to_string()),
+    })
+}
+
+/// 创建进程数量图表。如果平台不提供进程数量，则返回 `None`。
+///
+/// # 参数
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `config` - 仪表板的配置
+fn build_process_count_chart(
+    entries: &[Option<&AllStats>],
+    config: &DashboardConfig,
+) -> Option<ChartContext> {
+    let mut process_count_values = Vec::new();
+    let mut any_process_count = false;
+    let mut x_values = Vec::new();
+    let mut x_timestamps = Vec::new();
+    for entry in entries {
+        let stats = match entry {
+            Some(x) => x,
+            None => {
+                process_count_values.push(f32::NAN);
+                x_values.push(String::new());
+                x_timestamps.push(None);
+                continue;
+            }
+        };
+        match stats.general.process_count {
+            Some(x) => {
+                any_process_count = true;
+                process_count_values.push(x as f32);
+            }
+            None => process_count_values.push(0.0),
+        }
+
+        x_values.push(format_time(stats.collection_time));
+        x_timestamps.push(Some(stats.collection_time.timestamp_millis()));
+    }
+
+    if !any_process_count {
+        return None;
     }
+
+    let accompanying_text = format!("{} 个进程", process_count_values.last().unwrap_or(&0.0));
+    Some(ChartContext {
+        id: "process-count-chart".to_string(),
+        title: "进程数量".to_string(),
+        chart_type: ChartType::Area,
+        datasets: vec![DatasetContext {
+            name: "进程".to_string(),
+            line_color_code: PROCESS_COUNT_LINE_COLOR.to_string(),
+            fill_color_code: with_opacity(PROCESS_COUNT_LINE_COLOR, config.chart_fill_opacity),
+            latest_value: process_count_values.last().copied(),
+            values: process_count_values,
+            fill: true,
+            band: false,
+            anomaly_indices: Vec::new(),
+        }],
+        x_label: "时间".to_string(),
+        y_label: axis_label("进程数", None, false),
+        x_values,
+        x_timestamps,
+        min_y: 0.0,
+        max_y: 0.0,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
+        accompanying_text_1: accompanying_text,
+        accompanying_text_2: Some("".to_string()),
+    })
 }
 
 /// 创建网络图表
 ///
 /// # 参数
-/// * `stats_history` - 历史统计信息
-fn build_network_charts(stats_history: &StatsHistory) -> Vec<ChartContext> {
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `config` - 仪表板的配置
+fn build_network_charts(
+    entries: &[Option<&AllStats>],
+    config: &DashboardConfig,
+) -> Vec<ChartContext> {
     let mut sent_mb_values = Vec::new();
     let mut received_mb_values = Vec::new();
     let mut send_errors_values = Vec::new();
     let mut receive_errors_values = Vec::new();
     let mut tcp_sockets_values = Vec::new();
     let mut udp_sockets_values = Vec::new();
+    let mut any_sockets = false;
     let mut x_values = Vec::new();
-    for stats in stats_history.into_iter() {
+    let mut x_timestamps = Vec::new();
+    for entry in entries {
+        let stats = match entry {
+            Some(x) => x,
+            None => {
+                sent_mb_values.push(f32::NAN);
+                received_mb_values.push(f32::NAN);
+                send_errors_values.push(f32::NAN);
+                receive_errors_values.push(f32::NAN);
+                tcp_sockets_values.push(f32::NAN);
+                udp_sockets_values.push(f32::NAN);
+                x_values.push(String::new());
+                x_timestamps.push(None);
+                continue;
+            }
+        };
         match &stats.network.interfaces {
             Some(x) => {
                 let mut total_sent_mb = 0.0;
                 let mut total_received_mb = 0.0;
                 let mut total_send_errors = 0.0;
                 let mut total_receive_errors = 0.0;
-                for interface_stats in x {
+                for interface_stats in x.iter().filter(|x| config.include_loopback || !x.loopback) {
                     total_sent_mb += interface_stats.sent_mb as f32;
                     total_received_mb += interface_stats.received_mb as f32;
                     total_send_errors += interface_stats.send_errors as f32;
@@ -557,6 +2078,7 @@ fn build_network_charts(stats_history: &StatsHistory) -> Vec<ChartContext> {
 
         match &stats.network.sockets {
             Some(x) => {
+                any_sockets = true;
                 tcp_sockets_values.push(x.tcp_in_use as f32);
                 udp_sockets_values.push(x.udp_in_use as f32);
             }
@@ -567,44 +2089,69 @@ fn build_network_charts(stats_history: &StatsHistory) -> Vec<ChartContext> {
         }
 
         x_values.push(format_time(stats.collection_time));
+        x_timestamps.push(Some(stats.collection_time.timestamp_millis()));
     }
 
     let mut charts = Vec::new();
 
-    let usage_accompanying_text = format!(
-        "{} MB sent, {} MB received",
-        sent_mb_values.last().unwrap_or(&0.0),
-        received_mb_values.last().unwrap_or(&0.0)
-    );
-    let usage_datasets = vec![
-        DatasetContext {
-            name: "发送".to_string(),
-            line_color_code: SENT_LINE_COLOR.to_string(),
-            fill_color_code: SENT_FILL_COLOR.to_string(),
-            values: sent_mb_values,
-            fill: false,
-        },
-        DatasetContext {
-            name: "接收".to_string(),
-            line_color_code: RECEIVED_LINE_COLOR.to_string(),
-            fill_color_code: RECEIVED_FILL_COLOR.to_string(),
-            values: received_mb_values,
-            fill: false,
-        },
-    ];
+    if config.network_per_interface_charts {
+        charts.extend(build_interface_usage_charts(
+            entries,
+            &x_values,
+            &x_timestamps,
+            config,
+        ));
+    } else {
+        let usage_accompanying_text = format!(
+            "{} MB sent, {} MB received",
+            sent_mb_values.last().unwrap_or(&0.0),
+            received_mb_values.last().unwrap_or(&0.0)
+        );
+        let usage_total_text = network_usage_total_text(
+            sent_mb_values.last().copied(),
+            received_mb_values.last().copied(),
+            config,
+        );
+        let usage_datasets = vec![
+            DatasetContext {
+                name: "发送".to_string(),
+                line_color_code: SENT_LINE_COLOR.to_string(),
+                fill_color_code: with_opacity(SENT_LINE_COLOR, config.chart_fill_opacity),
+                latest_value: sent_mb_values.last().copied(),
+                values: sent_mb_values,
+                fill: config.network_stacked,
+                band: false,
+                anomaly_indices: Vec::new(),
+            },
+            DatasetContext {
+                name: "接收".to_string(),
+                line_color_code: RECEIVED_LINE_COLOR.to_string(),
+                fill_color_code: with_opacity(RECEIVED_LINE_COLOR, config.chart_fill_opacity),
+                latest_value: received_mb_values.last().copied(),
+                values: received_mb_values,
+                fill: config.network_stacked,
+                band: false,
+                anomaly_indices: Vec::new(),
+            },
+        ];
 
-    charts.push(ChartContext {
-        id: "network-usage-chart".to_string(),
-        title: "累积网络使用量".to_string(),
-        datasets: usage_datasets,
-        x_label: "时间".to_string(),
-        y_label: "总计 (MB)".to_string(),
-        x_values: x_values.clone(),
-        min_y: 0.0,
-        max_y: 0.0,
-        accompanying_text_1: usage_accompanying_text,
-        accompanying_text_2: "".to_string(),
-    });
+        charts.push(ChartContext {
+            id: "network-usage-chart".to_string(),
+            title: "累积网络使用量".to_string(),
+            chart_type: network_usage_chart_type(config),
+            datasets: usage_datasets,
+            x_label: "时间".to_string(),
+            y_label: axis_label("总计", Some(Unit::Megabytes), false),
+            x_values: x_values.clone(),
+            x_timestamps: x_timestamps.clone(),
+            min_y: 0.0,
+            max_y: 0.0,
+            y_tick_count: config.y_tick_count,
+            annotations: Vec::new(),
+            accompanying_text_1: usage_accompanying_text,
+            accompanying_text_2: Some(usage_total_text),
+        });
+    }
 
     let errors_accompanying_text = format!(
         "{} 已发送, {} 已接收",
@@ -615,68 +2162,488 @@ fn build_network_charts(stats_history: &StatsHistory) -> Vec<ChartContext> {
         DatasetContext {
             name: "发送".to_string(),
             line_color_code: SEND_ERRORS_LINE_COLOR.to_string(),
-            fill_color_code: SEND_ERRORS_FILL_COLOR.to_string(),
+            fill_color_code: with_opacity(SEND_ERRORS_LINE_COLOR, config.chart_fill_opacity),
+            latest_value: send_errors_values.last().copied(),
             values: send_errors_values,
             fill: false,
+            band: false,
+            anomaly_indices: Vec::new(),
         },
         DatasetContext {
             name: "Receive".to_string(),
             line_color_code: RECEIVE_ERRORS_LINE_COLOR.to_string(),
-            fill_color_code: RECEIVE_ERRORS_FILL_COLOR.to_string(),
+            fill_color_code: with_opacity(RECEIVE_ERRORS_LINE_COLOR, config.chart_fill_opacity),
+            latest_value: receive_errors_values.last().copied(),
             values: receive_errors_values,
             fill: false,
+            band: false,
+            anomaly_indices: Vec::new(),
         },
     ];
 
     charts.push(ChartContext {
         id: "network-errors-chart".to_string(),
         title: "累积网络错误".to_string(),
+        chart_type: ChartType::Line,
         datasets: errors_datasets,
         x_label: "时间".to_string(),
-        y_label: "总错误".to_string(),
+        y_label: axis_label("总错误", None, false),
         x_values: x_values.clone(),
+        x_timestamps: x_timestamps.clone(),
         min_y: 0.0,
         max_y: 0.0,
+        y_tick_count: config.y_tick_count,
+        annotations: Vec::new(),
         accompanying_text_1: errors_accompanying_text,
-        accompanying_text_2: "".to_string(),
+        accompanying_text_2: Some("".to_string()),
     });
 
-    let sockets_accompanying_text = format!(
-        "{} TCP, {} UDP",
-        tcp_sockets_values.last().unwrap_or(&0.0),
-        udp_sockets_values.last().unwrap_or(&0.0)
-    );
-    let sockets_datasets = vec![
+    // 禁用套接字收集时不显示套接字图表
+    if any_sockets {
+        let sockets_accompanying_text = format!(
+            "{} TCP, {} UDP",
+            tcp_sockets_values.last().unwrap_or(&0.0),
+            udp_sockets_values.last().unwrap_or(&0.0)
+        );
+        let sockets_datasets = vec![
+            DatasetContext {
+                name: "TCP".to_string(),
+                line_color_code: TCP_LINE_COLOR.to_string(),
+                fill_color_code: with_opacity(TCP_LINE_COLOR, config.chart_fill_opacity),
+                latest_value: tcp_sockets_values.last().copied(),
+                values: tcp_sockets_values,
+                fill: false,
+                band: false,
+                anomaly_indices: Vec::new(),
+            },
+            DatasetContext {
+                name: "UDP".to_string(),
+                line_color_code: UDP_LINE_COLOR.to_string(),
+                fill_color_code: with_opacity(UDP_LINE_COLOR, config.chart_fill_opacity),
+                latest_value: udp_sockets_values.last().copied(),
+                values: udp_sockets_values,
+                fill: false,
+                band: false,
+                anomaly_indices: Vec::new(),
+            },
+        ];
+
+        charts.push(ChartContext {
+            id: "sockets-chart".to_string(),
+            title: "套接字使用量".to_string(),
+            chart_type: ChartType::Line,
+            datasets: sockets_datasets,
+            x_label: "时间".to_string(),
+            y_label: axis_label("使用量", None, false),
+            x_values,
+            x_timestamps,
+            min_y: 0.0,
+            max_y: 0.0,
+            y_tick_count: config.y_tick_count,
+            annotations: Vec::new(),
+            accompanying_text_1: sockets_accompanying_text,
+            accompanying_text_2: Some("".to_string()),
+        });
+    }
+
+    charts
+}
+
+/// 为每个网络接口创建累积使用量图表。最繁忙（当前吞吐量最高）的接口排在最前面。
+///
+/// # 参数
+/// * `entries` - 历史统计信息，`None` 表示数据中断
+/// * `x_values` - X 轴上标记的名称
+/// * `x_timestamps` - 每个 X 轴标记对应的收集时间，以毫秒为单位
+/// * `config` - 仪表板的配置
+fn build_interface_usage_charts(
+    entries: &[Option<&AllStats>],
+    x_values: &[String],
+    x_timestamps: &[Option<i64>],
+    config: &DashboardConfig,
+) -> Vec<ChartContext> {
+    let mut latest_interfaces: Vec<&NetworkInterfaceStats> = match entries
+        .iter()
+        .rev()
+        .find_map(|entry| entry.and_then(|stats| stats.network.interfaces.as_ref()))
+    {
+        Some(x) => x
+            .iter()
+            .filter(|x| config.include_loopback || !x.loopback)
+            .collect(),
+        None => return Vec::new(),
+    };
+    let throughput = |interface: &NetworkInterfaceStats| {
+        interface.sent_mbps.unwrap_or(0.0) + interface.received_mbps.unwrap_or(0.0)
+    };
+    latest_interfaces.sort_by(|a, b| throughput(b).total_cmp(&throughput(a)));
+    latest_interfaces.truncate(config.max_interface_charts);
+
+    let mut charts = Vec::new();
+    for latest in latest_interfaces {
+        let mut sent_mb_values = Vec::new();
+        let mut received_mb_values = Vec::new();
+        for entry in entries {
+            let interface = entry
+                .and_then(|stats| stats.network.interfaces.as_ref())
+                .and_then(|x| x.iter().find(|interface| interface.name == latest.name));
+            match interface {
+                Some(x) => {
+                    sent_mb_values.push(x.sent_mb as f32);
+                    received_mb_values.push(x.received_mb as f32);
+                }
+                None => {
+                    sent_mb_values.push(f32::NAN);
+                    received_mb_values.push(f32::NAN);
+                }
+            }
+        }
+
+        let accompanying_text = format!(
+            "{} MB sent, {} MB received",
+            latest.sent_mb, latest.received_mb
+        );
+        let total_text = network_usage_total_text(
+            Some(latest.sent_mb as f32),
+            Some(latest.received_mb as f32),
+            config,
+        );
+        charts.push(ChartContext {
+            id: format!("network-usage-chart-{}", latest.name),
+            title: format!("{} 累积网络使用量", config.interface_label(&latest.name)),
+            chart_type: network_usage_chart_type(config),
+            datasets: vec![
+                DatasetContext {
+                    name: "发送".to_string(),
+                    line_color_code: SENT_LINE_COLOR.to_string(),
+                    fill_color_code: with_opacity(SENT_LINE_COLOR, config.chart_fill_opacity),
+                    latest_value: sent_mb_values.last().copied(),
+                    values: sent_mb_values,
+                    fill: config.network_stacked,
+                    band: false,
+                    anomaly_indices: Vec::new(),
+                },
+                DatasetContext {
+                    name: "接收".to_string(),
+                    line_color_code: RECEIVED_LINE_COLOR.to_string(),
+                    fill_color_code: with_opacity(RECEIVED_LINE_COLOR, config.chart_fill_opacity),
+                    latest_value: received_mb_values.last().copied(),
+                    values: received_mb_values,
+                    fill: config.network_stacked,
+                    band: false,
+                    anomaly_indices: Vec::new(),
+                },
+            ],
+            x_label: "时间".to_string(),
+            y_label: axis_label("总计", Some(Unit::Megabytes), false),
+            x_values: x_values.to_vec(),
+            x_timestamps: x_timestamps.to_vec(),
+            min_y: 0.0,
+            max_y: 0.0,
+            y_tick_count: config.y_tick_count,
+            annotations: Vec::new(),
+            accompanying_text_1: accompanying_text,
+            accompanying_text_2: Some(total_text),
+        });
+    }
+
+    charts
+}
+
+/// 网络使用量图表的类型。堆叠显示时接收量叠加在发送量之上，最上面的线就是总使用量。
+///
+/// # 参数
+/// * `config` - 仪表板的配置
+fn network_usage_chart_type(config: &DashboardConfig) -> ChartType {
+    if config.network_stacked {
+        ChartType::Stacked
+    } else {
+        ChartType::Line
+    }
+}
+
+/// 堆叠显示网络使用量时，在图表旁边显示的总使用量。不堆叠时为空。
+///
+/// # 参数
+/// * `sent_mb` - 最近的发送量，以MB为单位
+/// * `received_mb` - 最近的接收量，以MB为单位
+/// * `config` - 仪表板的配置
+fn network_usage_total_text(
+    sent_mb: Option<f32>,
+    received_mb: Option<f32>,
+    config: &DashboardConfig,
+) -> String {
+    if !config.network_stacked {
+        return "".to_string();
+    }
+    match (sent_mb, received_mb) {
+        (Some(sent), Some(received)) if !sent.is_nan() && !received.is_nan() => {
+            format!("合计 {} MB", sent + received)
+        }
+        _ => "合计 -- MB".to_string(),
+    }
+}
+
+/// 创建显示最小值和最大值之间的区间的两个数据集。上边界在前，并填充到下边界。
+///
+/// # 参数
+/// * `min_values` - 每个条目的最小值
+/// * `max_values` - 每个条目的最大值
+/// * `color_code` - 区间的颜色代码
+fn band_datasets(
+    min_values: Vec<f32>,
+    max_values: Vec<f32>,
+    color_code: &str,
+) -> [DatasetContext; 2] {
+    [
         DatasetContext {
-            name: "TCP".to_string(),
-            line_color_code: TCP_LINE_COLOR.to_string(),
-            fill_color_code: TCP_FILL_COLOR.to_string(),
-            values: tcp_sockets_values,
-            fill: false,
+            name: "最大值".to_string(),
+            line_color_code: color_code.to_string(),
+            fill_color_code: color_code.to_string(),
+            latest_value: max_values.last().copied(),
+            values: max_values,
+            fill: true,
+            band: true,
+            anomaly_indices: Vec::new(),
         },
         DatasetContext {
-            name: "UDP".to_string(),
-            line_color_code: UDP_LINE_COLOR.to_string(),
-            fill_color_code: UDP_FILL_COLOR.to_string(),
-            values: udp_sockets_values,
+            name: "最小值".to_string(),
+            line_color_code: color_code.to_string(),
+            fill_color_code: color_code.to_string(),
+            latest_value: min_values.last().copied(),
+            values: min_values,
             fill: false,
+            band: true,
+            anomaly_indices: Vec::new(),
         },
-    ];
+    ]
+}
 
-    charts.push(ChartContext {
-        id: "sockets-chart".to_string(),
-        title: "套接字使用量".to_string(),
-        datasets: sockets_datasets,
-        x_label: "时间".to_string(),
-        y_label: "使用量".to_string(),
-        x_values,
-        min_y: 0.0,
-        max_y: 0.0,
-        accompanying_text_1: sockets_accompanying_text,
-        accompanying_text_2: "".to_string(),
+/// 为紧凑的上下文量化图表中的值：绝对值不小于 `COMPACT_INTEGER_THRESHOLD` 的值四舍五入为整数，其他值保留一位小数。
+/// `NaN`（数据中断）保持不变。
+///
+/// # 参数
+/// * `value` - 要量化的值
+fn quantize(value: f32) -> f32 {
+    if value.abs() >= COMPACT_INTEGER_THRESHOLD {
+        value.round()
+    } else {
+        (value * 10.0).round() / 10.0
+    }
+}
+
+/// 为 `#rrggbb` 形式的颜色代码加上不透明度，得到 `#rrggbbaa` 形式的颜色代码。
+///
+/// # 参数
+/// * `color_code` - 不带不透明度的颜色代码
+/// * `opacity` - 不透明度，从 0.0（完全透明）到 1.0（完全不透明）
+fn with_opacity(color_code: &str, opacity: f32) -> String {
+    let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("{}{:02x}", color_code, alpha)
+}
+
+/// 按照 `chart_order` 中的顺序排列图表。不在 `chart_order` 中的图表保持原来的相对顺序，排在最后。
+///
+/// # 参数
+/// * `charts` - 要排序的图表
+/// * `chart_order` - 优先显示的图表的 ID
+fn sort_charts(charts: &mut [ChartContext], chart_order: &[String]) {
+    // 排序是稳定的，因此未指定的图表保持默认顺序
+    charts.sort_by_key(|chart| {
+        chart_order
+            .iter()
+            .position(|id| *id == chart.id)
+            .unwrap_or(chart_order.len())
     });
+}
 
-    charts
+/// 根据 `time_label_dates` 在图表的时间标签前添加日期，以便区分跨越午夜的历史中不同日期的相同时间。
+/// 日期按本地时区划分。没有时间的标签（例如数据中断处和柱状图的类别）保持不变。
+///
+/// # 参数
+/// * `charts` - 要添加日期的图表
+/// * `time_label_dates` - 何时在标签中包含日期
+fn add_dates_to_time_labels(charts: &mut [ChartContext], time_label_dates: TimeLabelDates) {
+    for chart in charts {
+        let dates: Vec<Option<NaiveDate>> = chart
+            .x_timestamps
+            .iter()
+            .map(|x| {
+                x.and_then(|millis| Local.timestamp_millis_opt(millis).single())
+                    .map(|time| time.naive_local().date())
+            })
+            .collect();
+        let spans_multiple_days = dates.iter().flatten().min() != dates.iter().flatten().max();
+        if time_label_dates == TimeLabelDates::DayBoundaries && !spans_multiple_days {
+            continue;
+        }
+
+        let mut previous_date = None;
+        for (label, date) in chart.x_values.iter_mut().zip(dates) {
+            let date = match date {
+                Some(x) => x,
+                None => continue,
+            };
+            if time_label_dates == TimeLabelDates::Always || previous_date != Some(date) {
+                *label = format!("{} {}", date.format(DATE_LABEL_FORMAT), label);
+            }
+            previous_date = Some(date);
+        }
+    }
+}
+
+/// 在所有图表的数据集中标出异常值
+///
+/// # 参数
+/// * `charts` - 要标出异常值的图表
+fn mark_anomalies(charts: &mut [ChartContext]) {
+    for dataset in charts
+        .iter_mut()
+        // 柱状图和仪表的值不是时间序列，检测异常没有意义
+        .filter(|chart| !matches!(chart.chart_type, ChartType::Bar | ChartType::Gauge))
+        .flat_map(|chart| chart.datasets.iter_mut())
+        // 区间的边界只是平均值的补充，不单独标出异常值
+        .filter(|dataset| !dataset.band)
+    {
+        dataset.anomaly_indices = find_anomalies(&dataset.values);
+    }
+}
+
+/// 查找与前面的值的滚动平均值相差超过 `ANOMALY_Z_SCORE_THRESHOLD` 个标准差的值。
+///
+/// 每个值只与它前面最多 `ANOMALY_WINDOW_SIZE` 个值比较。数据中断（`NaN`）会被跳过，前面的有效值少于 `ANOMALY_MIN_POINTS` 个的值不做检测。
+///
+/// # 参数
+/// * `values` - 要检测的值
+fn find_anomalies(values: &[f32]) -> Vec<usize> {
+    let mut anomalies = Vec::new();
+    let mut window = VecDeque::with_capacity(ANOMALY_WINDOW_SIZE);
+    for (i, &value) in values.iter().enumerate() {
+        if value.is_nan() {
+            continue;
+        }
+
+        if window.len() >= ANOMALY_MIN_POINTS {
+            let n = window.len() as f32;
+            let mean = window.iter().sum::<f32>() / n;
+            let variance = window.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / n;
+            let std_dev = variance.sqrt();
+            // 前面的值完全相同时无法计算 z 分数
+            if std_dev > 0.0 && (value - mean).abs() / std_dev > ANOMALY_Z_SCORE_THRESHOLD {
+                anomalies.push(i);
+            }
+        }
+
+        if window.len() == ANOMALY_WINDOW_SIZE {
+            window.pop_front();
+        }
+        window.push_back(value);
+    }
+
+    anomalies
+}
+
+/// 按照每个图表的平滑窗口平滑图表中的数据集。在检测异常值之后进行，因此异常值仍然根据原始值检测。
+///
+/// # 参数
+/// * `charts` - 要平滑的图表
+/// * `config` - 仪表板的配置
+fn smooth_charts(charts: &mut [ChartContext], config: &DashboardConfig) {
+    for chart in charts
+        .iter_mut()
+        // 柱状图和仪表的值不是时间序列，平滑没有意义
+        .filter(|chart| !matches!(chart.chart_type, ChartType::Bar | ChartType::Gauge))
+    {
+        let window = config
+            .chart_smoothing_windows
+            .get(&chart.id)
+            .copied()
+            .unwrap_or(config.chart_smoothing_window);
+        if window <= 1 {
+            continue;
+        }
+        for dataset in &mut chart.datasets {
+            dataset.values = smoothed(&dataset.values, window);
+            dataset.latest_value = dataset.values.last().copied();
+        }
+    }
+}
+
+/// 计算每个值与它前面最多 `window - 1` 个值的平均值。数据中断（`NaN`）保持不变，并且中断前的值不参与中断后的平均值。
+///
+/// # 参数
+/// * `values` - 要平滑的值
+/// * `window` - 每个平均值最多包含的值的数量
+fn smoothed(values: &[f32], window: usize) -> Vec<f32> {
+    let mut smoothed = Vec::with_capacity(values.len());
+    let mut recent = VecDeque::with_capacity(window.min(values.len()));
+    // `recent` 中的值之和。使用 f64 累加，以免长时间加减后误差累积
+    let mut sum = 0.0_f64;
+    for &value in values {
+        if value.is_nan() {
+            recent.clear();
+            sum = 0.0;
+            smoothed.push(value);
+            continue;
+        }
+
+        if recent.len() == window {
+            if let Some(oldest) = recent.pop_front() {
+                sum -= f64::from(oldest);
+            }
+        }
+        recent.push_back(value);
+        sum += f64::from(value);
+        smoothed.push((sum / recent.len() as f64) as f32);
+    }
+
+    smoothed
+}
+
+/// 获取统计历史中的所有条目，并在相邻两个条目之间的时间远超预期间隔处插入 `None` 以表示数据中断。
+/// 持久化过滤条件跳过了之前的条目的条目（`after_filtered_entries`）之前不插入 `None`。
+///
+/// # 参数
+/// * `stats_history` - 历史统计信息
+/// * `config` - 仪表板的配置，包含相邻两个条目之间的预期间隔
+fn entries_with_gaps<'a>(
+    stats_history: &'a StatsHistory,
+    config: &DashboardConfig,
+) -> Vec<Option<&'a AllStats>> {
+    let mut entries = Vec::new();
+    let mut previous_time = None;
+    for stats in stats_history.into_iter() {
+        if let Some(previous_time) = previous_time {
+            let expected_interval = config
+                .expected_interval_at(previous_time)
+                .max(config.expected_interval_at(stats.collection_time));
+            let max_gap = chrono::Duration::from_std(expected_interval * GAP_INTERVAL_MULTIPLIER)
+                .unwrap_or_else(|_| chrono::Duration::max_value());
+            // 持久化过滤条件跳过的条目不是数据中断
+            if stats.collection_time - previous_time > max_gap && !stats.after_filtered_entries {
+                entries.push(None);
+            }
+        }
+        previous_time = Some(stats.collection_time);
+        entries.push(Some(stats));
+    }
+
+    entries
+}
+
+/// 构建坐标轴的标签，例如 `总计 (MB)` 或 `速率 (MB/s)`。
+///
+/// # 参数
+/// * `name` - 坐标轴表示的量的名称
+/// * `unit` - 量的单位。如果为 `None`，标签中不显示单位
+/// * `per_second` - 是否为每秒的速率
+fn axis_label(name: &str, unit: Option<Unit>, per_second: bool) -> String {
+    let suffix = if per_second { "/s" } else { "" };
+    match unit {
+        Some(unit) => format!("{} ({}{})", name, unit.symbol(), suffix),
+        None if per_second => format!("{} (每秒)", name),
+        None => name.to_string(),
+    }
 }
 
 /// 格式化时间
@@ -686,3 +2653,164 @@ fn build_network_charts(stats_history: &StatsHistory) -> Vec<ChartContext> {
 fn format_time(time: DateTime<Local>) -> String {
     time.format("%I:%M:%S %p").to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{Collector, CollectorConfig, MountCollection};
+    use crate::system_source::fixed::FixedSystem;
+
+    /// 创建使用默认值的仪表板配置
+    fn test_config() -> DashboardConfig {
+        DashboardConfig {
+            expected_sample_interval: Duration::from_secs(60),
+            quiet_hours: None,
+            display_precision: 2,
+            network_per_interface_charts: false,
+            network_stacked: false,
+            max_interface_charts: 8,
+            include_loopback: false,
+            interface_aliases: BTreeMap::new(),
+            anomaly_detection: false,
+            chart_order: Vec::new(),
+            chart_smoothing_window: 1,
+            chart_smoothing_windows: BTreeMap::new(),
+            history_page_order: HistoryPageOrder::Oldest,
+            min_max_bands: false,
+            base_path: String::new(),
+            chart_fill_opacity: 0.2,
+            filesystem_warning_percent: 80.0,
+            filesystem_critical_percent: 90.0,
+            filesystem_unit_scaling: false,
+            entropy_available_min: 0,
+            stuck_metric_entries: 0,
+            y_tick_count: 5,
+            fleet_aggregation: FleetAggregation::Average,
+            cpu_histogram: false,
+            busy_score_weights: BusyScoreWeights::default(),
+            time_label_dates: TimeLabelDates::DayBoundaries,
+            load_average_windows: Vec::new(),
+            memory_metric: MemoryMetric::TotalMinusAvailable,
+            compact: false,
+        }
+    }
+
+    /// 创建收集时间为 `start` 之后提供的秒数的统计历史
+    fn history_at(start: DateTime<Local>, offsets_secs: &[i64]) -> StatsHistory {
+        let collectors = CollectorConfig {
+            disabled_collectors: vec![Collector::Mounts],
+            mount_collection_timeout: Duration::from_secs(1),
+            log_unsupported_once: true,
+            concurrent_collection: false,
+        };
+        let stats = AllStats::from(
+            &FixedSystem::default(),
+            Duration::ZERO,
+            &collectors,
+            &MountCollection::default(),
+        );
+        StatsHistory::from_stats(
+            offsets_secs
+                .iter()
+                .map(|x| AllStats {
+                    collection_time: start + chrono::Duration::seconds(*x),
+                    ..stats.clone()
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn collecting_history_with_one_entry_has_no_charts() {
+        let history = history_at(Local::now(), &[0]);
+        let config = test_config();
+
+        let context = DashboardContext::from_history(&history, None, &config, true);
+        assert!(context.charts.is_empty());
+        assert_eq!(context.sections[0].name, "正在收集数据…");
+
+        // 不再增加条目的历史仍然显示图表
+        let context = DashboardContext::from_history(&history, None, &config, false);
+        assert!(!context.charts.is_empty());
+        assert!(context.sections.iter().all(|x| x.name != "正在收集数据…"));
+    }
+
+    #[test]
+    fn empty_filesystems_have_zero_usage() {
+        let mount = MountStats {
+            fs_type: "proc".to_string(),
+            mounted_from: "proc".to_string(),
+            mounted_on: "/proc".to_string(),
+            used_mb: 0,
+            total_mb: 0,
+            read_only: false,
+            mount_options: None,
+        };
+        let section = build_filesystems_section(&[mount], &test_config());
+        assert_eq!(section.stats, ["总使用量: 0 / 0 MB (0.00%)"]);
+        assert_eq!(section.subsections[0].stats[2], "使用量: 0 / 0 MB (0.00%)");
+        assert!(matches!(section.severity, Severity::Normal));
+    }
+
+    #[test]
+    fn storage_usage_unit_depends_on_total() {
+        let mut config = test_config();
+        config.display_precision = 1;
+        config.filesystem_unit_scaling = true;
+        assert_eq!("512 / 999 MB", format_storage_usage(512, 999, &config));
+        assert_eq!("0.5 / 1.0 GB", format_storage_usage(500, 1000, &config));
+        assert_eq!(
+            "999.0 / 999.9 GB",
+            format_storage_usage(999_000, 999_900, &config)
+        );
+        assert_eq!(
+            "0.9 / 1.0 TB",
+            format_storage_usage(900_000, 1_000_000, &config)
+        );
+        assert_eq!(
+            "1.9 / 4.0 TB",
+            format_storage_usage(1_900_000, 4_000_000, &config)
+        );
+
+        // 不缩放单位时总是以 MB 显示
+        config.filesystem_unit_scaling = false;
+        assert_eq!(
+            "1900000 / 4000000 MB",
+            format_storage_usage(1_900_000, 4_000_000, &config)
+        );
+    }
+
+    #[test]
+    fn smoothing_restarts_after_gaps() {
+        let values = [1.0, 3.0, 5.0, f32::NAN, 2.0, 4.0];
+        let smoothed = smoothed(&values, 2);
+        assert_eq!(smoothed[..3], [1.0, 2.0, 4.0]);
+        assert!(smoothed[3].is_nan());
+        // 中断前的值不参与中断后的平均值
+        assert_eq!(smoothed[4..], [2.0, 3.0]);
+    }
+
+    #[test]
+    fn smoothing_window_can_be_larger_than_values() {
+        assert_eq!(smoothed(&[2.0, 4.0, 6.0], 10), [2.0, 3.0, 4.0]);
+        assert!(smoothed(&[], 10).is_empty());
+    }
+
+    #[test]
+    fn gaps_after_filtered_entries_are_not_marked() {
+        let start = Local::now();
+        let stats: Vec<AllStats> = history_at(start, &[0, 60, 1000, 2000])
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| AllStats {
+                after_filtered_entries: i == 3,
+                ..x.clone()
+            })
+            .collect();
+        let history = StatsHistory::from_stats(stats);
+
+        let entries = entries_with_gaps(&history, &test_config());
+        let gaps: Vec<bool> = entries.iter().map(Option::is_none).collect();
+        assert_eq!(gaps, vec![false, false, true, false, false]);
+    }
+}
@@ -0,0 +1,49 @@
+//! 用于报告问题的调试快照，将当前的统计信息、配置和最近的统计历史打包到一个可下载的 JSON 文件中。
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local};
+use rocket::http::Header;
+use rocket::response::{self, Responder};
+use rocket::Request;
+use serde::Serialize;
+
+use crate::pretty_json::PrettyJson;
+use crate::stats::AllStats;
+use crate::stats_history::CollectorHealth;
+
+/// 下载的快照文件名的时间格式
+const FILE_NAME_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// 调试快照
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugSnapshot {
+    /// 此程序的版本
+    pub version: &'static str,
+    /// 生成快照的时间
+    pub generated_at: DateTime<Local>,
+    /// 最近收集的统计信息。尚未收集任何统计信息时为 `None`。
+    pub stats: Option<AllStats>,
+    /// 启动时使用的配置值，与 `/config` 相同
+    pub config: BTreeMap<String, serde_json::Value>,
+    /// 统计信息收集线程的健康状况，与 `/stats/collector-health` 相同
+    pub collector_health: CollectorHealth,
+    /// 内存中的统计历史，从旧到新排列
+    pub recent_history: Vec<AllStats>,
+}
+
+impl<'r> Responder<'r, 'static> for DebugSnapshot {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let file_name = format!(
+            "system-stats-snapshot-{}.json",
+            self.generated_at.format(FILE_NAME_TIME_FORMAT)
+        );
+        let mut response = PrettyJson::new(self, Some(true)).respond_to(request)?;
+        response.set_header(Header::new(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        ));
+        Ok(response)
+    }
+}
@@ -0,0 +1,168 @@
+//! 文件系统的健康检查，供外部监控使用
+
+use serde::Serialize;
+
+use crate::stats::MountStats;
+
+/// 文件系统健康检查的配置
+pub struct DiskHealthConfig {
+    /// 使用百分比达到多少时视为不健康
+    pub max_used_percent: f64,
+    /// 可用空间低于多少字节时视为不健康。为 0 时不检查可用空间。
+    pub min_free_bytes: u64,
+    /// 不检查的挂载点
+    pub excluded_mounts: Vec<String>,
+    /// 不检查的文件系统类型，例如 `squashfs`
+    pub excluded_fs_types: Vec<String>,
+}
+
+impl DiskHealthConfig {
+    /// 是否应该检查提供的文件系统。只读的文件系统（例如 snap 包的 squashfs 挂载）总是满的，但不会再被写入，因此不检查。
+    /// 总大小为 0 的文件系统（例如 `/proc` 等虚拟文件系统）没有可用空间可言，也不检查。
+    ///
+    /// # 参数
+    /// * `mount` - 文件系统的统计信息
+    fn should_check(&self, mount: &MountStats) -> bool {
+        !mount.read_only
+            && mount.total_mb > 0
+            && !self.excluded_mounts.contains(&mount.mounted_on)
+            && !self
+                .excluded_fs_types
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(&mount.fs_type))
+    }
+}
+
+/// 文件系统健康检查的结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskHealth {
+    /// 是否所有文件系统都健康
+    pub healthy: bool,
+    /// 不健康的文件系统
+    pub unhealthy_mounts: Vec<UnhealthyMount>,
+}
+
+/// 一个不健康的文件系统
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnhealthyMount {
+    /// 文件系统的挂载点
+    pub mounted_on: String,
+    /// 使用百分比
+    pub used_percent: f64,
+    /// 可用空间，以字节为单位
+    pub free_bytes: u64,
+    /// 视为不健康的原因
+    pub reasons: Vec<String>,
+}
+
+impl DiskHealth {
+    /// 检查文件系统是否健康。跳过只读的、总大小为 0 的和配置中排除的文件系统。
+    ///
+    /// # 参数
+    /// * `filesystems` - 最近收集的文件系统统计信息
+    /// * `config` - 健康检查的配置
+    pub fn check(filesystems: &[MountStats], config: &DiskHealthConfig) -> DiskHealth {
+        let unhealthy_mounts: Vec<UnhealthyMount> = filesystems
+            .iter()
+            .filter(|mount| config.should_check(mount))
+            .filter_map(|mount| {
+                let used_percent = mount.used_percent();
+                let free_bytes = mount.free_bytes();
+
+                let mut reasons = Vec::new();
+                if used_percent >= config.max_used_percent {
+                    reasons.push(format!(
+                        "usage {:.1}% is at or above {}%",
+                        used_percent, config.max_used_percent
+                    ));
+                }
+                if free_bytes < config.min_free_bytes {
+                    reasons.push(format!(
+                        "{} bytes free is below {} bytes",
+                        free_bytes, config.min_free_bytes
+                    ));
+                }
+
+                if reasons.is_empty() {
+                    None
+                } else {
+                    Some(UnhealthyMount {
+                        mounted_on: mount.mounted_on.clone(),
+                        used_percent,
+                        free_bytes,
+                        reasons,
+                    })
+                }
+            })
+            .collect();
+
+        DiskHealth {
+            healthy: unhealthy_mounts.is_empty(),
+            unhealthy_mounts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mount(mounted_on: &str, fs_type: &str, read_only: bool) -> MountStats {
+        MountStats {
+            fs_type: fs_type.to_string(),
+            mounted_from: "/dev/sda1".to_string(),
+            mounted_on: mounted_on.to_string(),
+            used_mb: 100,
+            total_mb: 100,
+            read_only,
+            mount_options: None,
+        }
+    }
+
+    fn config() -> DiskHealthConfig {
+        DiskHealthConfig {
+            max_used_percent: 90.0,
+            min_free_bytes: 0,
+            excluded_mounts: vec!["/boot/efi".to_string()],
+            excluded_fs_types: vec!["squashfs".to_string()],
+        }
+    }
+
+    #[test]
+    fn full_filesystems_are_unhealthy() {
+        let health = DiskHealth::check(&[mount("/", "ext4", false)], &config());
+        assert!(!health.healthy);
+        assert_eq!("/", health.unhealthy_mounts[0].mounted_on);
+    }
+
+    #[test]
+    fn read_only_and_excluded_filesystems_are_skipped() {
+        let filesystems = [
+            mount("/snap/core/1", "ext4", true),
+            mount("/boot/efi", "vfat", false),
+            mount("/media/image", "SquashFS", false),
+        ];
+        let health = DiskHealth::check(&filesystems, &config());
+        assert!(health.healthy);
+        assert!(health.unhealthy_mounts.is_empty());
+    }
+
+    #[test]
+    fn empty_filesystems_are_skipped() {
+        let empty = MountStats {
+            used_mb: 0,
+            total_mb: 0,
+            ..mount("/proc", "proc", false)
+        };
+        assert_eq!(0.0, empty.used_percent());
+        let config = DiskHealthConfig {
+            min_free_bytes: 1,
+            ..config()
+        };
+        let health = DiskHealth::check(&[empty], &config);
+        assert!(health.healthy);
+        assert!(health.unhealthy_mounts.is_empty());
+    }
+}
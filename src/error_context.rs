@@ -1,5 +1,8 @@
 //! 错误模板的上下文。
 
+use rocket::response::{self, Responder};
+use rocket::Request;
+use rocket_dyn_templates::Template;
 use serde::Serialize;
 
 /// 错误模板的上下文。
@@ -10,3 +13,29 @@ pub struct ErrorContext {
     /// 错误消息。
     pub message: String,
 }
+
+/// 渲染好的错误页面。模板被装箱，使得以它作为错误类型的 `Result` 不会太大。
+pub struct ErrorPage(Box<Template>);
+
+impl ErrorPage {
+    /// 创建一个 `ErrorPage`。
+    ///
+    /// # 参数
+    /// * `title` - 页面的标题。
+    /// * `message` - 错误消息。
+    pub fn new(title: impl Into<String>, message: impl Into<String>) -> ErrorPage {
+        ErrorPage(Box::new(Template::render(
+            "error",
+            &ErrorContext {
+                title: title.into(),
+                message: message.into(),
+            },
+        )))
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ErrorPage {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        (*self.0).respond_to(request)
+    }
+}
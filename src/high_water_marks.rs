@@ -0,0 +1,175 @@
+//! 统计信息的历史最高值，例如 CPU 的最高温度。启用持久化时保存在统计历史目录中，重启后仍然保留。
+
+use std::{
+    fs::{rename, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::stats::AllStats;
+use crate::stats_history::{create_history_dir, history_file_options};
+
+/// 启用持久化时，保存历史最高值的文件的名称。位于统计历史目录中。
+const HIGH_WATER_MARKS_FILE_NAME: &str = "high_water_marks.json";
+
+/// 一项统计信息的最高值
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    /// 最高值
+    pub value: f32,
+    /// 达到最高值的收集时间
+    pub time: DateTime<Local>,
+}
+
+/// 各项统计信息的最高值
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Records {
+    /// 开始记录最高值的时间
+    pub since: DateTime<Local>,
+    /// CPU 总负载百分比的最高值
+    pub aggregate_cpu_load_percent: Option<Record>,
+    /// CPU 温度的最高值，以摄氏度为单位
+    pub cpu_temp_celsius: Option<Record>,
+    /// 实际使用的内存的最高值，以MB为单位
+    pub memory_used_mb: Option<Record>,
+}
+
+impl Records {
+    /// 创建一个从现在开始记录的空 `Records`。
+    fn new() -> Records {
+        Records {
+            since: Local::now(),
+            aggregate_cpu_load_percent: None,
+            cpu_temp_celsius: None,
+            memory_used_mb: None,
+        }
+    }
+
+    /// 用新收集的统计信息更新最高值。返回是否有任何最高值被更新。
+    ///
+    /// # 参数
+    /// * `stats` - 新收集的统计信息
+    fn update(&mut self, stats: &AllStats) -> bool {
+        let time = stats.collection_time;
+        let memory_used_mb = stats.memory.as_ref().map(|x| x.effective_used_mb() as f32);
+        let mut updated = false;
+        for (record, value) in [
+            (
+                &mut self.aggregate_cpu_load_percent,
+                stats.cpu.aggregate_load_percent,
+            ),
+            (&mut self.cpu_temp_celsius, stats.cpu.temp_celsius),
+            (&mut self.memory_used_mb, memory_used_mb),
+        ] {
+            let value = match value {
+                Some(x) if !x.is_nan() => x,
+                _ => continue,
+            };
+            if !matches!(record, Some(x) if x.value >= value) {
+                *record = Some(Record { value, time });
+                updated = true;
+            }
+        }
+
+        updated
+    }
+}
+
+/// 历史最高值，在每次收集统计信息后更新
+pub struct HighWaterMarks {
+    /// 最高值
+    records: Mutex<Records>,
+    /// 保存最高值的文件。不持久化时为 `None`。
+    file_path: Option<PathBuf>,
+    /// 是否只允许当前用户访问创建的目录和文件
+    restrict_permissions: bool,
+}
+
+impl HighWaterMarks {
+    /// 创建一个 `HighWaterMarks`。如果提供了 `dir`，则从中加载之前保存的最高值，并在每次更新后保存到其中。
+    ///
+    /// # 参数
+    /// * `dir` - 保存最高值的目录。为 `None` 时只记录此程序启动以来的最高值。
+    /// * `restrict_permissions` - 是否只允许当前用户访问创建的目录和文件，与统计历史文件相同。
+    pub fn new(dir: Option<&Path>, restrict_permissions: bool) -> HighWaterMarks {
+        let file_path = dir.map(|x| x.join(HIGH_WATER_MARKS_FILE_NAME));
+        let records = match &file_path {
+            Some(path) if path.exists() => match load_records(path) {
+                Ok(x) => x,
+                Err(e) => {
+                    println!("从 {:?} 加载历史最高值时出错: {}", path, e);
+                    Records::new()
+                }
+            },
+            _ => Records::new(),
+        };
+
+        HighWaterMarks {
+            records: Mutex::new(records),
+            file_path,
+            restrict_permissions,
+        }
+    }
+
+    /// 用新收集的统计信息更新最高值，如果有最高值被更新并且启用了持久化，则保存到文件。
+    ///
+    /// # 参数
+    /// * `stats` - 新收集的统计信息
+    pub fn update(&self, stats: &AllStats) {
+        let mut records = self.records.lock().unwrap();
+        if records.update(stats) {
+            if let Err(e) = self.save(&records) {
+                println!("保存历史最高值时出错: {}", e);
+            }
+        }
+    }
+
+    /// 获取当前的最高值。
+    pub fn get(&self) -> Records {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// 如果启用了持久化，则将最高值写入文件，覆盖之前的内容。
+    ///
+    /// # 参数
+    /// * `records` - 最高值
+    fn save(&self, records: &Records) -> io::Result<()> {
+        let path = match &self.file_path {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            if !dir.exists() {
+                create_history_dir(dir, self.restrict_permissions)?;
+            }
+        }
+
+        // 先写入临时文件再重命名，避免写入途中出错时留下不完整的文件
+        let temp_path = path.with_extension("json.tmp");
+        let temp_file = history_file_options(self.restrict_permissions)
+            .write(true)
+            .truncate(true)
+            .open(&temp_path)?;
+        let mut writer = BufWriter::new(temp_file);
+        serde_json::to_writer(&mut writer, records)?;
+        writer.flush()?;
+        drop(writer);
+
+        rename(temp_path, path)
+    }
+}
+
+/// 从文件加载最高值
+///
+/// # 参数
+/// * `path` - 保存最高值的文件
+fn load_records(path: &Path) -> io::Result<Records> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
@@ -0,0 +1,44 @@
+//! 统计历史的 JSON 响应，条目数量受到限制时通过响应头告知客户端。
+
+use rocket::http::Header;
+use rocket::response::{self, Responder};
+use rocket::Request;
+
+use crate::pretty_json::PrettyJson;
+use crate::stats::AllStats;
+
+/// 条目因为超过 `max_history_response_entries` 而被截断时添加的响应头
+pub const TRUNCATED_HEADER_NAME: &str = "X-History-Truncated";
+
+/// 统计历史的响应，从新到旧排列。
+pub struct HistoryResponse {
+    /// 返回的统计信息
+    entries: PrettyJson<Vec<AllStats>>,
+    /// 条目是否因为超过允许返回的最大条目数而被截断
+    truncated: bool,
+}
+
+impl HistoryResponse {
+    /// 创建一个 `HistoryResponse`。
+    ///
+    /// # 参数
+    /// * `entries` - 返回的统计信息，从新到旧排列。
+    /// * `truncated` - 条目是否因为超过允许返回的最大条目数而被截断。
+    /// * `pretty` - 是否输出缩进的 JSON。
+    pub fn new(entries: Vec<AllStats>, truncated: bool, pretty: Option<bool>) -> HistoryResponse {
+        HistoryResponse {
+            entries: PrettyJson::new(entries, pretty),
+            truncated,
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for HistoryResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = self.entries.respond_to(request)?;
+        if self.truncated {
+            response.set_header(Header::new(TRUNCATED_HEADER_NAME, "true"));
+        }
+        Ok(response)
+    }
+}
@@ -1,10 +1,19 @@
-use std::num::NonZeroUsize;
+use std::{collections::BTreeMap, num::NonZeroUsize, path::Path, sync::Arc};
 
-use rocket::serde::json::Json;
-use rocket::{figment::Figment, http::Status, Rocket, State};
+use chrono::{DateTime, Local};
+use rocket::fs::{FileServer, Options};
+use rocket::response::stream::{Event, EventStream};
 use rocket::response::Redirect;
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{
+    figment::{providers::Env, Figment},
+    http::{uri::Origin, ContentType, Status},
+    serde::json::{self, Json},
+    Request, Rocket, Shutdown, State,
+};
 use rocket_dyn_templates::Template;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use systemstat::{Duration, Platform, System};
 
 mod stats;
@@ -13,24 +22,104 @@ use stats::*;
 mod stats_history;
 use stats_history::*;
 
+mod system_source;
+
+mod quiet_hours;
+use quiet_hours::*;
+
 mod dashboard_context;
 use dashboard_context::*;
 
 mod error_context;
 use error_context::*;
 
+mod pretty_json;
+use pretty_json::*;
+
+mod snake_case;
+
+mod api_error;
+use api_error::*;
+
+mod stats_diff;
+use stats_diff::*;
+
+mod negotiated_template;
+use negotiated_template::*;
+
+mod remote_agent;
+use remote_agent::*;
+
+mod compression;
+use compression::*;
+
+mod annotations;
+use annotations::*;
+
+mod disk_health;
+use disk_health::*;
+
+mod busy_score;
+use busy_score::*;
+
+mod history_response;
+use history_response::*;
+
+mod stats_window;
+use stats_window::*;
+
+mod debug_snapshot;
+use debug_snapshot::*;
+
+mod stuck_metrics;
+use stuck_metrics::*;
+
+mod high_water_marks;
+use high_water_marks::*;
+
+mod cors;
+use cors::*;
+
+#[cfg(feature = "xml")]
+mod xml;
+
 #[macro_use]
 extern crate rocket;
 
+/// 网站图标的内容
+const FAVICON: &[u8] = include_bytes!("../static/favicon.ico");
+
+/// 静态文件的挂载路径
+const STATIC_FILES_PATH: &str = "/static";
+
 const CPU_LOAD_SAMPLE_DURATION: Duration = Duration::from_millis(500);
-const DEFAULT_DARK_MODE: bool = true;
+/// 分页查看历史统计信息但未指定 `page_size` 时每页的条目数
+const DEFAULT_HISTORY_PAGE_SIZE: usize = 1000;
+
+/// 用于覆盖配置值的环境变量前缀。例如，`SSD_UPDATE_FREQUENCY_SECONDS` 覆盖 `update_frequency_seconds`。
+const CONFIG_ENV_VAR_PREFIX: &str = "SSD_";
 
 const RECENT_HISTORY_SIZE_CONFIG_KEY: &str = "recent_history_size";
 const DEFAULT_RECENT_HISTORY_SIZE: usize = 180;
 
+const MAX_HISTORY_RESPONSE_ENTRIES_CONFIG_KEY: &str = "max_history_response_entries";
+const DEFAULT_MAX_HISTORY_RESPONSE_ENTRIES: usize = 1000;
+
+const RAW_HISTORY_SIZE_CONFIG_KEY: &str = "raw_history_size";
+const DEFAULT_RAW_HISTORY_SIZE: usize = 60;
+
+const CPU_SPARSE_OUTPUT_CONFIG_KEY: &str = "cpu_sparse_output";
+const DEFAULT_CPU_SPARSE_OUTPUT: bool = false;
+
+const CPU_SPARSE_THRESHOLD_CONFIG_KEY: &str = "cpu_sparse_threshold_percent";
+const DEFAULT_CPU_SPARSE_THRESHOLD_PERCENT: f32 = 5.0;
+
 const CONSOLIDATION_LIMIT_CONFIG_KEY: &str = "consolidation_limit";
 const DEFAULT_CONSOLIDATION_LIMIT: usize = 20;
 
+const CONSOLIDATION_INTERVAL_CONFIG_KEY: &str = "consolidation_interval_seconds";
+const DEFAULT_CONSOLIDATION_INTERVAL_SECONDS: Option<u64> = None;
+
 const UPDATE_FREQUENCY_CONFIG_KEY: &str = "update_frequency_seconds";
 const DEFAULT_UPDATE_FREQUENCY_SECONDS: u64 = 3;
 
@@ -43,107 +132,1199 @@ const DEFAULT_HISTORY_FILES_DIRECTORY: &str = "./stats_history";
 const HISTORY_FILES_DIRECTORY_MAX_SIZE_CONFIG_KEY: &str = "history_files_max_size_bytes";
 const DEFAULT_HISTORY_FILES_DIRECTORY_MAX_SIZE_BYTES: u64 = 2_000_000; // 2MB
 
-/// 获取最新的系统统计信息
-#[get("/stats")]
-fn get_all_stats(stats_history: &State<UpdatingStatsHistory>) -> Result<Json<AllStats>, Status> {
-    match stats_history
-        .stats_history
-        .lock()
-        .unwrap()
-        .get_most_recent_stats()
-    {
-        Some(x) => Ok(Json((*x).clone())),
-        None => Err(Status::InternalServerError),
+const HISTORY_FILE_COUNT_CONFIG_KEY: &str = "history_file_count";
+const DEFAULT_HISTORY_FILE_COUNT: usize = 2;
+
+const PERSISTENCE_RETRY_BUFFER_SIZE_CONFIG_KEY: &str = "persistence_retry_buffer_size";
+const DEFAULT_PERSISTENCE_RETRY_BUFFER_SIZE: usize = 100;
+
+const SAMPLE_JITTER_CONFIG_KEY: &str = "sample_jitter_ms";
+const DEFAULT_SAMPLE_JITTER_MS: u64 = 0;
+
+const QUIET_HOURS_CONFIG_KEY: &str = "quiet_hours";
+const DEFAULT_QUIET_HOURS: Option<QuietHours> = None;
+
+const ALLOW_COLLECTION_CONTROL_CONFIG_KEY: &str = "allow_collection_control";
+const DEFAULT_ALLOW_COLLECTION_CONTROL: bool = false;
+
+const PERSISTENCE_MODE_CONFIG_KEY: &str = "persistence_mode";
+const DEFAULT_PERSISTENCE_MODE: PersistenceMode = PersistenceMode::Append;
+
+const PERSISTENCE_CHANGE_THRESHOLD_CONFIG_KEY: &str = "persistence_change_threshold_percent";
+const DEFAULT_PERSISTENCE_CHANGE_THRESHOLD_PERCENT: f32 = 0.0;
+
+const PERSISTENCE_MAX_GAP_CONFIG_KEY: &str = "persistence_max_gap_seconds";
+const DEFAULT_PERSISTENCE_MAX_GAP_SECONDS: u64 = 600;
+
+const RESTRICT_HISTORY_PERMISSIONS_CONFIG_KEY: &str = "restrict_history_permissions";
+const DEFAULT_RESTRICT_HISTORY_PERMISSIONS: bool = true;
+
+const LONG_TERM_HISTORY_DIRECTORY_CONFIG_KEY: &str = "long_term_history_directory";
+const DEFAULT_LONG_TERM_HISTORY_DIRECTORY: Option<String> = None;
+
+const LONG_TERM_CONSOLIDATION_FACTOR_CONFIG_KEY: &str = "long_term_consolidation_factor";
+const DEFAULT_LONG_TERM_CONSOLIDATION_FACTOR: usize = 60;
+
+const ALLOW_CONFIG_EXPORT_CONFIG_KEY: &str = "allow_config_export";
+const DEFAULT_ALLOW_CONFIG_EXPORT: bool = false;
+
+const ALLOW_DEBUG_SNAPSHOT_CONFIG_KEY: &str = "allow_debug_snapshot";
+const DEFAULT_ALLOW_DEBUG_SNAPSHOT: bool = false;
+
+const ALLOW_ADDING_ANNOTATIONS_CONFIG_KEY: &str = "allow_adding_annotations";
+const DEFAULT_ALLOW_ADDING_ANNOTATIONS: bool = false;
+
+const MAX_ANNOTATIONS_CONFIG_KEY: &str = "max_annotations";
+const DEFAULT_MAX_ANNOTATIONS: usize = 1000;
+
+const COMPRESS_RESPONSES_CONFIG_KEY: &str = "compress_responses";
+const DEFAULT_COMPRESS_RESPONSES: bool = true;
+
+const JSON_FIELD_CASE_CONFIG_KEY: &str = "json_field_case";
+const DEFAULT_JSON_FIELD_CASE: JsonFieldCase = JsonFieldCase::Camel;
+
+const DISABLED_COLLECTORS_CONFIG_KEY: &str = "disabled_collectors";
+const DEFAULT_DISABLED_COLLECTORS: Vec<Collector> = Vec::new();
+
+const LOG_UNSUPPORTED_ONCE_CONFIG_KEY: &str = "log_unsupported_once";
+const DEFAULT_LOG_UNSUPPORTED_ONCE: bool = true;
+
+const REMOTE_AGENTS_CONFIG_KEY: &str = "remote_agents";
+const DEFAULT_REMOTE_AGENTS: Vec<String> = Vec::new();
+
+const FLEET_AGGREGATION_CONFIG_KEY: &str = "fleet_aggregation";
+const DEFAULT_FLEET_AGGREGATION: FleetAggregation = FleetAggregation::Average;
+
+const BUSY_SCORE_WEIGHTS_CONFIG_KEY: &str = "busy_score_weights";
+
+const LOAD_AVERAGE_WINDOWS_CONFIG_KEY: &str = "load_average_windows";
+const DEFAULT_LOAD_AVERAGE_WINDOWS: &[LoadAverageWindow] = &[
+    LoadAverageWindow::OneMinute,
+    LoadAverageWindow::FiveMinutes,
+    LoadAverageWindow::FifteenMinutes,
+];
+
+const MEMORY_METRIC_CONFIG_KEY: &str = "memory_metric";
+const DEFAULT_MEMORY_METRIC: MemoryMetric = MemoryMetric::TotalMinusAvailable;
+
+const COMPACT_DASHBOARD_CONFIG_KEY: &str = "compact_dashboard";
+const DEFAULT_COMPACT_DASHBOARD: bool = false;
+
+const TIME_LABEL_DATES_CONFIG_KEY: &str = "time_label_dates";
+const DEFAULT_TIME_LABEL_DATES: TimeLabelDates = TimeLabelDates::DayBoundaries;
+
+const MOUNT_COLLECTION_TIMEOUT_MS_CONFIG_KEY: &str = "mount_collection_timeout_ms";
+const DEFAULT_MOUNT_COLLECTION_TIMEOUT_MS: u64 = 2000;
+
+const CONCURRENT_COLLECTION_CONFIG_KEY: &str = "concurrent_collection";
+const DEFAULT_CONCURRENT_COLLECTION: bool = true;
+
+const STATIC_FILES_DIRECTORY_CONFIG_KEY: &str = "static_files_directory";
+const DEFAULT_STATIC_FILES_DIRECTORY: &str = "./static";
+
+const ALLOWED_ORIGINS_CONFIG_KEY: &str = "allowed_origins";
+const DEFAULT_ALLOWED_ORIGINS: Vec<String> = Vec::new();
+
+const BASE_PATH_CONFIG_KEY: &str = "base_path";
+const DEFAULT_BASE_PATH: &str = "/";
+
+const DEFAULT_ROUTE_CONFIG_KEY: &str = "default_route";
+const DEFAULT_DEFAULT_ROUTE: &str = "/dashboard?dark=true";
+/// `default_route` 可以使用的路径。`/dashboard/chart/` 开头的路径也可以使用。
+const DEFAULT_ROUTE_PATHS: &[&str] = &[
+    "/dashboard",
+    "/dashboard/history",
+    "/dashboard/mini",
+    "/dashboard/fleet",
+    "/stats",
+    "/stats/text",
+];
+const DEFAULT_ROUTE_CHART_PATH_PREFIX: &str = "/dashboard/chart/";
+
+const FILESYSTEM_WARNING_PERCENT_CONFIG_KEY: &str = "filesystem_warning_percent";
+const DEFAULT_FILESYSTEM_WARNING_PERCENT: f64 = 80.0;
+
+const FILESYSTEM_CRITICAL_PERCENT_CONFIG_KEY: &str = "filesystem_critical_percent";
+const DEFAULT_FILESYSTEM_CRITICAL_PERCENT: f64 = 90.0;
+
+const FILESYSTEM_UNIT_SCALING_CONFIG_KEY: &str = "filesystem_unit_scaling";
+const DEFAULT_FILESYSTEM_UNIT_SCALING: bool = true;
+
+const DISK_FREE_BYTES_MIN_CONFIG_KEY: &str = "disk_free_bytes_min";
+const DEFAULT_DISK_FREE_BYTES_MIN: u64 = 0;
+
+const DISK_HEALTH_EXCLUDED_MOUNTS_CONFIG_KEY: &str = "disk_health_excluded_mounts";
+const DEFAULT_DISK_HEALTH_EXCLUDED_MOUNTS: Vec<String> = Vec::new();
+
+const DISK_HEALTH_EXCLUDED_FS_TYPES_CONFIG_KEY: &str = "disk_health_excluded_fs_types";
+const DEFAULT_DISK_HEALTH_EXCLUDED_FS_TYPES: Vec<String> = Vec::new();
+
+const ENTROPY_AVAILABLE_MIN_CONFIG_KEY: &str = "entropy_available_min";
+const DEFAULT_ENTROPY_AVAILABLE_MIN: u64 = 256;
+
+const STUCK_METRIC_ENTRIES_CONFIG_KEY: &str = "stuck_metric_entries";
+const DEFAULT_STUCK_METRIC_ENTRIES: usize = 0;
+
+const Y_TICK_COUNT_CONFIG_KEY: &str = "y_tick_count";
+const DEFAULT_Y_TICK_COUNT: usize = 11;
+
+const DISPLAY_PRECISION_CONFIG_KEY: &str = "display_precision";
+const DEFAULT_DISPLAY_PRECISION: usize = 2;
+
+const NETWORK_PER_INTERFACE_CHARTS_CONFIG_KEY: &str = "network_per_interface_charts";
+const DEFAULT_NETWORK_PER_INTERFACE_CHARTS: bool = false;
+
+const NETWORK_STACKED_CONFIG_KEY: &str = "network_stacked";
+const DEFAULT_NETWORK_STACKED: bool = false;
+
+const MAX_INTERFACE_CHARTS_CONFIG_KEY: &str = "max_interface_charts";
+const DEFAULT_MAX_INTERFACE_CHARTS: usize = 8;
+
+const INTERFACE_ALIASES_CONFIG_KEY: &str = "interface_aliases";
+const DEFAULT_INTERFACE_ALIASES: BTreeMap<String, String> = BTreeMap::new();
+
+const INCLUDE_LOOPBACK_CONFIG_KEY: &str = "include_loopback";
+const DEFAULT_INCLUDE_LOOPBACK: bool = false;
+
+const ANOMALY_DETECTION_CONFIG_KEY: &str = "anomaly_detection";
+const DEFAULT_ANOMALY_DETECTION: bool = false;
+
+const CHART_ORDER_CONFIG_KEY: &str = "chart_order";
+const DEFAULT_CHART_ORDER: Vec<String> = Vec::new();
+
+const CHART_SMOOTHING_WINDOW_CONFIG_KEY: &str = "chart_smoothing_window";
+const DEFAULT_CHART_SMOOTHING_WINDOW: usize = 1;
+
+const CHART_SMOOTHING_WINDOWS_CONFIG_KEY: &str = "chart_smoothing_windows";
+const DEFAULT_CHART_SMOOTHING_WINDOWS: BTreeMap<String, usize> = BTreeMap::new();
+
+const HISTORY_PAGE_ORDER_CONFIG_KEY: &str = "history_page_order";
+const DEFAULT_HISTORY_PAGE_ORDER: HistoryPageOrder = HistoryPageOrder::Newest;
+
+const MIN_MAX_BANDS_CONFIG_KEY: &str = "min_max_bands";
+const DEFAULT_MIN_MAX_BANDS: bool = true;
+
+const CPU_HISTOGRAM_CONFIG_KEY: &str = "cpu_histogram";
+const DEFAULT_CPU_HISTOGRAM: bool = false;
+
+const CHART_FILL_OPACITY_CONFIG_KEY: &str = "chart_fill_opacity";
+const DEFAULT_CHART_FILL_OPACITY: f32 = 0.6;
+
+const PERSISTENCE_FORMAT_CONFIG_KEY: &str = "persistence_format";
+const DEFAULT_PERSISTENCE_FORMAT: PersistenceFormat = PersistenceFormat::Json;
+
+/// 获取最新的系统统计信息
+#[get("/stats?<pretty>")]
+fn get_all_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<AllStats>, ApiError> {
+    let stats = most_recent_stats(stats_history)?;
+    Ok(PrettyJson::new((*stats).clone(), pretty))
+}
+
+/// 以纯文本形式获取最新的系统统计信息，每行一个 `键: 值`
+#[get("/stats/text")]
+fn get_text_stats(stats_history: &State<UpdatingStatsHistory>) -> Result<String, ApiError> {
+    Ok(most_recent_stats(stats_history)?.to_text())
+}
+
+/// 获取一般统计信息
+#[get("/stats/general?<pretty>")]
+fn get_general_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<GeneralStats>, ApiError> {
+    let stats = most_recent_stats(stats_history)?;
+    Ok(PrettyJson::new(stats.general.clone(), pretty))
+}
+
+/// 获取系统运行时间和启动时间
+#[get("/stats/uptime?<pretty>")]
+fn get_uptime_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<UptimeStats>, ApiError> {
+    let stats = most_recent_stats(stats_history)?;
+    Ok(PrettyJson::new(UptimeStats::from(&stats.general), pretty))
+}
+
+/// `/stats/cpu` 的配置
+struct CpuResponseConfig {
+    /// 只列出负载百分比至少为此值的逻辑 CPU。为 `None` 时列出所有逻辑 CPU 的负载。
+    sparse_threshold_percent: Option<f32>,
+}
+
+/// `/stats/cpu` 的响应，根据 `cpu_sparse_output` 列出所有或部分逻辑 CPU 的负载
+#[derive(Serialize)]
+#[serde(untagged)]
+enum CpuStatsResponse {
+    /// 列出所有逻辑 CPU 的负载
+    Dense(CpuStats),
+    /// 只列出负载达到阈值的逻辑 CPU
+    Sparse(SparseCpuStats),
+}
+
+/// 获取 CPU 统计信息
+#[get("/stats/cpu?<pretty>")]
+fn get_cpu_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    cpu_response_config: &State<CpuResponseConfig>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<CpuStatsResponse>, ApiError> {
+    let cpu = most_recent_stats(stats_history)?.cpu.clone();
+    let response = match cpu_response_config.sparse_threshold_percent {
+        Some(threshold) => CpuStatsResponse::Sparse(SparseCpuStats::from(cpu, threshold)),
+        None => CpuStatsResponse::Dense(cpu),
+    };
+    Ok(PrettyJson::new(response, pretty))
+}
+
+/// 获取每个逻辑 CPU 的负载百分比
+#[get("/stats/cpu/per-core?<pretty>")]
+fn get_per_core_cpu_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<Vec<f32>>, ApiError> {
+    let stats = most_recent_stats(stats_history)?;
+    match &stats.cpu.per_logical_cpu_load_percent {
+        Some(x) => Ok(PrettyJson::new(x.clone(), pretty)),
+        None => Err(ApiError::new(
+            Status::NotFound,
+            "per-core CPU load is unavailable",
+        )),
+    }
+}
+
+/// 获取内存统计信息
+#[get("/stats/memory?<pretty>")]
+fn get_memory_stats(pretty: Option<bool>) -> Result<PrettyJson<MemoryStats>, ApiError> {
+    match MemoryStats::from(&System::new()) {
+        Some(x) => Ok(PrettyJson::new(x, pretty)),
+        None => Err(ApiError::new(
+            Status::InternalServerError,
+            "failed to get memory stats",
+        )),
+    }
+}
+
+/// 获取文件系统统计信息
+#[get("/stats/filesystems?<pretty>", rank = 2)]
+fn get_filesystem_stats(
+    collector_config: &State<CollectorConfig>,
+    mount_collection: &State<MountCollection>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<Vec<MountStats>>, ApiError> {
+    if !collector_config.is_enabled(Collector::Mounts) {
+        return Err(ApiError::new(
+            Status::NotFound,
+            "filesystem stats collection is disabled",
+        ));
+    }
+    // 使用与后台收集线程不同的 `MountCollection`，以免它阻塞的线程使这里也跳过获取挂载信息
+    match mount_collection.collect(&System::new(), collector_config.mount_collection_timeout) {
+        Some(x) => Ok(PrettyJson::new(x, pretty)),
+        None => Err(ApiError::new(
+            Status::InternalServerError,
+            "failed to get filesystem stats",
+        )),
+    }
+}
+
+/// 获取根据最近收集的统计信息计算的系统繁忙程度评分
+#[get("/stats/busy-score?<pretty>")]
+fn get_busy_score(
+    stats_history: &State<UpdatingStatsHistory>,
+    dashboard_config: &State<DashboardConfig>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<BusyScore>, ApiError> {
+    let stats = most_recent_stats(stats_history)?;
+    Ok(PrettyJson::new(
+        BusyScore::from(&stats, &dashboard_config.busy_score_weights),
+        pretty,
+    ))
+}
+
+/// 获取最近 `minutes` 分钟内 CPU、内存、平均负载和网络总量的最小值、最大值、平均值和当前值
+#[get("/stats/window?<minutes>&<pretty>")]
+fn get_window_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    dashboard_config: &State<DashboardConfig>,
+    minutes: u32,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<WindowStats>, ApiError> {
+    if minutes == 0 {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "minutes must be greater than 0",
+        ));
+    }
+
+    let history = stats_history.stats_history.lock().unwrap();
+    WindowStats::from(&*history, minutes, dashboard_config.include_loopback)
+        .map(|x| PrettyJson::new(x, pretty))
+        .ok_or_else(|| {
+            ApiError::new(
+                Status::ServiceUnavailable,
+                format!("no stats collected in the last {} minutes", minutes),
+            )
+        })
+}
+
+/// 最简单的存活检查，供负载均衡器使用。不访问任何托管状态，因此不会因为统计信息的锁被占用而阻塞。
+#[get("/ping")]
+fn ping() -> &'static str {
+    "pong"
+}
+
+/// 检查文件系统是否健康，供外部监控使用。如果任何文件系统的使用百分比达到 `filesystem_critical_percent`，
+/// 或可用空间低于 `disk_free_bytes_min`，则返回 503 并列出这些文件系统。
+#[get("/health/disk?<pretty>")]
+fn get_disk_health(
+    stats_history: &State<UpdatingStatsHistory>,
+    collector_config: &State<CollectorConfig>,
+    disk_health_config: &State<DiskHealthConfig>,
+    pretty: Option<bool>,
+) -> Result<(Status, PrettyJson<DiskHealth>), ApiError> {
+    if !collector_config.is_enabled(Collector::Mounts) {
+        return Err(ApiError::new(
+            Status::NotFound,
+            "filesystem stats collection is disabled",
+        ));
+    }
+    let stats = most_recent_stats(stats_history)?;
+    let filesystems = match &stats.filesystems {
+        Some(x) => x,
+        None => {
+            return Err(ApiError::new(
+                Status::ServiceUnavailable,
+                "filesystem stats are unavailable",
+            ))
+        }
+    };
+
+    let health = DiskHealth::check(filesystems, disk_health_config);
+    let status = if health.healthy {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+    Ok((status, PrettyJson::new(health, pretty)))
+}
+
+/// 获取挂载在 `mount` 上的文件系统的统计信息
+#[get("/stats/filesystems?<mount>&<pretty>", rank = 1)]
+fn get_mount_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    collector_config: &State<CollectorConfig>,
+    mount: &str,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<MountStats>, ApiError> {
+    if !collector_config.is_enabled(Collector::Mounts) {
+        return Err(ApiError::new(
+            Status::NotFound,
+            "filesystem stats collection is disabled",
+        ));
+    }
+    let stats = most_recent_stats(stats_history)?;
+    let filesystems = stats.filesystems.as_ref().ok_or_else(|| {
+        ApiError::new(
+            Status::ServiceUnavailable,
+            "filesystem stats are unavailable",
+        )
+    })?;
+    match filesystems.iter().find(|x| x.mounted_on == mount) {
+        Some(x) => Ok(PrettyJson::new(x.clone(), pretty)),
+        None => Err(ApiError::new(
+            Status::NotFound,
+            format!("no filesystem mounted on {}", mount),
+        )),
+    }
+}
+
+/// 获取网络统计信息
+#[get("/stats/network?<pretty>")]
+fn get_network_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<NetworkStats>, ApiError> {
+    let stats = most_recent_stats(stats_history)?;
+    Ok(PrettyJson::new(stats.network.clone(), pretty))
+}
+
+/// 获取最近历史记录中最接近 `from` 和 `to` 的两次统计信息之间的差异。时间使用 RFC 3339 格式。
+#[get("/stats/diff?<from>&<to>&<pretty>")]
+fn get_stats_diff(
+    stats_history: &State<UpdatingStatsHistory>,
+    from: &str,
+    to: &str,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<StatsDiff>, ApiError> {
+    let from = parse_timestamp(from)?;
+    let to = parse_timestamp(to)?;
+    let history = stats_history.stats_history.lock().unwrap();
+    match (
+        history.get_stats_nearest_to(from),
+        history.get_stats_nearest_to(to),
+    ) {
+        (Some(from), Some(to)) => Ok(PrettyJson::new(StatsDiff::between(from, to), pretty)),
+        _ => Err(ApiError::not_collected()),
+    }
+}
+
+/// `/stats/history` 的配置
+struct HistoryResponseConfig {
+    /// 一次最多返回的条目数，避免历史很大时返回过大的响应
+    max_entries: NonZeroUsize,
+}
+
+/// `/stats/raw-history` 的配置
+struct RawHistoryConfig {
+    /// 内存中保留的未合并的统计信息的最大条目数。为 0 时不保留。
+    size: usize,
+}
+
+/// 获取内存中的统计历史，从新到旧排列。指定 `limit` 时最多返回这么多条目。
+/// 返回的条目数不超过 `max_history_response_entries`，因此被截断时添加 `X-History-Truncated: true` 响应头。
+#[get("/stats/history?<limit>&<pretty>")]
+fn get_stats_history(
+    stats_history: &State<UpdatingStatsHistory>,
+    history_response_config: &State<HistoryResponseConfig>,
+    limit: Option<usize>,
+    pretty: Option<bool>,
+) -> HistoryResponse {
+    let history = stats_history.stats_history.lock().unwrap();
+    let stats: Vec<&AllStats> = history.into_iter().collect();
+    let requested = limit.unwrap_or(usize::MAX).min(stats.len());
+    let count = requested.min(history_response_config.max_entries.get());
+    let entries = stats
+        .iter()
+        .rev()
+        .take(count)
+        .map(|x| (*x).clone())
+        .collect();
+    HistoryResponse::new(entries, count < requested, pretty)
+}
+
+/// 获取内存中保留的最近未合并的统计信息，从新到旧排列，每次收集一个条目。参数和截断方式与 `/stats/history` 相同。
+#[get("/stats/raw-history?<limit>&<pretty>")]
+fn get_raw_stats_history(
+    stats_history: &State<UpdatingStatsHistory>,
+    history_response_config: &State<HistoryResponseConfig>,
+    raw_history_config: &State<RawHistoryConfig>,
+    limit: Option<usize>,
+    pretty: Option<bool>,
+) -> Result<HistoryResponse, ApiError> {
+    if raw_history_config.size == 0 {
+        return Err(ApiError::new(Status::NotFound, "raw history is disabled"));
+    }
+
+    let stats = stats_history.raw_samples();
+    let requested = limit.unwrap_or(usize::MAX).min(stats.len());
+    let count = requested.min(history_response_config.max_entries.get());
+    let entries = stats.into_iter().rev().take(count).collect();
+    Ok(HistoryResponse::new(entries, count < requested, pretty))
+}
+
+/// 解析 RFC 3339 格式的时间。查询参数中没有编码的 `+` 会被解码为空格，因此时区偏移量前面的空格视为 `+`。
+/// 如果格式无效，则返回错误。
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Local>, ApiError> {
+    // 时区偏移量的格式为 `+HH:MM`
+    let normalized = match timestamp.len().checked_sub(6) {
+        Some(i) if timestamp.as_bytes()[i] == b' ' => {
+            format!("{}+{}", &timestamp[..i], &timestamp[i + 1..])
+        }
+        _ => timestamp.to_string(),
+    };
+    DateTime::parse_from_rfc3339(&normalized)
+        .map(|x| x.with_timezone(&Local))
+        .map_err(|e| {
+            ApiError::new(
+                Status::BadRequest,
+                format!("invalid timestamp '{}': {}", timestamp, e),
+            )
+        })
+}
+
+/// 以服务器发送事件（SSE）的形式推送统计信息。连接后立即发送最近收集的统计信息，之后每次收集到新的统计信息时发送一次，
+/// 格式与 `/stats` 相同。
+#[get("/stats/events")]
+fn stats_events(
+    stats_history: &State<UpdatingStatsHistory>,
+    json_field_case: &State<JsonFieldCase>,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let case = *json_field_case.inner();
+    // 先订阅再读取最近的统计信息，以免错过两者之间收集的统计信息
+    let mut receiver = stats_history.subscribe();
+    let most_recent_stats = stats_history.get_most_recent_stats();
+    EventStream! {
+        if let Some(stats) = most_recent_stats {
+            yield Event::json(&WithFieldCase::new(&*stats, case));
+        }
+        loop {
+            let stats = select! {
+                received = receiver.recv() => match received {
+                    Ok(x) => x,
+                    Err(RecvError::Closed) => break,
+                    // 客户端太慢，跳过了一些统计信息。继续发送之后的统计信息即可。
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+            yield Event::json(&WithFieldCase::new(&*stats, case));
+        }
+    }
+}
+
+/// 获取最近收集的统计信息。如果还没有收集到任何统计信息，则返回错误。
+fn most_recent_stats(stats_history: &UpdatingStatsHistory) -> Result<Arc<AllStats>, ApiError> {
+    stats_history
+        .get_most_recent_stats()
+        .ok_or_else(ApiError::not_collected)
+}
+
+/// 是否允许在运行时暂停、恢复和立即触发统计信息收集
+struct CollectionControlConfig {
+    allowed: bool,
+}
+
+/// 暂停收集统计信息
+#[post("/stats/collection/pause?<pretty>")]
+fn pause_collection(
+    stats_history: &State<UpdatingStatsHistory>,
+    collection_control_config: &State<CollectionControlConfig>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<CollectionStatus>, ApiError> {
+    if !collection_control_config.allowed {
+        return Err(ApiError::new(
+            Status::Forbidden,
+            "collection control is disabled",
+        ));
+    }
+    stats_history.pause();
+    Ok(PrettyJson::new(stats_history.collection_status(), pretty))
+}
+
+/// 恢复收集统计信息
+#[post("/stats/collection/resume?<pretty>")]
+fn resume_collection(
+    stats_history: &State<UpdatingStatsHistory>,
+    collection_control_config: &State<CollectionControlConfig>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<CollectionStatus>, ApiError> {
+    if !collection_control_config.allowed {
+        return Err(ApiError::new(
+            Status::Forbidden,
+            "collection control is disabled",
+        ));
+    }
+    stats_history.resume();
+    Ok(PrettyJson::new(stats_history.collection_status(), pretty))
+}
+
+/// 立即收集一次统计信息并更新历史记录，返回新收集的统计信息。
+/// 指定 `immediate=true` 时不阻塞采样 CPU 负载，而是使用上一次收集以来的平均 CPU 负载。
+#[post("/stats/sample?<pretty>&<immediate>")]
+fn sample_stats(
+    stats_history: &State<UpdatingStatsHistory>,
+    collection_control_config: &State<CollectionControlConfig>,
+    pretty: Option<bool>,
+    immediate: Option<bool>,
+) -> Result<PrettyJson<AllStats>, ApiError> {
+    if !collection_control_config.allowed {
+        return Err(ApiError::new(
+            Status::Forbidden,
+            "collection control is disabled",
+        ));
+    }
+    match stats_history.sample_now(!immediate.unwrap_or(false)) {
+        Ok(x) => Ok(PrettyJson::new(x, pretty)),
+        Err(e) => Err(ApiError::new(Status::InternalServerError, e.to_string())),
+    }
+}
+
+/// 列出时间轴上的所有注释，从旧到新
+#[get("/stats/annotations?<pretty>")]
+fn get_annotations(
+    annotations: &State<Annotations>,
+    stats_history: &State<UpdatingStatsHistory>,
+    history_persistence_config: &State<HistoryPersistenceConfig>,
+    pretty: Option<bool>,
+) -> PrettyJson<Vec<Annotation>> {
+    prune_annotations(annotations, stats_history, history_persistence_config);
+    PrettyJson::new(annotations.list(), pretty)
+}
+
+/// 是否允许通过 `/stats/annotations` 添加注释
+struct AnnotationConfig {
+    adding_allowed: bool,
+}
+
+/// 在时间轴上添加一个注释，例如部署的时间。返回添加的注释。
+#[post("/stats/annotations?<pretty>", data = "<new_annotation>")]
+fn add_annotation(
+    annotations: &State<Annotations>,
+    annotation_config: &State<AnnotationConfig>,
+    stats_history: &State<UpdatingStatsHistory>,
+    history_persistence_config: &State<HistoryPersistenceConfig>,
+    new_annotation: Result<Json<NewAnnotation>, json::Error<'_>>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<Annotation>, ApiError> {
+    if !annotation_config.adding_allowed {
+        return Err(ApiError::new(
+            Status::Forbidden,
+            "adding annotations is disabled",
+        ));
+    }
+    let new_annotation = match new_annotation {
+        Ok(x) => x.into_inner(),
+        Err(e) => {
+            return Err(ApiError::new(
+                Status::BadRequest,
+                format!("invalid annotation: {}", e),
+            ))
+        }
+    };
+    if new_annotation.label.trim().is_empty() {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "annotation label must not be empty",
+        ));
+    }
+
+    let annotation = Annotation {
+        label: new_annotation.label,
+        timestamp: new_annotation.timestamp.unwrap_or_else(Local::now),
+    };
+    if let Err(e) = annotations.add(annotation.clone()) {
+        return Err(ApiError::new(Status::InternalServerError, e.to_string()));
+    }
+    prune_annotations(annotations, stats_history, history_persistence_config);
+    Ok(PrettyJson::new(annotation, pretty))
+}
+
+/// 删除早于内存中的统计历史的注释。启用持久化时，保留的时间范围由持久化的统计信息决定，
+/// 因此改为在 `/dashboard/history` 加载所有持久化的统计信息时删除。
+///
+/// # 参数
+/// * `annotations` - 所有注释
+/// * `stats_history` - 本机的统计历史
+/// * `history_persistence_config` - 统计历史持久化的配置
+fn prune_annotations(
+    annotations: &Annotations,
+    stats_history: &UpdatingStatsHistory,
+    history_persistence_config: &HistoryPersistenceConfig,
+) {
+    if let HistoryPersistenceConfig::Disabled = history_persistence_config {
+        let oldest = stats_history
+            .stats_history
+            .lock()
+            .unwrap()
+            .oldest_retained_time();
+        if let Some(oldest) = oldest {
+            annotations.prune_before(oldest);
+        }
+    }
+}
+
+/// 启动时使用的配置值
+struct ResolvedConfig {
+    /// 是否允许通过 `/config` 获取配置值
+    export_allowed: bool,
+    /// 是否允许通过 `/debug/snapshot` 下载调试快照
+    debug_snapshot_allowed: bool,
+    /// 使用的配置值，以配置键为键。不能包含任何机密信息。
+    values: BTreeMap<String, serde_json::Value>,
+}
+
+/// 获取启动时使用的配置值，用于排查配置为什么没有生效
+#[get("/config?<pretty>")]
+fn get_config(
+    resolved_config: &State<ResolvedConfig>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<BTreeMap<String, serde_json::Value>>, ApiError> {
+    if !resolved_config.export_allowed {
+        return Err(ApiError::new(
+            Status::Forbidden,
+            "config export is disabled",
+        ));
+    }
+    Ok(PrettyJson::new(resolved_config.values.clone(), pretty))
+}
+
+/// 下载包含最近收集的统计信息、配置值、收集线程的健康状况和内存中的统计历史的调试快照，用于报告问题
+#[get("/debug/snapshot")]
+fn get_debug_snapshot(
+    stats_history: &State<UpdatingStatsHistory>,
+    resolved_config: &State<ResolvedConfig>,
+) -> Result<DebugSnapshot, ApiError> {
+    if !resolved_config.debug_snapshot_allowed {
+        return Err(ApiError::new(
+            Status::Forbidden,
+            "debug snapshot is disabled",
+        ));
+    }
+
+    let recent_history = stats_history
+        .stats_history
+        .lock()
+        .unwrap()
+        .into_iter()
+        .cloned()
+        .collect();
+    Ok(DebugSnapshot {
+        version: env!("CARGO_PKG_VERSION"),
+        generated_at: Local::now(),
+        stats: stats_history.get_most_recent_stats().map(|x| (*x).clone()),
+        config: resolved_config.values.clone(),
+        collector_health: stats_history.collector_health(),
+        recent_history,
+    })
+}
+
+/// 获取持久化的统计历史占用的空间、条目数和时间范围
+#[get("/stats/history/info?<pretty>")]
+fn get_history_info(
+    history_persistence_config: &State<HistoryPersistenceConfig>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<HistoryInfo>, ApiError> {
+    match history_persistence_config.inner() {
+        HistoryPersistenceConfig::Enabled {
+            dir, size_limit, ..
+        } => match HistoryInfo::load(dir, *size_limit) {
+            Ok(x) => Ok(PrettyJson::new(x, pretty)),
+            Err(e) => {
+                println!("Error loading persisted stats from {:?}: {}", dir, e);
+                Err(ApiError::new(
+                    Status::InternalServerError,
+                    "failed to load persisted stats",
+                ))
+            }
+        },
+        HistoryPersistenceConfig::Disabled => Err(ApiError::new(
+            Status::NotFound,
+            "stats history persistence is disabled",
+        )),
+    }
+}
+
+/// 获取最近收集的统计信息中哪些统计信息可用
+#[get("/stats/capabilities?<pretty>")]
+fn get_capabilities(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<Capabilities>, ApiError> {
+    let stats = most_recent_stats(stats_history)?;
+    Ok(PrettyJson::new(Capabilities::from(&*stats), pretty))
+}
+
+/// 获取统计信息收集的状态
+#[get("/stats/collection/status?<pretty>")]
+fn get_collection_status(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> PrettyJson<CollectionStatus> {
+    PrettyJson::new(stats_history.collection_status(), pretty)
+}
+
+/// 网站图标。编译进程序中，因此不依赖于静态文件目录。
+#[get("/favicon.ico")]
+fn favicon() -> (ContentType, &'static [u8]) {
+    (ContentType::Icon, FAVICON)
+}
+
+/// 静态文件目录中找不到请求的文件
+#[catch(404)]
+fn static_file_not_found(request: &Request) -> String {
+    debug!("找不到静态文件: {}", request.uri());
+    format!("No static file at {}", request.uri())
+}
+
+/// 获取后台收集线程的健康状况，例如最近一次成功收集的时间和连续失败的次数
+#[get("/stats/collector-health?<pretty>")]
+fn get_collector_health(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> PrettyJson<CollectorHealth> {
+    PrettyJson::new(stats_history.collector_health(), pretty)
+}
+
+/// 获取值在最近 `stuck_metric_entries` 个历史条目中完全相同的指标，这通常说明传感器或数据来源卡住了
+#[get("/stats/stuck-metrics?<pretty>")]
+fn get_stuck_metrics(
+    stats_history: &State<UpdatingStatsHistory>,
+    dashboard_config: &State<DashboardConfig>,
+    pretty: Option<bool>,
+) -> Result<PrettyJson<Vec<StuckMetric>>, ApiError> {
+    if dashboard_config.stuck_metric_entries == 0 {
+        return Err(ApiError::new(
+            Status::NotFound,
+            "stuck metric detection is disabled",
+        ));
+    }
+
+    let history = stats_history.stats_history.lock().unwrap();
+    Ok(PrettyJson::new(
+        find_stuck_metrics(&*history, dashboard_config.stuck_metric_entries),
+        pretty,
+    ))
+}
+
+/// 获取 CPU 负载、CPU 温度和内存使用量的历史最高值及其时间。启用持久化时，重启后仍然保留。
+#[get("/stats/records?<pretty>")]
+fn get_records(
+    stats_history: &State<UpdatingStatsHistory>,
+    pretty: Option<bool>,
+) -> PrettyJson<Records> {
+    PrettyJson::new(stats_history.records(), pretty)
+}
+
+/// 通过反向代理访问时的路径前缀
+struct BasePathConfig {
+    /// 路径前缀。直接访问时为 `/`。
+    prefix: Origin<'static>,
+}
+
+/// 访问首页时转发到的地址
+struct DefaultRouteConfig {
+    /// 转发到的地址，已包含路径前缀
+    target: Origin<'static>,
+}
+
+/// 首页 - 转发到 `default_route` 指定的页面，默认为仪表板
+#[get("/")]
+fn index(default_route_config: &State<DefaultRouteConfig>) -> Redirect {
+    Redirect::to(default_route_config.target.clone())
+}
+
+/// 解析 `default_route` 的值，并加上路径前缀。如果值无效或不是已知的页面，则返回错误。
+///
+/// # 参数
+/// * `route` - 配置的值，例如 `/dashboard/history?hours=24`
+/// * `base_path` - 路径前缀
+fn parse_default_route(route: &str, base_path: &Origin) -> Result<Origin<'static>, String> {
+    let route = Origin::parse(route).map_err(|e| e.to_string())?;
+    let path = route.path().as_str();
+    let known = DEFAULT_ROUTE_PATHS.contains(&path)
+        || (path.starts_with(DEFAULT_ROUTE_CHART_PATH_PREFIX)
+            && path.len() > DEFAULT_ROUTE_CHART_PATH_PREFIX.len());
+    if !known {
+        return Err(format!(
+            "未知的页面 {}，可以使用 {} 或 {}<id>",
+            path,
+            DEFAULT_ROUTE_PATHS.join("、"),
+            DEFAULT_ROUTE_CHART_PATH_PREFIX
+        ));
     }
+
+    let target = match route.query() {
+        Some(query) => format!(
+            "{}{}?{}",
+            base_path.path().as_str().trim_end_matches('/'),
+            path,
+            query
+        ),
+        None => format!(
+            "{}{}",
+            base_path.path().as_str().trim_end_matches('/'),
+            path
+        ),
+    };
+    Origin::parse_owned(target).map_err(|e| e.to_string())
 }
 
-/// 获取一般统计信息
-#[get("/stats/general")]
-fn get_general_stats(
+/// 从远程代理获取的统计历史
+struct RemoteStatsHistories {
+    /// 每个远程代理的名称和统计历史
+    histories: Vec<(String, UpdatingStatsHistory)>,
+}
+
+/// 查看仪表板。如果提供了 `machine`，则显示该名称的远程代理的统计信息，否则显示本机的统计信息。
+/// 如果请求首选 JSON，则返回仪表板的上下文。`compact` 为 `true` 时返回适合低带宽连接的紧凑上下文，
+/// 不提供时使用 `compact_dashboard` 配置。
+#[get("/dashboard?<dark>&<machine>&<compact>")]
+fn dashboard(
     stats_history: &State<UpdatingStatsHistory>,
-) -> Result<Json<GeneralStats>, Status> {
-    match stats_history
-        .stats_history
-        .lock()
-        .unwrap()
-        .get_most_recent_stats()
-    {
-        Some(x) => Ok(Json((*x).general.clone())),
-        None => Err(Status::InternalServerError),
+    remote_stats_histories: &State<RemoteStatsHistories>,
+    dashboard_config: &State<DashboardConfig>,
+    annotations: &State<Annotations>,
+    dark: Option<bool>,
+    machine: Option<&str>,
+    compact: Option<bool>,
+) -> Result<NegotiatedTemplate<DashboardContext>, ErrorPage> {
+    match machine_dashboard_context(
+        stats_history,
+        remote_stats_histories,
+        dashboard_config,
+        annotations,
+        dark,
+        machine,
+        compact,
+    ) {
+        Ok(context) => Ok(NegotiatedTemplate::new("dashboard", context)),
+        Err(message) => Err(ErrorPage::new("Dashboard", message)),
     }
 }
 
-/// 获取 CPU 统计信息
-#[get("/stats/cpu")]
-fn get_cpu_stats(stats_history: &State<UpdatingStatsHistory>) -> Result<Json<CpuStats>, Status> {
-    match stats_history
-        .stats_history
-        .lock()
-        .unwrap()
-        .get_most_recent_stats()
-    {
-        Some(x) => Ok(Json((*x).cpu.clone())),
-        None => Err(Status::InternalServerError),
-    }
+/// 以 JSON 形式获取仪表板的上下文，不论请求的 `Accept` 头是什么，便于从其他页面嵌入。参数与 `/dashboard` 相同。
+#[get("/dashboard/embed?<dark>&<machine>&<compact>")]
+#[allow(clippy::too_many_arguments)]
+fn embed_dashboard(
+    stats_history: &State<UpdatingStatsHistory>,
+    remote_stats_histories: &State<RemoteStatsHistories>,
+    dashboard_config: &State<DashboardConfig>,
+    annotations: &State<Annotations>,
+    json_field_case: &State<JsonFieldCase>,
+    dark: Option<bool>,
+    machine: Option<&str>,
+    compact: Option<bool>,
+) -> Result<Json<WithFieldCase<DashboardContext>>, ApiError> {
+    machine_dashboard_context(
+        stats_history,
+        remote_stats_histories,
+        dashboard_config,
+        annotations,
+        dark,
+        machine,
+        compact,
+    )
+    .map(|x| Json(WithFieldCase::new(x, **json_field_case)))
+    .map_err(|_| ApiError::new(Status::NotFound, "no such remote agent"))
 }
 
-/// 获取内存统计信息
-#[get("/stats/memory")]
-fn get_memory_stats() -> Result<Json<MemoryStats>, Status> {
-    match MemoryStats::from(&System::new()) {
-        Some(x) => Ok(Json(x)),
-        None => Err(Status::InternalServerError),
-    }
+/// 构建本机或一个远程代理的仪表板的上下文。如果没有名为 `machine` 的远程代理，则返回错误消息。
+///
+/// # 参数
+/// * `machine` - 远程代理的名称。为 `None` 时使用本机的统计信息。
+/// * `compact` - 是否返回紧凑的上下文。为 `None` 时使用 `dashboard_config.compact`。
+fn machine_dashboard_context(
+    stats_history: &UpdatingStatsHistory,
+    remote_stats_histories: &RemoteStatsHistories,
+    dashboard_config: &DashboardConfig,
+    annotations: &Annotations,
+    dark: Option<bool>,
+    machine: Option<&str>,
+    compact: Option<bool>,
+) -> Result<DashboardContext, String> {
+    let stats_history = match machine {
+        Some(machine) => match remote_stats_histories
+            .histories
+            .iter()
+            .find(|(name, _)| name == machine)
+        {
+            Some((_, x)) => x,
+            None => return Err(format!("No remote agent named '{}'.", machine)),
+        },
+        None => stats_history,
+    };
+    let context = DashboardContext::from_history(
+        &stats_history.stats_history.lock().unwrap(),
+        dark,
+        dashboard_config,
+        true,
+    )
+    .with_machines(
+        remote_stats_histories
+            .histories
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect(),
+        machine.map(|x| x.to_string()),
+    )
+    .with_records(&stats_history.records(), dashboard_config)
+    .with_annotations(&annotations.list());
+
+    Ok(if compact.unwrap_or(dashboard_config.compact) {
+        context.into_compact()
+    } else {
+        context
+    })
 }
 
-/// 获取文件系统统计信息
-#[get("/stats/filesystems")]
-fn get_filesystem_stats() -> Result<Json<Vec<MountStats>>, Status> {
-    match MountStats::from(&System::new()) {
-        Some(x) => Ok(Json(x)),
-        None => Err(Status::InternalServerError),
+/// 查看合并所有远程代理的统计信息的机群仪表板
+#[get("/dashboard/fleet?<dark>")]
+fn fleet_dashboard(
+    remote_stats_histories: &State<RemoteStatsHistories>,
+    dashboard_config: &State<DashboardConfig>,
+    annotations: &State<Annotations>,
+    dark: Option<bool>,
+) -> Template {
+    if remote_stats_histories.histories.is_empty() {
+        return Template::render(
+            "error",
+            &ErrorContext {
+                title: "Fleet Dashboard".to_string(),
+                message: "No remote agents are configured.".to_string(),
+            },
+        );
     }
-}
 
-/// 获取网络统计信息
-#[get("/stats/network")]
-fn get_network_stats() -> Json<NetworkStats> {
-    Json(NetworkStats::from(&System::new()))
+    let guards: Vec<_> = remote_stats_histories
+        .histories
+        .iter()
+        .map(|(name, x)| (name.clone(), x.stats_history.lock().unwrap()))
+        .collect();
+    let histories: Vec<(String, &StatsHistory)> = guards
+        .iter()
+        .map(|(name, guard)| (name.clone(), &**guard))
+        .collect();
+    let context = DashboardContext::from_fleet(&histories, dark, dashboard_config)
+        .with_machines(
+            histories.iter().map(|(name, _)| name.clone()).collect(),
+            None,
+        )
+        .with_annotations(&annotations.list());
+    Template::render("dashboard", &context)
 }
 
-/// 首页 - 转发到查看仪表板
-#[get("/")]
-fn index() -> Redirect {
-    Redirect::to(rocket::uri!(dashboard(Some(true))))
+/// 全屏查看仪表板中的单个图表
+#[get("/dashboard/chart/<id>?<dark>")]
+fn chart_dashboard(
+    stats_history: &State<UpdatingStatsHistory>,
+    dashboard_config: &State<DashboardConfig>,
+    annotations: &State<Annotations>,
+    id: &str,
+    dark: Option<bool>,
+) -> Template {
+    let context = DashboardContext::from_history(
+        &stats_history.stats_history.lock().unwrap(),
+        dark,
+        dashboard_config,
+        false,
+    )
+    .with_annotations(&annotations.list());
+    match context.into_single_chart(id) {
+        Some(x) => Template::render("single_chart", &x),
+        None => Template::render(
+            "error",
+            &ErrorContext {
+                title: "Chart".to_string(),
+                message: format!("No chart with ID '{}'.", id),
+            },
+        ),
+    }
 }
 
-/// 查看仪表板
-#[get("/dashboard?<dark>")]
-fn dashboard(stats_history: &State<UpdatingStatsHistory>, dark: Option<bool>) -> Template {
+/// 查看只有迷你图的仪表板，用于通过 iframe 嵌入到其他页面中
+#[get("/dashboard/mini?<dark>")]
+fn mini_dashboard(
+    stats_history: &State<UpdatingStatsHistory>,
+    dashboard_config: &State<DashboardConfig>,
+    annotations: &State<Annotations>,
+    dark: Option<bool>,
+) -> Template {
     let context = DashboardContext::from_history(
         &stats_history.stats_history.lock().unwrap(),
-        dark.unwrap_or(DEFAULT_DARK_MODE),
-    );
-    Template::render("dashboard", &context)
+        dark,
+        dashboard_config,
+        true,
+    )
+    .with_annotations(&annotations.list());
+    Template::render("mini_dashboard", context.into_minimal())
 }
 
-/// 用于查看持久统计信息仪表板（历史信息）
-#[get("/dashboard/history?<dark>")]
+/// 用于查看持久统计信息仪表板（历史信息）。如果提供了 `hours`，则只显示最近几个小时的统计信息。
+/// 如果提供了 `page` 或 `page_size`，则只显示统计信息的一页，并显示翻页链接。
+#[get("/dashboard/history?<dark>&<hours>&<page>&<page_size>")]
+#[allow(clippy::too_many_arguments)]
 fn history_dashboard(
     history_persistence_config: &State<HistoryPersistenceConfig>,
+    dashboard_config: &State<DashboardConfig>,
+    base_path_config: &State<BasePathConfig>,
+    annotations: &State<Annotations>,
     dark: Option<bool>,
+    hours: Option<u32>,
+    page: Option<usize>,
+    page_size: Option<usize>,
 ) -> Result<Template, Status> {
     match history_persistence_config.inner() {
-        HistoryPersistenceConfig::Enabled { dir, size_limit: _ } => {
-            let history = match StatsHistory::load_from(dir) {
-                Ok(x) => x,
-                Err(e) => {
-                    println!("Error loading persisted stats from {:?}: {}", dir, e);
-                    return Err(Status::InternalServerError);
+        HistoryPersistenceConfig::Enabled {
+            dir,
+            size_limit,
+            long_term,
+            ..
+        } => {
+            let history = load_persisted_history(dir, hours)?;
+            let bytes_used = history_files_size(dir).map_err(|e| {
+                println!(
+                    "Error getting the size of persisted stats in {:?}: {}",
+                    dir, e
+                );
+                Status::InternalServerError
+            })?;
+            let (history, interval_factor) = match long_term {
+                Some(long_term) => {
+                    let long_term_history = load_persisted_history(&long_term.dir, hours)?;
+                    // 长期历史中条目之间的间隔更长，放宽数据中断的判断以免将其误认为中断
+                    (
+                        history.merged_with_long_term(&long_term_history),
+                        long_term.consolidation_factor.get() as u32,
+                    )
+                }
+                None => (history, 1),
+            };
+            let dashboard_config = DashboardConfig {
+                expected_sample_interval: dashboard_config.expected_sample_interval
+                    * interval_factor,
+                quiet_hours: dashboard_config
+                    .quiet_hours
+                    .map(|(quiet_hours, interval)| (quiet_hours, interval * interval_factor)),
+                ..dashboard_config.inner().clone()
+            };
+            // 加载了所有持久化的统计信息时，才能确定保留的时间范围
+            if hours.is_none() {
+                if let Some(oldest) = history.oldest_retained_time() {
+                    annotations.prune_before(oldest);
                 }
+            }
+            let annotations = annotations.list();
+            if page.is_none() && page_size.is_none() {
+                let context =
+                    DashboardContext::from_history(&history, dark, &dashboard_config, false)
+                        .with_annotations(&annotations)
+                        .with_history_usage(bytes_used, *size_limit, &dashboard_config);
+                return Ok(Template::render("dashboard", &context));
+            }
+
+            let page = page.unwrap_or(1);
+            let page_size = NonZeroUsize::new(page_size.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE))
+                .ok_or(Status::BadRequest)?;
+            let page_count = history.page_count(page_size);
+            if page == 0 || page > page_count.max(1) {
+                return Err(Status::NotFound);
+            }
+            // uri! 会将参数绑定到与路由参数同名的局部变量，直接传入 `dark` 和 `hours` 会触发 clippy::redundant_locals
+            let (link_dark, link_hours) = (dark, hours);
+            let page_url = |page: usize| {
+                rocket::uri!(
+                    base_path_config.prefix.clone(),
+                    history_dashboard(link_dark, link_hours, Some(page), Some(page_size.get()))
+                )
+                .to_string()
             };
-            let context =
-                DashboardContext::from_history(&history, dark.unwrap_or(DEFAULT_DARK_MODE));
+            let context = DashboardContext::from_history(
+                &history.page(page, page_size, dashboard_config.history_page_order),
+                dark,
+                &dashboard_config,
+                false,
+            )
+            .with_annotations(&annotations)
+            .with_history_usage(bytes_used, *size_limit, &dashboard_config)
+            .with_pagination(PaginationContext {
+                page,
+                page_count,
+                previous_url: if page > 1 {
+                    Some(page_url(page - 1))
+                } else {
+                    None
+                },
+                next_url: if page < page_count {
+                    Some(page_url(page + 1))
+                } else {
+                    None
+                },
+            });
             Ok(Template::render("dashboard", &context))
         }
         HistoryPersistenceConfig::Disabled => Ok(Template::render(
@@ -156,89 +1337,796 @@ fn history_dashboard(
     }
 }
 
+/// 从目录加载持久化的统计信息。如果提供了 `hours`，则只加载最近几个小时的统计信息。
+///
+/// # 参数
+/// * `dir` - 加载统计信息的目录
+/// * `hours` - 要加载的小时数
+fn load_persisted_history(dir: &Path, hours: Option<u32>) -> Result<StatsHistory, Status> {
+    let loaded = match hours {
+        Some(hours) => {
+            let now = Local::now();
+            StatsHistory::load_range(dir, now - chrono::Duration::hours(hours.into()), now)
+        }
+        None => StatsHistory::load_from(dir),
+    };
+    loaded.map_err(|e| {
+        println!("Error loading persisted stats from {:?}: {}", dir, e);
+        Status::InternalServerError
+    })
+}
+
 #[launch]
 fn rocket() -> Rocket<rocket::Build> {
-    let mut rocket = rocket::build()
+    // 优先级：环境变量 > Rocket.toml > 默认值
+    let figment = rocket::Config::figment().merge(Env::prefixed(CONFIG_ENV_VAR_PREFIX).global());
+    let mut rocket = rocket::custom(figment)
         .mount(
             "/",
             rocket::routes![
                 get_all_stats,
+                stats_events,
+                get_text_stats,
                 get_general_stats,
+                get_uptime_stats,
                 get_cpu_stats,
+                get_per_core_cpu_stats,
                 get_memory_stats,
                 get_filesystem_stats,
+                get_mount_stats,
+                ping,
+                get_disk_health,
+                get_busy_score,
+                get_window_stats,
                 get_network_stats,
+                get_stats_diff,
+                pause_collection,
+                resume_collection,
+                sample_stats,
+                get_annotations,
+                add_annotation,
+                get_collection_status,
+                get_collector_health,
+                get_stuck_metrics,
+                get_records,
+                get_history_info,
+                get_stats_history,
+                get_raw_stats_history,
+                get_capabilities,
+                get_config,
+                get_debug_snapshot,
+                favicon,
                 index,
                 dashboard,
+                embed_dashboard,
+                fleet_dashboard,
+                chart_dashboard,
+                mini_dashboard,
                 history_dashboard,
             ],
         )
+        .register(STATIC_FILES_PATH, catchers![static_file_not_found])
         .attach(Template::fairing());
 
     let config = rocket.figment();
+    let mut resolved_values = BTreeMap::new();
+    if let Ok(rocket_config) = config.extract::<rocket::Config>() {
+        resolved_values.insert(
+            "address".to_string(),
+            serde_json::json!(rocket_config.address),
+        );
+        resolved_values.insert("port".to_string(), serde_json::json!(rocket_config.port));
+    }
 
-    let update_frequency_secs = get_config_value(
+    let static_files_dir = get_config_value(
         config,
+        &mut resolved_values,
+        STATIC_FILES_DIRECTORY_CONFIG_KEY,
+        DEFAULT_STATIC_FILES_DIRECTORY.to_string(),
+    );
+
+    let mut update_frequency_secs = get_config_value(
+        config,
+        &mut resolved_values,
         UPDATE_FREQUENCY_CONFIG_KEY,
         DEFAULT_UPDATE_FREQUENCY_SECONDS,
     );
+    // 收集频率不长于 CPU 采样时间时，收集线程会不停地收集
+    if Duration::from_secs(update_frequency_secs) <= CPU_LOAD_SAMPLE_DURATION {
+        println!(
+            "{} 必须长于 CPU 采样时间 {:?}，改为使用默认值 {}",
+            UPDATE_FREQUENCY_CONFIG_KEY, CPU_LOAD_SAMPLE_DURATION, DEFAULT_UPDATE_FREQUENCY_SECONDS
+        );
+        update_frequency_secs = DEFAULT_UPDATE_FREQUENCY_SECONDS;
+        resolved_values.insert(
+            UPDATE_FREQUENCY_CONFIG_KEY.to_string(),
+            serde_json::json!(update_frequency_secs),
+        );
+    }
+
+    let sample_jitter_ms = get_config_value(
+        config,
+        &mut resolved_values,
+        SAMPLE_JITTER_CONFIG_KEY,
+        DEFAULT_SAMPLE_JITTER_MS,
+    );
+
+    let mut quiet_hours: Option<QuietHours> = get_config_value(
+        config,
+        &mut resolved_values,
+        QUIET_HOURS_CONFIG_KEY,
+        DEFAULT_QUIET_HOURS,
+    );
+    if let Some(x) = &quiet_hours {
+        if x.update_frequency() <= CPU_LOAD_SAMPLE_DURATION {
+            println!(
+                "{} 的 update_frequency_seconds 必须长于 CPU 采样时间 {:?}，忽略安静时段",
+                QUIET_HOURS_CONFIG_KEY, CPU_LOAD_SAMPLE_DURATION
+            );
+            quiet_hours = None;
+            resolved_values.insert(QUIET_HOURS_CONFIG_KEY.to_string(), serde_json::Value::Null);
+        }
+    }
 
-    let recent_history_size = get_config_value(
+    let recent_history_size = get_non_zero_config_value(
         config,
+        &mut resolved_values,
         RECENT_HISTORY_SIZE_CONFIG_KEY,
         DEFAULT_RECENT_HISTORY_SIZE,
     );
 
-    let consolidation_limit = get_config_value(
+    let max_history_response_entries = get_non_zero_config_value(
+        config,
+        &mut resolved_values,
+        MAX_HISTORY_RESPONSE_ENTRIES_CONFIG_KEY,
+        DEFAULT_MAX_HISTORY_RESPONSE_ENTRIES,
+    );
+    let raw_history_size = get_config_value(
+        config,
+        &mut resolved_values,
+        RAW_HISTORY_SIZE_CONFIG_KEY,
+        DEFAULT_RAW_HISTORY_SIZE,
+    );
+    let cpu_sparse_output = get_config_value(
+        config,
+        &mut resolved_values,
+        CPU_SPARSE_OUTPUT_CONFIG_KEY,
+        DEFAULT_CPU_SPARSE_OUTPUT,
+    );
+    let cpu_sparse_threshold_percent = if cpu_sparse_output {
+        Some(get_config_value(
+            config,
+            &mut resolved_values,
+            CPU_SPARSE_THRESHOLD_CONFIG_KEY,
+            DEFAULT_CPU_SPARSE_THRESHOLD_PERCENT,
+        ))
+    } else {
+        None
+    };
+
+    let consolidation_interval_secs = get_config_value(
+        config,
+        &mut resolved_values,
+        CONSOLIDATION_INTERVAL_CONFIG_KEY,
+        DEFAULT_CONSOLIDATION_INTERVAL_SECONDS,
+    );
+    // 设置了合并间隔时按时间合并，并忽略 `consolidation_limit`
+    let consolidation_trigger = match consolidation_interval_secs {
+        Some(secs) if secs > 0 => {
+            if config.find_value(CONSOLIDATION_LIMIT_CONFIG_KEY).is_ok() {
+                println!(
+                    "同时设置了 {} 和 {}，将按时间合并并忽略 {}",
+                    CONSOLIDATION_INTERVAL_CONFIG_KEY,
+                    CONSOLIDATION_LIMIT_CONFIG_KEY,
+                    CONSOLIDATION_LIMIT_CONFIG_KEY
+                );
+            }
+            ConsolidationTrigger::Interval(Duration::from_secs(secs))
+        }
+        _ => ConsolidationTrigger::Count(get_non_zero_config_value(
+            config,
+            &mut resolved_values,
+            CONSOLIDATION_LIMIT_CONFIG_KEY,
+            DEFAULT_CONSOLIDATION_LIMIT,
+        )),
+    };
+
+    let allow_collection_control = get_config_value(
+        config,
+        &mut resolved_values,
+        ALLOW_COLLECTION_CONTROL_CONFIG_KEY,
+        DEFAULT_ALLOW_COLLECTION_CONTROL,
+    );
+
+    let allow_config_export = get_config_value(
+        config,
+        &mut resolved_values,
+        ALLOW_CONFIG_EXPORT_CONFIG_KEY,
+        DEFAULT_ALLOW_CONFIG_EXPORT,
+    );
+    let allow_debug_snapshot = get_config_value(
+        config,
+        &mut resolved_values,
+        ALLOW_DEBUG_SNAPSHOT_CONFIG_KEY,
+        DEFAULT_ALLOW_DEBUG_SNAPSHOT,
+    );
+
+    let allow_adding_annotations = get_config_value(
+        config,
+        &mut resolved_values,
+        ALLOW_ADDING_ANNOTATIONS_CONFIG_KEY,
+        DEFAULT_ALLOW_ADDING_ANNOTATIONS,
+    );
+    let max_annotations = get_non_zero_config_value(
+        config,
+        &mut resolved_values,
+        MAX_ANNOTATIONS_CONFIG_KEY,
+        DEFAULT_MAX_ANNOTATIONS,
+    );
+
+    let compress_responses = get_config_value(
+        config,
+        &mut resolved_values,
+        COMPRESS_RESPONSES_CONFIG_KEY,
+        DEFAULT_COMPRESS_RESPONSES,
+    );
+    let json_field_case = get_config_value(
+        config,
+        &mut resolved_values,
+        JSON_FIELD_CASE_CONFIG_KEY,
+        DEFAULT_JSON_FIELD_CASE,
+    );
+
+    let collector_config = CollectorConfig {
+        disabled_collectors: get_config_value(
+            config,
+            &mut resolved_values,
+            DISABLED_COLLECTORS_CONFIG_KEY,
+            DEFAULT_DISABLED_COLLECTORS,
+        ),
+        mount_collection_timeout: Duration::from_millis(get_config_value(
+            config,
+            &mut resolved_values,
+            MOUNT_COLLECTION_TIMEOUT_MS_CONFIG_KEY,
+            DEFAULT_MOUNT_COLLECTION_TIMEOUT_MS,
+        )),
+        log_unsupported_once: get_config_value(
+            config,
+            &mut resolved_values,
+            LOG_UNSUPPORTED_ONCE_CONFIG_KEY,
+            DEFAULT_LOG_UNSUPPORTED_ONCE,
+        ),
+        concurrent_collection: get_config_value(
+            config,
+            &mut resolved_values,
+            CONCURRENT_COLLECTION_CONFIG_KEY,
+            DEFAULT_CONCURRENT_COLLECTION,
+        ),
+    };
+
+    let remote_agents: Vec<String> = get_config_value(
+        config,
+        &mut resolved_values,
+        REMOTE_AGENTS_CONFIG_KEY,
+        DEFAULT_REMOTE_AGENTS,
+    );
+    let remote_agents: Vec<RemoteAgent> = remote_agents
+        .iter()
+        .filter_map(|url| match RemoteAgent::parse(url) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                println!("忽略无效的远程代理: {}", e);
+                None
+            }
+        })
+        .collect();
+
+    let fleet_aggregation = get_config_value(
+        config,
+        &mut resolved_values,
+        FLEET_AGGREGATION_CONFIG_KEY,
+        DEFAULT_FLEET_AGGREGATION,
+    );
+
+    let busy_score_weights = get_config_value(
+        config,
+        &mut resolved_values,
+        BUSY_SCORE_WEIGHTS_CONFIG_KEY,
+        BusyScoreWeights::default(),
+    );
+
+    let memory_metric = get_config_value(
+        config,
+        &mut resolved_values,
+        MEMORY_METRIC_CONFIG_KEY,
+        DEFAULT_MEMORY_METRIC,
+    );
+
+    let compact_dashboard = get_config_value(
+        config,
+        &mut resolved_values,
+        COMPACT_DASHBOARD_CONFIG_KEY,
+        DEFAULT_COMPACT_DASHBOARD,
+    );
+
+    let time_label_dates = get_config_value(
+        config,
+        &mut resolved_values,
+        TIME_LABEL_DATES_CONFIG_KEY,
+        DEFAULT_TIME_LABEL_DATES,
+    );
+
+    let load_average_windows = get_config_value(
+        config,
+        &mut resolved_values,
+        LOAD_AVERAGE_WINDOWS_CONFIG_KEY,
+        DEFAULT_LOAD_AVERAGE_WINDOWS.to_vec(),
+    );
+
+    let allowed_origins = get_config_value(
+        config,
+        &mut resolved_values,
+        ALLOWED_ORIGINS_CONFIG_KEY,
+        DEFAULT_ALLOWED_ORIGINS,
+    );
+
+    let base_path = get_config_value(
+        config,
+        &mut resolved_values,
+        BASE_PATH_CONFIG_KEY,
+        DEFAULT_BASE_PATH.to_string(),
+    );
+    let base_path = match Origin::parse_owned(base_path) {
+        Ok(x) => x.into_normalized(),
+        Err(e) => {
+            println!(
+                "无效的 {}，使用默认值 {}: {}",
+                BASE_PATH_CONFIG_KEY, DEFAULT_BASE_PATH, e
+            );
+            Origin::ROOT
+        }
+    };
+
+    let default_route = get_config_value(
+        config,
+        &mut resolved_values,
+        DEFAULT_ROUTE_CONFIG_KEY,
+        DEFAULT_DEFAULT_ROUTE.to_string(),
+    );
+    let default_route = match parse_default_route(&default_route, &base_path) {
+        Ok(x) => x,
+        Err(e) => {
+            println!(
+                "无效的 {}，使用默认值 {}: {}",
+                DEFAULT_ROUTE_CONFIG_KEY, DEFAULT_DEFAULT_ROUTE, e
+            );
+            // 默认值一定有效
+            parse_default_route(DEFAULT_DEFAULT_ROUTE, &base_path).unwrap()
+        }
+    };
+
+    let display_precision = get_config_value(
+        config,
+        &mut resolved_values,
+        DISPLAY_PRECISION_CONFIG_KEY,
+        DEFAULT_DISPLAY_PRECISION,
+    );
+
+    let y_tick_count = get_config_value(
+        config,
+        &mut resolved_values,
+        Y_TICK_COUNT_CONFIG_KEY,
+        DEFAULT_Y_TICK_COUNT,
+    );
+
+    let network_per_interface_charts = get_config_value(
+        config,
+        &mut resolved_values,
+        NETWORK_PER_INTERFACE_CHARTS_CONFIG_KEY,
+        DEFAULT_NETWORK_PER_INTERFACE_CHARTS,
+    );
+
+    let network_stacked = get_config_value(
+        config,
+        &mut resolved_values,
+        NETWORK_STACKED_CONFIG_KEY,
+        DEFAULT_NETWORK_STACKED,
+    );
+
+    let max_interface_charts = get_config_value(
+        config,
+        &mut resolved_values,
+        MAX_INTERFACE_CHARTS_CONFIG_KEY,
+        DEFAULT_MAX_INTERFACE_CHARTS,
+    );
+    let include_loopback = get_config_value(
+        config,
+        &mut resolved_values,
+        INCLUDE_LOOPBACK_CONFIG_KEY,
+        DEFAULT_INCLUDE_LOOPBACK,
+    );
+    let interface_aliases = get_config_value(
+        config,
+        &mut resolved_values,
+        INTERFACE_ALIASES_CONFIG_KEY,
+        DEFAULT_INTERFACE_ALIASES,
+    );
+
+    let anomaly_detection = get_config_value(
+        config,
+        &mut resolved_values,
+        ANOMALY_DETECTION_CONFIG_KEY,
+        DEFAULT_ANOMALY_DETECTION,
+    );
+
+    let chart_order = get_config_value(
+        config,
+        &mut resolved_values,
+        CHART_ORDER_CONFIG_KEY,
+        DEFAULT_CHART_ORDER,
+    );
+
+    let chart_smoothing_window = get_non_zero_config_value(
+        config,
+        &mut resolved_values,
+        CHART_SMOOTHING_WINDOW_CONFIG_KEY,
+        DEFAULT_CHART_SMOOTHING_WINDOW,
+    )
+    .get();
+
+    let chart_smoothing_windows = get_config_value(
+        config,
+        &mut resolved_values,
+        CHART_SMOOTHING_WINDOWS_CONFIG_KEY,
+        DEFAULT_CHART_SMOOTHING_WINDOWS,
+    );
+
+    let history_page_order = get_config_value(
+        config,
+        &mut resolved_values,
+        HISTORY_PAGE_ORDER_CONFIG_KEY,
+        DEFAULT_HISTORY_PAGE_ORDER,
+    );
+
+    let min_max_bands = get_config_value(
+        config,
+        &mut resolved_values,
+        MIN_MAX_BANDS_CONFIG_KEY,
+        DEFAULT_MIN_MAX_BANDS,
+    );
+    let cpu_histogram = get_config_value(
+        config,
+        &mut resolved_values,
+        CPU_HISTOGRAM_CONFIG_KEY,
+        DEFAULT_CPU_HISTOGRAM,
+    );
+
+    let chart_fill_opacity = get_config_value(
+        config,
+        &mut resolved_values,
+        CHART_FILL_OPACITY_CONFIG_KEY,
+        DEFAULT_CHART_FILL_OPACITY,
+    );
+
+    let filesystem_warning_percent = get_config_value(
+        config,
+        &mut resolved_values,
+        FILESYSTEM_WARNING_PERCENT_CONFIG_KEY,
+        DEFAULT_FILESYSTEM_WARNING_PERCENT,
+    );
+    let filesystem_critical_percent = get_config_value(
+        config,
+        &mut resolved_values,
+        FILESYSTEM_CRITICAL_PERCENT_CONFIG_KEY,
+        DEFAULT_FILESYSTEM_CRITICAL_PERCENT,
+    );
+    let filesystem_unit_scaling = get_config_value(
+        config,
+        &mut resolved_values,
+        FILESYSTEM_UNIT_SCALING_CONFIG_KEY,
+        DEFAULT_FILESYSTEM_UNIT_SCALING,
+    );
+    let disk_free_bytes_min = get_config_value(
+        config,
+        &mut resolved_values,
+        DISK_FREE_BYTES_MIN_CONFIG_KEY,
+        DEFAULT_DISK_FREE_BYTES_MIN,
+    );
+    let disk_health_excluded_mounts: Vec<String> = get_config_value(
+        config,
+        &mut resolved_values,
+        DISK_HEALTH_EXCLUDED_MOUNTS_CONFIG_KEY,
+        DEFAULT_DISK_HEALTH_EXCLUDED_MOUNTS,
+    );
+    let disk_health_excluded_fs_types: Vec<String> = get_config_value(
+        config,
+        &mut resolved_values,
+        DISK_HEALTH_EXCLUDED_FS_TYPES_CONFIG_KEY,
+        DEFAULT_DISK_HEALTH_EXCLUDED_FS_TYPES,
+    );
+    let entropy_available_min = get_config_value(
+        config,
+        &mut resolved_values,
+        ENTROPY_AVAILABLE_MIN_CONFIG_KEY,
+        DEFAULT_ENTROPY_AVAILABLE_MIN,
+    );
+    let stuck_metric_entries = get_config_value(
         config,
-        CONSOLIDATION_LIMIT_CONFIG_KEY,
-        DEFAULT_CONSOLIDATION_LIMIT,
+        &mut resolved_values,
+        STUCK_METRIC_ENTRIES_CONFIG_KEY,
+        DEFAULT_STUCK_METRIC_ENTRIES,
     );
 
     let history_persistence_enabled = get_config_value(
         config,
+        &mut resolved_values,
         PERSIST_HISTORY_TOGGLE_CONFIG_KEY,
         DEFAULT_PERSIST_HISTORY_TOGGLE,
     );
     let persistence_config = if history_persistence_enabled {
         let history_files_dir = get_config_value(
             config,
+            &mut resolved_values,
             HISTORY_FILES_DIRECTORY_CONFIG_KEY,
             DEFAULT_HISTORY_FILES_DIRECTORY.to_string(),
         );
-        let history_files_dir_max_size = get_config_value(
+        let mut history_files_dir_max_size = get_config_value(
             config,
+            &mut resolved_values,
             HISTORY_FILES_DIRECTORY_MAX_SIZE_CONFIG_KEY,
             DEFAULT_HISTORY_FILES_DIRECTORY_MAX_SIZE_BYTES,
         );
+        // 大小限制为 0 时每次写入都会轮换文件，也无法计算使用百分比
+        if history_files_dir_max_size == 0 {
+            println!(
+                "{} 不能为 0，改为使用默认值 {}",
+                HISTORY_FILES_DIRECTORY_MAX_SIZE_CONFIG_KEY,
+                DEFAULT_HISTORY_FILES_DIRECTORY_MAX_SIZE_BYTES
+            );
+            history_files_dir_max_size = DEFAULT_HISTORY_FILES_DIRECTORY_MAX_SIZE_BYTES;
+            resolved_values.insert(
+                HISTORY_FILES_DIRECTORY_MAX_SIZE_CONFIG_KEY.to_string(),
+                serde_json::json!(history_files_dir_max_size),
+            );
+        }
+        let history_file_count = get_non_zero_config_value(
+            config,
+            &mut resolved_values,
+            HISTORY_FILE_COUNT_CONFIG_KEY,
+            DEFAULT_HISTORY_FILE_COUNT,
+        );
+        let retry_buffer_size = get_config_value(
+            config,
+            &mut resolved_values,
+            PERSISTENCE_RETRY_BUFFER_SIZE_CONFIG_KEY,
+            DEFAULT_PERSISTENCE_RETRY_BUFFER_SIZE,
+        );
+        let persistence_format = get_config_value(
+            config,
+            &mut resolved_values,
+            PERSISTENCE_FORMAT_CONFIG_KEY,
+            DEFAULT_PERSISTENCE_FORMAT,
+        );
+        let persistence_mode = get_config_value(
+            config,
+            &mut resolved_values,
+            PERSISTENCE_MODE_CONFIG_KEY,
+            DEFAULT_PERSISTENCE_MODE,
+        );
+        let long_term_history_dir = get_config_value(
+            config,
+            &mut resolved_values,
+            LONG_TERM_HISTORY_DIRECTORY_CONFIG_KEY,
+            DEFAULT_LONG_TERM_HISTORY_DIRECTORY,
+        );
+        let long_term = long_term_history_dir.map(|dir| {
+            let consolidation_factor = get_non_zero_config_value(
+                config,
+                &mut resolved_values,
+                LONG_TERM_CONSOLIDATION_FACTOR_CONFIG_KEY,
+                DEFAULT_LONG_TERM_CONSOLIDATION_FACTOR,
+            );
+            LongTermPersistenceConfig {
+                dir: dir.into(),
+                consolidation_factor,
+            }
+        });
+        let persistence_change_threshold_percent = get_config_value(
+            config,
+            &mut resolved_values,
+            PERSISTENCE_CHANGE_THRESHOLD_CONFIG_KEY,
+            DEFAULT_PERSISTENCE_CHANGE_THRESHOLD_PERCENT,
+        );
+        // 阈值为 0 时任何变化都会持久化，相当于不过滤
+        let filter = if persistence_change_threshold_percent > 0.0 {
+            let max_gap_seconds = get_config_value(
+                config,
+                &mut resolved_values,
+                PERSISTENCE_MAX_GAP_CONFIG_KEY,
+                DEFAULT_PERSISTENCE_MAX_GAP_SECONDS,
+            );
+            Some(PersistenceFilter {
+                change_threshold_percent: persistence_change_threshold_percent,
+                max_gap: Duration::from_secs(max_gap_seconds),
+            })
+        } else {
+            None
+        };
+        let restrict_permissions = get_config_value(
+            config,
+            &mut resolved_values,
+            RESTRICT_HISTORY_PERMISSIONS_CONFIG_KEY,
+            DEFAULT_RESTRICT_HISTORY_PERMISSIONS,
+        );
         HistoryPersistenceConfig::Enabled {
             dir: history_files_dir.into(),
             size_limit: history_files_dir_max_size,
+            format: persistence_format,
+            mode: persistence_mode,
+            long_term,
+            filter,
+            restrict_permissions,
+            file_count: history_file_count,
+            retry_buffer_size,
         }
     } else {
         HistoryPersistenceConfig::Disabled
     };
 
+    let update_frequency = Duration::from_secs(update_frequency_secs);
+    // 启用持久化时，注释保存在统计历史目录中
+    let annotations = match &persistence_config {
+        HistoryPersistenceConfig::Enabled {
+            dir,
+            restrict_permissions,
+            ..
+        } => Annotations::new(Some(dir), *restrict_permissions, max_annotations),
+        HistoryPersistenceConfig::Disabled => Annotations::new(None, false, max_annotations),
+    };
+
+    if compress_responses {
+        rocket = rocket.attach(ResponseCompression);
+    }
+    if !allowed_origins.is_empty() {
+        rocket = rocket.attach(Cors::new(allowed_origins));
+    }
+
     rocket = rocket
+        // 目录不存在时只记录警告，而不是启动失败
+        .mount(
+            STATIC_FILES_PATH,
+            FileServer::new(static_files_dir, Options::Missing),
+        )
+        .manage(DashboardConfig {
+            // 每次合并后才会向历史记录添加一个新条目
+            expected_sample_interval: consolidation_trigger
+                .expected_entry_interval(update_frequency),
+            // 安静时段内收集频率较低，条目之间的间隔也更长，不应该被视为数据中断
+            quiet_hours: quiet_hours.map(|x| {
+                (
+                    x,
+                    consolidation_trigger.expected_entry_interval(x.update_frequency()),
+                )
+            }),
+            display_precision,
+            network_per_interface_charts,
+            network_stacked,
+            max_interface_charts,
+            include_loopback,
+            interface_aliases,
+            anomaly_detection,
+            min_max_bands,
+            chart_order,
+            chart_smoothing_window,
+            chart_smoothing_windows,
+            history_page_order,
+            base_path: base_path.path().as_str().trim_end_matches('/').to_string(),
+            chart_fill_opacity,
+            filesystem_warning_percent,
+            filesystem_critical_percent,
+            filesystem_unit_scaling,
+            entropy_available_min,
+            stuck_metric_entries,
+            y_tick_count,
+            fleet_aggregation,
+            busy_score_weights,
+            time_label_dates,
+            load_average_windows,
+            memory_metric,
+            compact: compact_dashboard,
+            cpu_histogram,
+        })
+        .manage(DiskHealthConfig {
+            max_used_percent: filesystem_critical_percent,
+            min_free_bytes: disk_free_bytes_min,
+            excluded_mounts: disk_health_excluded_mounts,
+            excluded_fs_types: disk_health_excluded_fs_types,
+        })
+        .manage(BasePathConfig { prefix: base_path })
+        .manage(json_field_case)
+        .manage(DefaultRouteConfig {
+            target: default_route,
+        })
+        .manage(CollectionControlConfig {
+            allowed: allow_collection_control,
+        })
+        .manage(HistoryResponseConfig {
+            max_entries: max_history_response_entries,
+        })
+        .manage(RawHistoryConfig {
+            size: raw_history_size,
+        })
+        .manage(CpuResponseConfig {
+            sparse_threshold_percent: cpu_sparse_threshold_percent,
+        })
+        .manage(ResolvedConfig {
+            export_allowed: allow_config_export,
+            debug_snapshot_allowed: allow_debug_snapshot,
+            values: resolved_values,
+        })
         .manage(persistence_config.clone())
+        .manage(annotations)
+        .manage(AnnotationConfig {
+            adding_allowed: allow_adding_annotations,
+        })
+        .manage(collector_config.clone())
+        .manage(MountCollection::default())
+        .manage(RemoteStatsHistories {
+            // 远程代理的统计信息只保存在内存中
+            histories: remote_agents
+                .into_iter()
+                .map(|agent| {
+                    (
+                        agent.name(),
+                        UpdatingStatsHistory::new(
+                            StatsSource::Remote(agent),
+                            CPU_LOAD_SAMPLE_DURATION,
+                            update_frequency,
+                            Duration::from_millis(sample_jitter_ms),
+                            quiet_hours,
+                            recent_history_size,
+                            raw_history_size,
+                            consolidation_trigger,
+                            HistoryPersistenceConfig::Disabled,
+                            collector_config.clone(),
+                        ),
+                    )
+                })
+                .collect(),
+        })
         .manage(UpdatingStatsHistory::new(
-            System::new(),
+            StatsSource::Local(System::new()),
             CPU_LOAD_SAMPLE_DURATION,
-            Duration::from_secs(update_frequency_secs),
-            NonZeroUsize::new(recent_history_size).unwrap(),
-            NonZeroUsize::new(consolidation_limit).unwrap(),
+            update_frequency,
+            Duration::from_millis(sample_jitter_ms),
+            quiet_hours,
+            recent_history_size,
+            raw_history_size,
+            consolidation_trigger,
             persistence_config,
+            collector_config,
         ));
 
     rocket
 }
 
-/// 从提供的配置中获取一个值，如果未找到，则返回默认值。
-fn get_config_value<'a, T>(config: &Figment, key: &str, default: T) -> T
+/// 从提供的配置中获取一个值，如果未找到，则返回默认值。使用的值会记录到 `resolved_values` 中。
+///
+/// # 参数
+/// * `config` - 要从中获取值的配置
+/// * `resolved_values` - 已使用的配置值，以配置键为键
+/// * `key` - 配置键
+/// * `default` - 未找到时使用的默认值
+fn get_config_value<'a, T>(
+    config: &Figment,
+    resolved_values: &mut BTreeMap<String, serde_json::Value>,
+    key: &str,
+    default: T,
+) -> T
 where
-    T: Deserialize<'a> + std::fmt::Debug,
+    T: Deserialize<'a> + Serialize + std::fmt::Debug,
 {
-    match config.extract_inner(key) {
+    let value = match config.extract_inner(key) {
         Ok(x) => {
             println!("为 {} 使用配置值 {:?}", key, x);
             x
@@ -247,5 +2135,77 @@ where
             println!("为 {} 使用默认值 {:?} ({})", key, default, e);
             default
         }
+    };
+    if let Ok(x) = serde_json::to_value(&value) {
+        resolved_values.insert(key.to_string(), x);
+    }
+    value
+}
+
+/// 从提供的配置中获取一个不能为 0 的值。如果未找到，或者配置的值为 0，则返回默认值，而不是在之后使用时崩溃。
+///
+/// # 参数
+/// * `config` - 要从中获取值的配置
+/// * `resolved_values` - 已使用的配置值，以配置键为键
+/// * `key` - 配置键
+/// * `default` - 未找到或为 0 时使用的默认值。不能为 0。
+fn get_non_zero_config_value(
+    config: &Figment,
+    resolved_values: &mut BTreeMap<String, serde_json::Value>,
+    key: &str,
+    default: usize,
+) -> NonZeroUsize {
+    let value = get_config_value(config, resolved_values, key, default);
+    match NonZeroUsize::new(value) {
+        Some(x) => x,
+        None => {
+            println!("{} 不能为 0，改为使用默认值 {}", key, default);
+            resolved_values.insert(key.to_string(), serde_json::json!(default));
+            NonZeroUsize::new(default).expect("默认值不能为 0")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_accept_decoded_plus_before_offset() {
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T12:30:00+08:00").unwrap();
+        for timestamp in ["2024-05-01T12:30:00+08:00", "2024-05-01T12:30:00 08:00"] {
+            assert_eq!(expected, parse_timestamp(timestamp).unwrap());
+        }
+        assert_eq!(
+            DateTime::parse_from_rfc3339("2024-05-01T12:30:00-03:00").unwrap(),
+            parse_timestamp("2024-05-01T12:30:00-03:00").unwrap()
+        );
+        assert!(parse_timestamp("2024-05-01T12:30:00").is_err());
+        assert!(parse_timestamp(" 08:00").is_err());
+    }
+
+    #[test]
+    fn zero_non_zero_config_values_fall_back_to_default() {
+        let key = RECENT_HISTORY_SIZE_CONFIG_KEY;
+        let mut resolved_values = BTreeMap::new();
+        let config = Figment::from((key, 0));
+        assert_eq!(
+            4,
+            get_non_zero_config_value(&config, &mut resolved_values, key, 4).get()
+        );
+        assert_eq!(Some(&serde_json::json!(4)), resolved_values.get(key));
+
+        let config = Figment::from((key, 7));
+        assert_eq!(
+            7,
+            get_non_zero_config_value(&config, &mut resolved_values, key, 4).get()
+        );
+        assert_eq!(Some(&serde_json::json!(7)), resolved_values.get(key));
+
+        // 没有配置时使用默认值
+        assert_eq!(
+            4,
+            get_non_zero_config_value(&Figment::new(), &mut resolved_values, key, 4).get()
+        );
     }
 }
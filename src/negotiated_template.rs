@@ -0,0 +1,46 @@
+//! 根据 `Accept` 请求头在 HTML 和 JSON 之间选择的模板响应。
+
+use rocket::http::Header;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::Request;
+use rocket_dyn_templates::Template;
+use serde::Serialize;
+
+/// 一个模板响应。如果请求的 `Accept` 头首选 JSON，则以 JSON 形式返回模板的上下文，否则返回渲染后的模板。
+/// 这样同一个 URL 既可以给人查看，也可以给脚本使用。
+pub struct NegotiatedTemplate<T> {
+    /// 模板的名称。
+    name: &'static str,
+    /// 模板的上下文。
+    context: T,
+}
+
+impl<T> NegotiatedTemplate<T> {
+    /// 创建一个 `NegotiatedTemplate`。
+    ///
+    /// # 参数
+    /// * `name` - 模板的名称。
+    /// * `context` - 模板的上下文。
+    pub fn new(name: &'static str, context: T) -> NegotiatedTemplate<T> {
+        NegotiatedTemplate { name, context }
+    }
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for NegotiatedTemplate<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let prefers_json = match request.accept() {
+            Some(accept) => accept.preferred().media_type().is_json(),
+            None => false,
+        };
+
+        let mut response = if prefers_json {
+            Json(self.context).respond_to(request)?
+        } else {
+            Template::render(self.name, &self.context).respond_to(request)?
+        };
+        // 响应内容取决于 `Accept` 头，缓存时需要区分
+        response.set_header(Header::new("Vary", "Accept"));
+        Ok(response)
+    }
+}
@@ -0,0 +1,130 @@
+//! 可选择缩进输出的 JSON 响应。启用 `xml` feature 时，如果请求首选 XML，则输出 XML。
+
+use rocket::http::Status;
+use rocket::response::{self, content::RawJson, Responder};
+use rocket::serde::json::Json;
+use rocket::Request;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::snake_case::SnakeCase;
+
+/// JSON 响应中字段名的命名方式
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonFieldCase {
+    /// 小驼峰命名（例如 `cpuLoadPercent`），与结构体上的 serde 属性相同
+    Camel,
+    /// 蛇形命名（例如 `cpu_load_percent`）。只转换结构体的字段名，映射的键（例如网络接口名称和配置中的接口别名）保持不变。
+    Snake,
+}
+
+/// 按照 `json_field_case` 命名字段的值
+pub struct WithFieldCase<T> {
+    /// 要序列化的值
+    value: T,
+    /// 字段名的命名方式
+    case: JsonFieldCase,
+}
+
+impl<T> WithFieldCase<T> {
+    /// 创建一个 `WithFieldCase`。
+    ///
+    /// # 参数
+    /// * `value` - 要序列化的值
+    /// * `case` - 字段名的命名方式
+    pub fn new(value: T, case: JsonFieldCase) -> WithFieldCase<T> {
+        WithFieldCase { value, case }
+    }
+}
+
+impl<T: Serialize> Serialize for WithFieldCase<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.case {
+            JsonFieldCase::Camel => self.value.serialize(serializer),
+            JsonFieldCase::Snake => SnakeCase(&self.value).serialize(serializer),
+        }
+    }
+}
+
+/// 一个 JSON 响应。默认输出压缩的 JSON，也可以输出缩进的 JSON 以便阅读。
+/// 启用 `xml` feature 时，如果请求的 `Accept` 头首选 `application/xml`，则输出字段名相同的 XML。
+pub struct PrettyJson<T> {
+    /// 要序列化的值。
+    value: T,
+    /// 是否输出缩进的 JSON。
+    pretty: bool,
+}
+
+impl<T> PrettyJson<T> {
+    /// 创建一个 `PrettyJson`。
+    ///
+    /// # 参数
+    /// * `value` - 要序列化的值。
+    /// * `pretty` - 是否输出缩进的 JSON。如果为 `None`，则输出压缩的 JSON。
+    pub fn new(value: T, pretty: Option<bool>) -> PrettyJson<T> {
+        PrettyJson {
+            value,
+            pretty: pretty.unwrap_or(false),
+        }
+    }
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for PrettyJson<T> {
+    #[cfg(feature = "xml")]
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        use crate::xml::{prefers_xml, Xml};
+        use rocket::http::Header;
+
+        let mut response = if prefers_xml(request) {
+            Xml(self.value).respond_to(request)?
+        } else {
+            self.respond_with_json(request)?
+        };
+        // 响应内容取决于 `Accept` 头，缓存时需要区分
+        response.set_header(Header::new("Vary", "Accept"));
+        Ok(response)
+    }
+
+    #[cfg(not(feature = "xml"))]
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        self.respond_with_json(request)
+    }
+}
+
+impl<T: Serialize> PrettyJson<T> {
+    /// 将值序列化为 JSON 响应。`json_field_case` 为 `snake` 时，字段名转换为蛇形命名。
+    ///
+    /// # 参数
+    /// * `request` - 请求
+    fn respond_with_json(self, request: &Request<'_>) -> response::Result<'static> {
+        let case = request
+            .rocket()
+            .state::<JsonFieldCase>()
+            .copied()
+            .unwrap_or(JsonFieldCase::Camel);
+        respond_with_value(WithFieldCase::new(self.value, case), self.pretty, request)
+    }
+}
+
+/// 将值按原样序列化为 JSON 响应。
+///
+/// # 参数
+/// * `value` - 要序列化的值
+/// * `pretty` - 是否输出缩进的 JSON
+/// * `request` - 请求
+fn respond_with_value<T: Serialize>(
+    value: T,
+    pretty: bool,
+    request: &Request<'_>,
+) -> response::Result<'static> {
+    if pretty {
+        let string = serde_json::to_string_pretty(&value).map_err(|e| {
+            //TODO use actual logging once https://github.com/SergioBenitez/Rocket/issues/21 is done
+            println!("JSON 序列化失败: {}", e);
+            Status::InternalServerError
+        })?;
+        RawJson(string).respond_to(request)
+    } else {
+        Json(value).respond_to(request)
+    }
+}
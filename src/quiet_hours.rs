@@ -0,0 +1,141 @@
+//! 每天以较低的频率收集统计信息的安静时段
+
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// 安静时段的开始和结束时间的格式
+const TIME_FORMAT: &str = "%H:%M";
+
+/// 每天以较低的频率收集统计信息的时段，例如夜间。开始时间晚于结束时间时，时段跨越午夜。
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct QuietHours {
+    /// 安静时段的开始时间（服务器的本地时间），格式为 `HH:MM`
+    #[serde(with = "hour_minute")]
+    pub start: NaiveTime,
+    /// 安静时段的结束时间（服务器的本地时间），格式为 `HH:MM`
+    #[serde(with = "hour_minute")]
+    pub end: NaiveTime,
+    /// 安静时段内收集统计信息的频率，以秒为单位
+    pub update_frequency_seconds: u64,
+}
+
+impl QuietHours {
+    /// 安静时段内收集统计信息的频率
+    pub fn update_frequency(&self) -> Duration {
+        Duration::from_secs(self.update_frequency_seconds)
+    }
+
+    /// 判断某个时间是否在安静时段内。开始时间等于结束时间时，安静时段为空。
+    ///
+    /// # 参数
+    /// * `time` - 要判断的时间
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// 从某个时间到下一次进入或离开安静时段的时间
+    ///
+    /// # 参数
+    /// * `time` - 开始计算的时间
+    fn time_until_transition(&self, time: NaiveTime) -> Duration {
+        [self.start, self.end]
+            .iter()
+            .map(|boundary| {
+                let until = *boundary - time;
+                if until <= chrono::Duration::zero() {
+                    until + chrono::Duration::days(1)
+                } else {
+                    until
+                }
+            })
+            .min()
+            .and_then(|x| x.to_std().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// 根据当前时间决定收集统计信息的频率，以及到下一次收集前最多可以休眠多久。
+///
+/// 最长休眠时间不会超过下一次进入或离开安静时段的时间，这样切换频率时不需要等到按旧频率休眠结束。
+///
+/// # 参数
+/// * `quiet_hours` - 安静时段。为 `None` 时始终使用 `update_frequency`。
+/// * `update_frequency` - 安静时段之外收集统计信息的频率
+///
+/// 返回当前的收集频率和最长休眠时间。
+pub fn current_update_frequency(
+    quiet_hours: Option<&QuietHours>,
+    update_frequency: Duration,
+) -> (Duration, Duration) {
+    match quiet_hours {
+        Some(quiet_hours) => {
+            let now = Local::now().time();
+            let frequency = if quiet_hours.contains(now) {
+                quiet_hours.update_frequency()
+            } else {
+                update_frequency
+            };
+            (frequency, quiet_hours.time_until_transition(now))
+        }
+        None => (update_frequency, Duration::MAX),
+    }
+}
+
+/// 以 `HH:MM` 格式序列化和反序列化时间
+mod hour_minute {
+    use chrono::NaiveTime;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::TIME_FORMAT;
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format(TIME_FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&value, TIME_FORMAT).map_err(|e| {
+            D::Error::custom(format!("无效的时间 '{}'，应为 HH:MM 格式: {}", value, e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn contains_handles_periods_across_midnight() {
+        let quiet_hours = QuietHours {
+            start: time(23, 0),
+            end: time(7, 0),
+            update_frequency_seconds: 30,
+        };
+        assert!(quiet_hours.contains(time(23, 0)));
+        assert!(quiet_hours.contains(time(3, 0)));
+        assert!(!quiet_hours.contains(time(7, 0)));
+        assert!(!quiet_hours.contains(time(12, 0)));
+    }
+
+    #[test]
+    fn contains_handles_periods_within_a_day() {
+        let quiet_hours = QuietHours {
+            start: time(12, 0),
+            end: time(13, 0),
+            update_frequency_seconds: 30,
+        };
+        assert!(quiet_hours.contains(time(12, 30)));
+        assert!(!quiet_hours.contains(time(13, 0)));
+        assert!(!quiet_hours.contains(time(11, 59)));
+    }
+}
@@ -0,0 +1,154 @@
+//! 从远程代理获取统计信息。远程代理是另一个运行此程序的实例，通过 `/stats` 提供其统计信息。
+
+use rocket::http::uri::Absolute;
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::stats::AllStats;
+
+/// 未指定端口时使用的端口
+const DEFAULT_HTTP_PORT: u16 = 80;
+/// 获取统计信息的路径，位于远程代理 URL 的路径之后
+const STATS_PATH: &str = "/stats";
+
+/// 一个远程代理
+#[derive(Clone, Debug)]
+pub struct RemoteAgent {
+    /// 远程代理的主机名或 IP 地址。IPv6 地址包含方括号，例如 `[::1]`。
+    host: String,
+    /// 远程代理的端口
+    port: u16,
+    /// 远程代理所在的路径前缀，不包含末尾的 `/`，例如通过反向代理访问时的 `/dashboard`
+    path_prefix: String,
+}
+
+impl RemoteAgent {
+    /// 从 URL 创建一个 `RemoteAgent`。只支持 `http://` URL。URL 的路径作为获取统计信息的路径前缀，查询会被忽略。
+    /// 如果 URL 无效，则返回错误消息。
+    ///
+    /// # 参数
+    /// * `url` - 远程代理的 URL，例如 `http://192.168.1.10:8001`、`http://[fd00::10]:8001` 或 `http://example.com/dashboard`
+    pub fn parse(url: &str) -> Result<RemoteAgent, String> {
+        let uri = Absolute::parse(url).map_err(|e| format!("invalid URL {}: {}", url, e))?;
+        if !uri.scheme().eq_ignore_ascii_case("http") {
+            return Err(format!("only http:// URLs are supported: {}", url));
+        }
+        let authority = match uri.authority() {
+            Some(x) if !x.host().is_empty() => x,
+            _ => return Err(format!("missing host in {}", url)),
+        };
+
+        Ok(RemoteAgent {
+            host: authority.host().to_string(),
+            port: authority.port().unwrap_or(DEFAULT_HTTP_PORT),
+            path_prefix: uri.path().as_str().trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// 远程代理的名称，即 `主机:端口`
+    pub fn name(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// 从远程代理获取最近收集的统计信息。
+    ///
+    /// # 参数
+    /// * `timeout` - 连接、发送和接收各自的最长等待时间
+    pub fn fetch_stats(&self, timeout: Duration) -> io::Result<AllStats> {
+        let host = self.host.trim_start_matches('[').trim_end_matches(']');
+        let address = (host, self.port).to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("无法解析 {}", self.host))
+        })?;
+        let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        // 使用 `Connection: close`，这样响应体在连接关闭时结束
+        write!(
+            stream,
+            "GET {}{} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+            self.path_prefix,
+            STATS_PATH,
+            self.name()
+        )?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+
+        let body = response_body(&response)?;
+        serde_json::from_slice(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// 获取 HTTP 响应的响应体。如果状态码不是 200，或者响应使用了不支持的分块传输编码，则返回错误。
+///
+/// # 参数
+/// * `response` - 完整的 HTTP 响应
+fn response_body(response: &[u8]) -> io::Result<&[u8]> {
+    let header_end = response
+        .windows(4)
+        .position(|x| x == b"\r\n\r\n")
+        .ok_or_else(|| invalid_response("响应不完整"))?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let mut lines = head.lines();
+
+    let status_line = lines.next().unwrap_or("");
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(invalid_response(&format!("意外的状态: {}", status_line)));
+    }
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+            {
+                return Err(invalid_response("不支持分块传输编码"));
+            }
+        }
+    }
+
+    Ok(&response[header_end + 4..])
+}
+
+/// 创建一个表示无效响应的错误。
+fn invalid_response(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keeps_path_prefix() {
+        let agent = RemoteAgent::parse("http://example.com:8001/dashboard/").unwrap();
+        assert_eq!("example.com", agent.host);
+        assert_eq!(8001, agent.port);
+        assert_eq!("/dashboard", agent.path_prefix);
+
+        let agent = RemoteAgent::parse("http://example.com").unwrap();
+        assert_eq!(DEFAULT_HTTP_PORT, agent.port);
+        assert_eq!("", agent.path_prefix);
+    }
+
+    #[test]
+    fn parse_handles_ipv6_addresses() {
+        let agent = RemoteAgent::parse("http://[fd00::10]:8001").unwrap();
+        assert_eq!("[fd00::10]", agent.host);
+        assert_eq!(8001, agent.port);
+        assert_eq!("[fd00::10]:8001", agent.name());
+
+        let agent = RemoteAgent::parse("http://[::1]/stats-dashboard").unwrap();
+        assert_eq!(DEFAULT_HTTP_PORT, agent.port);
+        assert_eq!("/stats-dashboard", agent.path_prefix);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_urls() {
+        assert!(RemoteAgent::parse("https://example.com").is_err());
+        assert!(RemoteAgent::parse("http://").is_err());
+        assert!(RemoteAgent::parse("example.com:8001").is_err());
+        assert!(RemoteAgent::parse("http://example.com:port").is_err());
+    }
+}
@@ -1,17 +1,80 @@
 //! 系统统计信息的集合
 
-use std::{io::Error, thread};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Error,
+    sync::{
+        mpsc::{self, TryRecvError},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
 
 use chrono::{DateTime, Local};
 use serde::Deserialize;
 use serde::Serialize;
 use systemstat::{
-    saturating_sub_bytes, ByteSize, Duration, IpAddr, NetworkAddrs, Platform, System,
+    saturating_sub_bytes, ByteSize, CPULoad, Duration, IpAddr, NetworkAddrs, PlatformCpuLoad,
+    PlatformMemory,
 };
 
+use crate::system_source::SystemSource;
+
 // 每MB的字节数
 const BYTES_PER_MB: u64 = 1_000_000;
 
+/// Linux 上 utmp 数据库的位置
+#[cfg(target_os = "linux")]
+const LINUX_UTMP_PATH: &str = "/var/run/utmp";
+
+/// `BYTES_PER_MB` 对应的单位名称，记录在每个统计信息的来源中
+const MB_UNIT_NAME: &str = "MB";
+// 每兆比特的比特数
+const BITS_PER_MEGABIT: f64 = 1_000_000.0;
+
+/// 已经记录过的不受支持的统计信息
+static UNSUPPORTED_LOG: Mutex<UnsupportedLog> = Mutex::new(UnsupportedLog {
+    log_once: false,
+    reported: BTreeSet::new(),
+    reported_this_collection: BTreeSet::new(),
+});
+
+/// 可以禁用的统计信息收集器
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Collector {
+    /// 套接字统计信息
+    Sockets,
+    /// CPU 温度
+    Temperature,
+    /// 已挂载文件系统的统计信息
+    Mounts,
+}
+
+/// 统计信息收集器的配置
+#[derive(Clone, Debug)]
+pub struct CollectorConfig {
+    /// 被禁用的收集器。被禁用的统计信息不会被收集，而是保存为 `None`。
+    pub disabled_collectors: Vec<Collector>,
+    /// 等待收集已挂载文件系统的统计信息的最长时间。超时的统计信息保存为 `None`。
+    pub mount_collection_timeout: Duration,
+    /// 是否只记录一次每种不受支持的统计信息，而不是每次收集都记录
+    pub log_unsupported_once: bool,
+    /// 是否在采样 CPU 负载的同时，在其他线程中收集互不依赖的统计信息（一般、内存、文件系统和网络统计）
+    pub concurrent_collection: bool,
+}
+
+impl CollectorConfig {
+    /// 提供的收集器是否已启用。
+    ///
+    /// # 参数
+    /// * `collector` - 要检查的收集器
+    pub fn is_enabled(&self, collector: Collector) -> bool {
+        !self.disabled_collectors.contains(&collector)
+    }
+}
+
 /// 所有系统统计信息
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -28,6 +91,38 @@ pub struct AllStats {
     pub network: NetworkStats,
     /// 收集统计数据的时间
     pub collection_time: DateTime<Local>,
+    /// 收集统计数据所花费的时间，以毫秒为单位（包括采样 CPU 负载的时间）
+    #[serde(default)]
+    pub collection_duration_ms: u64,
+    /// 收集统计数据的程序和平台。加入此字段之前写入的历史条目为 `None`。
+    #[serde(default)]
+    pub source: Option<SampleSource>,
+    /// 持久化的统计历史中，此条目之前是否有被持久化过滤条件跳过的条目。为 `true` 时，此条目与上一个条目之间的间隔是过滤造成的，不是数据中断。
+    #[serde(default)]
+    pub after_filtered_entries: bool,
+}
+
+/// 收集统计信息的程序和平台。历史文件被复制到其他机器或者在升级后继续使用时，用于发现混合了不同来源的条目。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleSource {
+    /// 收集统计信息的程序的版本
+    pub collector_version: String,
+    /// 收集统计信息的平台，格式为 `操作系统-架构`，例如 `linux-x86_64`
+    pub platform: String,
+    /// 以MB为单位的统计信息所用的单位，例如 `MB` 表示 1,000,000 字节
+    pub units: Option<String>,
+}
+
+impl SampleSource {
+    /// 此程序在当前平台上收集的统计信息的来源
+    pub fn current() -> SampleSource {
+        SampleSource {
+            collector_version: env!("CARGO_PKG_VERSION").to_string(),
+            platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            units: Some(MB_UNIT_NAME.to_string()),
+        }
+    }
 }
 
 impl AllStats {
@@ -36,15 +131,245 @@ impl AllStats {
     /// # 参数
     /// * `sys` - 指定需要获取信息的系统
     /// * `cpu_sample_duration` - 采样 CPU 负载所需的时间。请注意，此函数将在返回之前在此期间阻塞它所在的线程。
-    pub fn from(sys: &System, cpu_sample_duration: Duration) -> AllStats {
-        AllStats {
-            general: GeneralStats::from(&sys),
-            cpu: CpuStats::from(&sys, cpu_sample_duration),
-            memory: MemoryStats::from(&sys),
-            filesystems: MountStats::from(&sys),
-            network: NetworkStats::from(&sys),
-            collection_time: Local::now(),
+    /// * `collectors` - 要使用的收集器
+    /// * `mount_collection` - 获取挂载信息的状态，同一个收集者的每次收集应使用同一个
+    ///
+    /// 启用 `collectors.concurrent_collection` 时，其他统计信息在采样 CPU 负载期间于其他线程中收集，
+    /// 因此总收集时间接近 CPU 采样时间，而不是所有收集时间之和。
+    pub fn from(
+        sys: &impl SystemSource,
+        cpu_sample_duration: Duration,
+        collectors: &CollectorConfig,
+        mount_collection: &MountCollection,
+    ) -> AllStats {
+        AllStats::collect(sys, collectors, mount_collection, || {
+            CpuStats::from(sys, cpu_sample_duration, collectors)
+        })
+    }
+
+    /// 获取所提供系统的所有统计信息，但不阻塞线程采样 CPU 负载，而是根据 `previous_cpu_counters` 到现在的
+    /// CPU 时间计数器之差计算 CPU 负载。详见 `CpuStats::since`。如果无法以这种方式计算 CPU 负载，则返回“None”。
+    ///
+    /// # 参数
+    /// * `sys` - 指定需要获取信息的系统
+    /// * `previous_cpu_counters` - 之前读取的 CPU 时间计数器，通常是上一次收集结束时读取的
+    /// * `collectors` - 要使用的收集器
+    /// * `mount_collection` - 获取挂载信息的状态，同一个收集者的每次收集应使用同一个
+    pub fn since(
+        sys: &impl SystemSource,
+        previous_cpu_counters: &CpuCounters,
+        collectors: &CollectorConfig,
+        mount_collection: &MountCollection,
+    ) -> Option<AllStats> {
+        let cpu = CpuStats::since(sys, previous_cpu_counters, collectors)?;
+        Some(AllStats::collect(sys, collectors, mount_collection, || cpu))
+    }
+
+    /// 获取所提供系统的所有统计信息，使用 `cpu` 获取 CPU 统计信息。
+    ///
+    /// # 参数
+    /// * `sys` - 指定需要获取信息的系统
+    /// * `collectors` - 要使用的收集器
+    /// * `mount_collection` - 获取挂载信息的状态
+    /// * `cpu` - 获取 CPU 统计信息。并发收集时在其他统计信息收集期间于当前线程中调用。
+    fn collect(
+        sys: &impl SystemSource,
+        collectors: &CollectorConfig,
+        mount_collection: &MountCollection,
+        cpu: impl FnOnce() -> CpuStats,
+    ) -> AllStats {
+        let start = Instant::now();
+        // 使用开始采样的时间，这样无论是否并发收集，收集时间都一致
+        let collection_time = Local::now();
+        UNSUPPORTED_LOG.lock().unwrap().log_once = collectors.log_unsupported_once;
+        let filesystems = || {
+            if collectors.is_enabled(Collector::Mounts) {
+                mount_collection.collect(sys, collectors.mount_collection_timeout)
+            } else {
+                None
+            }
+        };
+
+        let (general, cpu, memory, filesystems, network) = if collectors.concurrent_collection {
+            thread::scope(|scope| {
+                let general = scope.spawn(|| GeneralStats::from(sys));
+                let memory = scope.spawn(|| MemoryStats::from(sys));
+                let filesystems = scope.spawn(filesystems);
+                let network = scope.spawn(|| NetworkStats::from(sys, collectors));
+                let cpu = cpu();
+                (
+                    general.join().unwrap(),
+                    cpu,
+                    memory.join().unwrap(),
+                    filesystems.join().unwrap(),
+                    network.join().unwrap(),
+                )
+            })
+        } else {
+            (
+                GeneralStats::from(sys),
+                cpu(),
+                MemoryStats::from(sys),
+                filesystems(),
+                NetworkStats::from(sys, collectors),
+            )
+        };
+
+        let mut stats = AllStats {
+            general,
+            cpu,
+            memory,
+            filesystems,
+            network,
+            collection_time,
+            collection_duration_ms: 0,
+            source: Some(SampleSource::current()),
+            after_filtered_entries: false,
+        };
+        stats.collection_duration_ms = start.elapsed().as_millis() as u64;
+        UNSUPPORTED_LOG.lock().unwrap().finish_collection();
+        stats
+    }
+
+    /// 将统计信息格式化为纯文本，每行一个 `键: 值`，便于在 shell 脚本中使用 `grep` 处理。
+    ///
+    /// 键名是稳定的。每个文件系统和网络接口的键带有方括号括起来的挂载点或接口名称，例如 `fs_used_mb[/]`。
+    /// 不可用的统计信息将被省略。
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+
+        if let Some(x) = self.general.uptime_seconds {
+            lines.push(format!("uptime_seconds: {}", x));
+        }
+        if let Some(x) = self.general.boot_timestamp {
+            lines.push(format!("boot_timestamp: {}", x));
+        }
+        if let Some(x) = &self.general.load_averages {
+            lines.push(format!("load_average_1m: {}", x.one_minute));
+            lines.push(format!("load_average_5m: {}", x.five_minutes));
+            lines.push(format!("load_average_15m: {}", x.fifteen_minutes));
+        }
+        if let Some(x) = self.general.process_count {
+            lines.push(format!("process_count: {}", x));
+        }
+        if let Some(x) = self.general.thread_count {
+            lines.push(format!("thread_count: {}", x));
+        }
+        if let Some(x) = self.general.logged_in_users {
+            lines.push(format!("logged_in_users: {}", x));
+        }
+        if let Some(x) = self.general.user_sessions {
+            lines.push(format!("user_sessions: {}", x));
+        }
+
+        if let Some(x) = self.cpu.aggregate_load_percent {
+            lines.push(format!("cpu_load: {}", x));
+        }
+        if let Some(x) = &self.cpu.per_logical_cpu_load_percent {
+            for (i, load) in x.iter().enumerate() {
+                lines.push(format!("cpu_load[{}]: {}", i, load));
+            }
+        }
+        if let Some(x) = &self.cpu.load_breakdown {
+            lines.push(format!("cpu_user_percent: {}", x.user_percent));
+            lines.push(format!("cpu_nice_percent: {}", x.nice_percent));
+            lines.push(format!("cpu_system_percent: {}", x.system_percent));
+            lines.push(format!("cpu_interrupt_percent: {}", x.interrupt_percent));
+            lines.push(format!("cpu_idle_percent: {}", x.idle_percent));
+            if let Some(iowait) = x.iowait_percent {
+                lines.push(format!("cpu_iowait_percent: {}", iowait));
+            }
+            if let Some(steal) = x.steal_percent {
+                lines.push(format!("cpu_steal_percent: {}", steal));
+            }
+        }
+        if let Some(x) = self.cpu.temp_celsius {
+            lines.push(format!("cpu_temp_celsius: {}", x));
+        }
+
+        if let Some(x) = &self.memory {
+            lines.push(format!("mem_used_mb: {}", round_bytes_to_mb(x.used_bytes)));
+            lines.push(format!(
+                "mem_total_mb: {}",
+                round_bytes_to_mb(x.total_bytes)
+            ));
+            if let Some(available) = x.available_bytes {
+                lines.push(format!(
+                    "mem_available_mb: {}",
+                    round_bytes_to_mb(available)
+                ));
+            }
+            if let Some(cached) = x.cached_bytes {
+                lines.push(format!("mem_cached_mb: {}", round_bytes_to_mb(cached)));
+            }
+            if let Some(buffers) = x.buffers_bytes {
+                lines.push(format!("mem_buffers_mb: {}", round_bytes_to_mb(buffers)));
+            }
+        }
+
+        if let Some(x) = &self.filesystems {
+            for mount in x {
+                let name = &mount.mounted_on;
+                lines.push(format!("fs_type[{}]: {}", name, mount.fs_type));
+                lines.push(format!("fs_mounted_from[{}]: {}", name, mount.mounted_from));
+                lines.push(format!("fs_used_mb[{}]: {}", name, mount.used_mb));
+                lines.push(format!("fs_total_mb[{}]: {}", name, mount.total_mb));
+                lines.push(format!("fs_read_only[{}]: {}", name, mount.read_only));
+            }
         }
+
+        if let Some(x) = &self.network.interfaces {
+            for interface in x {
+                let name = &interface.name;
+                lines.push(format!("net_sent_mb[{}]: {}", name, interface.sent_mb));
+                lines.push(format!(
+                    "net_received_mb[{}]: {}",
+                    name, interface.received_mb
+                ));
+                lines.push(format!(
+                    "net_sent_packets[{}]: {}",
+                    name, interface.sent_packets
+                ));
+                lines.push(format!(
+                    "net_received_packets[{}]: {}",
+                    name, interface.received_packets
+                ));
+                lines.push(format!(
+                    "net_send_errors[{}]: {}",
+                    name, interface.send_errors
+                ));
+                lines.push(format!(
+                    "net_receive_errors[{}]: {}",
+                    name, interface.receive_errors
+                ));
+                if let Some(mbps) = interface.sent_mbps {
+                    lines.push(format!("net_sent_mbps[{}]: {}", name, mbps));
+                }
+                if let Some(mbps) = interface.received_mbps {
+                    lines.push(format!("net_received_mbps[{}]: {}", name, mbps));
+                }
+            }
+        }
+        if let Some(x) = &self.network.sockets {
+            lines.push(format!("tcp_in_use: {}", x.tcp_in_use));
+            lines.push(format!("tcp_orphaned: {}", x.tcp_orphaned));
+            lines.push(format!("udp_in_use: {}", x.udp_in_use));
+            lines.push(format!("tcp6_in_use: {}", x.tcp6_in_use));
+            lines.push(format!("udp6_in_use: {}", x.udp6_in_use));
+        }
+
+        lines.push(format!(
+            "collection_time: {}",
+            self.collection_time.to_rfc3339()
+        ));
+        lines.push(format!(
+            "collection_duration_ms: {}",
+            self.collection_duration_ms
+        ));
+
+        let mut text = lines.join("\n");
+        text.push('\n');
+        text
     }
 }
 
@@ -58,6 +383,120 @@ pub struct GeneralStats {
     pub boot_timestamp: Option<i64>,
     /// 系统的平均负载
     pub load_averages: Option<LoadAverages>,
+    /// 正在运行的进程数量。如果平台不提供此信息，则为“None”。
+    #[serde(default)]
+    pub process_count: Option<usize>,
+    /// 所有进程的线程总数。如果平台不提供此信息，则为“None”。
+    #[serde(default)]
+    pub thread_count: Option<usize>,
+    /// 内核随机数熵池中可用的熵，以比特为单位。熵不足时，读取 `/dev/random` 的程序（例如建立 TLS 连接时）可能会阻塞。
+    /// 如果平台不提供此信息，则为“None”。
+    #[serde(default)]
+    pub entropy_available: Option<u64>,
+    /// 已登录的不同用户的数量。如果平台不提供此信息，则为“None”。
+    #[serde(default)]
+    pub logged_in_users: Option<usize>,
+    /// 登录会话的数量（同一用户的多个终端或 SSH 连接分别计算）。如果平台不提供此信息，则为“None”。
+    #[serde(default)]
+    pub user_sessions: Option<usize>,
+}
+
+/// 系统运行时间。字段名使用蛇形命名，为外部的运行时间监控提供一个简单稳定的格式。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UptimeStats {
+    /// 系统运行的秒数
+    pub uptime_seconds: Option<u64>,
+    /// 自 UNIX 纪元以来的启动时间（以秒为单位）
+    pub boot_timestamp: Option<i64>,
+}
+
+impl From<&GeneralStats> for UptimeStats {
+    fn from(general: &GeneralStats) -> UptimeStats {
+        UptimeStats {
+            uptime_seconds: general.uptime_seconds,
+            boot_timestamp: general.boot_timestamp,
+        }
+    }
+}
+
+/// 最近收集的统计信息中哪些统计信息可用。许多统计信息取决于平台或被禁用的收集器，客户端可以据此隐藏不受支持的部分。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// 系统运行时间是否可用
+    pub uptime: bool,
+    /// 启动时间是否可用
+    pub boot_timestamp: bool,
+    /// 平均负载是否可用
+    pub load_averages: bool,
+    /// 进程数量是否可用
+    pub process_count: bool,
+    /// 线程数量是否可用
+    pub thread_count: bool,
+    /// 可用的熵是否可用
+    pub entropy_available: bool,
+    /// 已登录的用户数量和登录会话数量是否可用
+    pub logged_in_users: bool,
+    /// 每个逻辑 CPU 的负载是否可用
+    pub per_logical_cpu_load: bool,
+    /// 所有 CPU 的总负载是否可用
+    pub aggregate_cpu_load: bool,
+    /// CPU 温度是否可用
+    pub cpu_temp: bool,
+    /// CPU 时间分布是否可用
+    pub cpu_load_breakdown: bool,
+    /// I/O 等待时间是否可用
+    pub cpu_iowait: bool,
+    /// steal 时间是否可用
+    pub cpu_steal: bool,
+    /// 内存统计是否可用
+    pub memory: bool,
+    /// 可用内存是否可用
+    pub memory_available: bool,
+    /// 已挂载文件系统的统计信息是否可用
+    pub filesystems: bool,
+    /// 网络接口的统计信息是否可用
+    pub network_interfaces: bool,
+    /// 网络接口的吞吐量是否可用
+    pub network_rates: bool,
+    /// 套接字统计是否可用
+    pub sockets: bool,
+}
+
+impl From<&AllStats> for Capabilities {
+    fn from(stats: &AllStats) -> Capabilities {
+        let load_breakdown = stats.cpu.load_breakdown.as_ref();
+        Capabilities {
+            uptime: stats.general.uptime_seconds.is_some(),
+            boot_timestamp: stats.general.boot_timestamp.is_some(),
+            load_averages: stats.general.load_averages.is_some(),
+            process_count: stats.general.process_count.is_some(),
+            thread_count: stats.general.thread_count.is_some(),
+            entropy_available: stats.general.entropy_available.is_some(),
+            logged_in_users: stats.general.logged_in_users.is_some(),
+            per_logical_cpu_load: stats.cpu.per_logical_cpu_load_percent.is_some(),
+            aggregate_cpu_load: stats.cpu.aggregate_load_percent.is_some(),
+            cpu_temp: stats.cpu.temp_celsius.is_some(),
+            cpu_load_breakdown: load_breakdown.is_some(),
+            cpu_iowait: load_breakdown.and_then(|x| x.iowait_percent).is_some(),
+            cpu_steal: load_breakdown.and_then(|x| x.steal_percent).is_some(),
+            memory: stats.memory.is_some(),
+            memory_available: stats
+                .memory
+                .as_ref()
+                .and_then(|x| x.available_bytes)
+                .is_some(),
+            filesystems: stats.filesystems.is_some(),
+            network_interfaces: stats.network.interfaces.is_some(),
+            network_rates: stats
+                .network
+                .interfaces
+                .iter()
+                .flatten()
+                .any(|x| x.sent_mbps.is_some() || x.received_mbps.is_some()),
+            sockets: stats.network.sockets.is_some(),
+        }
+    }
 }
 
 /// 平均负载
@@ -74,7 +513,7 @@ pub struct LoadAverages {
 
 impl GeneralStats {
     /// 获取所提供系统的一般统计信息。
-    pub fn from(sys: &System) -> GeneralStats {
+    pub fn from(sys: &impl SystemSource) -> GeneralStats {
         let uptime_seconds = match sys.uptime() {
             Ok(x) => Some(x.as_secs()),
             Err(e) => {
@@ -103,12 +542,131 @@ impl GeneralStats {
             }
         };
 
+        let (process_count, thread_count) = process_and_thread_counts();
+        let (logged_in_users, user_sessions) = logged_in_users_and_sessions();
+
         GeneralStats {
             uptime_seconds,
             boot_timestamp,
             load_averages,
+            process_count,
+            thread_count,
+            entropy_available: sys.entropy_available(),
+            logged_in_users,
+            user_sessions,
+        }
+    }
+}
+
+/// 获取正在运行的进程数量和线程总数。
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn process_and_thread_counts() -> (Option<usize>, Option<usize>) {
+    // /proc 中每个进程都有一个以其 PID 命名的目录
+    let process_count = match std::fs::read_dir("/proc") {
+        Ok(entries) => Some(
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_string_lossy()
+                        .bytes()
+                        .all(|b| b.is_ascii_digit())
+                })
+                .count(),
+        ),
+        Err(e) => {
+            log("获取进程数量时出错: ", e);
+            None
+        }
+    };
+
+    // /proc/loadavg 的第 4 个字段的格式为：可运行的线程数/线程总数
+    let thread_count = match std::fs::read_to_string("/proc/loadavg") {
+        Ok(contents) => contents
+            .split_whitespace()
+            .nth(3)
+            .and_then(|x| x.split('/').nth(1))
+            .and_then(|x| x.parse().ok()),
+        Err(e) => {
+            log("获取线程数量时出错: ", e);
+            None
+        }
+    };
+
+    (process_count, thread_count)
+}
+
+/// 获取正在运行的进程数量和线程总数。此平台不提供这些信息。
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn process_and_thread_counts() -> (Option<usize>, Option<usize>) {
+    (None, None)
+}
+
+/// 从 utmpx 数据库中获取已登录的不同用户的数量和登录会话的数量。
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+fn logged_in_users_and_sessions() -> (Option<usize>, Option<usize>) {
+    // 没有 utmp 文件时（例如在容器中）`getutxent` 不会报错，只是没有任何条目，不能当作没有用户登录
+    #[cfg(target_os = "linux")]
+    if !std::path::Path::new(LINUX_UTMP_PATH).exists() {
+        return (None, None);
+    }
+
+    // 读取 utmpx 数据库的位置是进程全局的，不能同时在多个线程中读取
+    static UTMPX_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = UTMPX_LOCK.lock().unwrap();
+
+    let mut users = BTreeSet::new();
+    let mut session_count = 0;
+    // SAFETY: 持有锁时只有这里读取 utmpx 数据库；`getutxent` 返回的条目在下一次调用之前有效，使用前已复制所需的字段
+    unsafe {
+        libc::setutxent();
+        loop {
+            let entry = libc::getutxent();
+            if entry.is_null() {
+                break;
+            }
+            let entry = &*entry;
+            if entry.ut_type == libc::USER_PROCESS {
+                session_count += 1;
+                // 用户名占满整个字段时没有结尾的 NUL
+                let user: Vec<u8> = entry
+                    .ut_user
+                    .iter()
+                    .take_while(|&&c| c != 0)
+                    .map(|&c| c as u8)
+                    .collect();
+                users.insert(user);
+            }
         }
+        libc::endutxent();
     }
+
+    (Some(users.len()), Some(session_count))
+}
+
+/// 获取已登录的用户数量和登录会话的数量。此平台不提供这些信息。
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+fn logged_in_users_and_sessions() -> (Option<usize>, Option<usize>) {
+    (None, None)
+}
+
+/// 获取内核随机数熵池中可用的熵，以比特为单位。
+#[cfg(target_os = "linux")]
+pub(crate) fn entropy_available() -> Option<u64> {
+    match std::fs::read_to_string("/proc/sys/kernel/random/entropy_avail") {
+        Ok(contents) => contents.trim().parse().ok(),
+        Err(e) => {
+            log("获取可用熵时出错: ", e);
+            None
+        }
+    }
+}
+
+/// 获取内核随机数熵池中可用的熵。此平台不提供此信息。
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn entropy_available() -> Option<u64> {
+    None
 }
 
 /// CPU统计
@@ -121,6 +679,74 @@ pub struct CpuStats {
     pub aggregate_load_percent: Option<f32>,
     /// CPU 的温度，以摄氏度为单位
     pub temp_celsius: Option<f32>,
+    /// 合并的统计信息中 CPU 整体负载百分比的峰值。只有合并后的统计信息才有此值，因为合并后的负载是平均值。
+    #[serde(default)]
+    pub peak_aggregate_load_percent: Option<f32>,
+    /// 合并的统计信息中 CPU 温度的峰值，以摄氏度为单位。只有合并后的统计信息才有此值。
+    #[serde(default)]
+    pub peak_temp_celsius: Option<f32>,
+    /// 合并的统计信息中 CPU 整体负载百分比的最小值。只有合并后的统计信息才有此值。
+    #[serde(default)]
+    pub min_aggregate_load_percent: Option<f32>,
+    /// CPU 时间按用途的细分
+    #[serde(default)]
+    pub load_breakdown: Option<CpuLoadBreakdown>,
+}
+
+/// 只列出负载达到阈值的逻辑 CPU 的 CPU 统计信息，用于减小逻辑 CPU 很多时的响应大小。
+/// `perLogicalCpuLoadPercent` 始终为 `null`，由 `busyLogicalCpuLoadPercent` 代替。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SparseCpuStats {
+    /// 除每个逻辑 CPU 的负载之外的 CPU 统计信息
+    #[serde(flatten)]
+    pub cpu: CpuStats,
+    /// 逻辑 CPU 的总数
+    pub logical_cpu_count: Option<usize>,
+    /// 负载达到阈值的逻辑 CPU 的编号及其负载百分比，按编号排列
+    pub busy_logical_cpu_load_percent: Option<Vec<(usize, f32)>>,
+}
+
+impl SparseCpuStats {
+    /// 从 CPU 统计信息创建 `SparseCpuStats`，省略负载低于阈值的逻辑 CPU。
+    ///
+    /// # 参数
+    /// * `cpu` - CPU 统计信息
+    /// * `threshold_percent` - 逻辑 CPU 的负载百分比至少为多少时才列出
+    pub fn from(mut cpu: CpuStats, threshold_percent: f32) -> SparseCpuStats {
+        let per_logical_cpu_load_percent = cpu.per_logical_cpu_load_percent.take();
+        SparseCpuStats {
+            cpu,
+            logical_cpu_count: per_logical_cpu_load_percent.as_ref().map(Vec::len),
+            busy_logical_cpu_load_percent: per_logical_cpu_load_percent.map(|loads| {
+                loads
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, load)| *load >= threshold_percent)
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// CPU 时间按用途的细分，各项均为占总 CPU 时间的百分比
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuLoadBreakdown {
+    /// 用户态
+    pub user_percent: f32,
+    /// 低优先级（nice）的用户态
+    pub nice_percent: f32,
+    /// 内核态
+    pub system_percent: f32,
+    /// 处理中断
+    pub interrupt_percent: f32,
+    /// 空闲
+    pub idle_percent: f32,
+    /// 等待 I/O。如果平台不提供此信息，则为“None”。
+    pub iowait_percent: Option<f32>,
+    /// 被虚拟机管理程序分给其他虚拟机（steal）。如果平台不提供此信息，则为“None”。
+    pub steal_percent: Option<f32>,
 }
 
 impl CpuStats {
@@ -129,9 +755,15 @@ impl CpuStats {
     /// # 参数
     /// * `sys` - 指定需要获取信息的系统
     /// * `sample_duration` - 采样 CPU 负载所需的时间。请注意，此函数将在返回之前在此期间阻塞它所在的线程。
-    pub fn from(sys: &System, sample_duration: Duration) -> CpuStats {
+    /// * `collectors` - 要使用的收集器
+    pub fn from(
+        sys: &impl SystemSource,
+        sample_duration: Duration,
+        collectors: &CollectorConfig,
+    ) -> CpuStats {
         let cpu_load = sys.cpu_load();
         let cpu_load_aggregate = sys.cpu_load_aggregate();
+        let cpu_times_start = sys.cpu_counters().map(|x| x.aggregate);
         thread::sleep(sample_duration);
         let per_logical_cpu_load_percent = match cpu_load {
             Ok(x) => match x.done() {
@@ -147,55 +779,290 @@ impl CpuStats {
             }
         };
 
-        let aggregate_load_percent = match cpu_load_aggregate {
+        let (aggregate_load_percent, load_breakdown) = match cpu_load_aggregate {
             Ok(x) => match x.done() {
-                Ok(cpu) => Some((1.0 - cpu.idle) * 100.0),
+                Ok(cpu) => (
+                    Some((1.0 - cpu.idle) * 100.0),
+                    Some(cpu_load_breakdown(sys, &cpu, cpu_times_start)),
+                ),
                 Err(e) => {
                     log("获取总 CPU 负载时​​出错: ", e);
-                    None
+                    (None, None)
                 }
             },
             Err(e) => {
                 log("获取总 CPU 负载时​​出错: ", e);
-                None
-            }
-        };
-
-        let temp_celsius = match sys.cpu_temp() {
-            Ok(x) => Some(x),
-            Err(e) => {
-                log("获取 CPU 温度时出错: ", e);
-                None
+                (None, None)
             }
         };
 
         CpuStats {
             per_logical_cpu_load_percent,
             aggregate_load_percent,
-            temp_celsius,
+            temp_celsius: cpu_temp(sys, collectors),
+            peak_aggregate_load_percent: None,
+            peak_temp_celsius: None,
+            min_aggregate_load_percent: None,
+            load_breakdown,
+        }
+    }
+
+    /// 根据 `previous` 到现在的 CPU 时间计数器之差计算所提供系统的 CPU 统计信息。与 `from` 不同，此函数不会阻塞线程。
+    ///
+    /// 得到的负载是从读取 `previous` 到现在这段时间内的平均负载，因此其准确性取决于距离读取 `previous` 经过的时间：
+    /// 间隔很短时计数器变化很小，负载的误差较大；间隔很长时则无法反映最近的负载变化。
+    /// 如果此平台无法读取 CPU 时间计数器，或者两次读取之间没有经过任何时间，则返回“None”。
+    ///
+    /// # 参数
+    /// * `sys` - 指定需要获取信息的系统
+    /// * `previous` - 之前读取的 CPU 时间计数器
+    /// * `collectors` - 要使用的收集器
+    pub fn since(
+        sys: &impl SystemSource,
+        previous: &CpuCounters,
+        collectors: &CollectorConfig,
+    ) -> Option<CpuStats> {
+        let current = sys.cpu_counters()?;
+        let load_breakdown = current.aggregate.breakdown_since(&previous.aggregate)?;
+        // CPU 数量变化时（例如 CPU 热插拔）无法对应每个逻辑 CPU
+        let per_logical_cpu_load_percent =
+            if current.per_logical_cpu.len() == previous.per_logical_cpu.len() {
+                current
+                    .per_logical_cpu
+                    .iter()
+                    .zip(&previous.per_logical_cpu)
+                    .map(|(current, previous)| {
+                        current
+                            .breakdown_since(previous)
+                            .map(|x| 100.0 - x.idle_percent)
+                    })
+                    .collect()
+            } else {
+                None
+            };
+
+        Some(CpuStats {
+            per_logical_cpu_load_percent,
+            aggregate_load_percent: Some(100.0 - load_breakdown.idle_percent),
+            temp_celsius: cpu_temp(sys, collectors),
+            peak_aggregate_load_percent: None,
+            peak_temp_celsius: None,
+            min_aggregate_load_percent: None,
+            load_breakdown: Some(load_breakdown),
+        })
+    }
+}
+
+/// 获取 CPU 的温度。如果没有启用温度收集器或无法获取，则返回“None”。
+///
+/// # 参数
+/// * `sys` - 指定需要获取信息的系统
+/// * `collectors` - 要使用的收集器
+fn cpu_temp(sys: &impl SystemSource, collectors: &CollectorConfig) -> Option<f32> {
+    if !collectors.is_enabled(Collector::Temperature) {
+        return None;
+    }
+
+    match sys.cpu_temp() {
+        Ok(x) => Some(x),
+        Err(e) => {
+            log("获取 CPU 温度时出错: ", e);
+            None
+        }
+    }
+}
+
+/// 获取 CPU 时间的细分。如果能读取采样开始和结束时的 CPU 时间，则根据两者之差计算，这样可以包含 steal 时间；
+/// 否则使用 `cpu` 中的负载。
+///
+/// # 参数
+/// * `sys` - 指定需要获取信息的系统
+/// * `cpu` - 采样期间的总 CPU 负载
+/// * `cpu_times_start` - 采样开始时的 CPU 时间
+fn cpu_load_breakdown(
+    sys: &impl SystemSource,
+    cpu: &CPULoad,
+    cpu_times_start: Option<CpuTimes>,
+) -> CpuLoadBreakdown {
+    if let (Some(start), Some(end)) = (cpu_times_start, sys.cpu_counters()) {
+        let end = end.aggregate;
+        if let Some(x) = end.breakdown_since(&start) {
+            return x;
+        }
+    }
+
+    CpuLoadBreakdown {
+        user_percent: cpu.user * 100.0,
+        nice_percent: cpu.nice * 100.0,
+        system_percent: cpu.system * 100.0,
+        interrupt_percent: cpu.interrupt * 100.0,
+        idle_percent: cpu.idle * 100.0,
+        iowait_percent: platform_iowait(&cpu.platform).map(|x| x * 100.0),
+        steal_percent: None,
+    }
+}
+
+/// 从 `/proc/stat` 读取的一个或所有 CPU 的累计时间，以时钟周期为单位
+#[derive(Clone, Copy)]
+struct CpuTimes {
+    user: u64,
+    nice: u64,
+    system: u64,
+    idle: u64,
+    iowait: u64,
+    /// 硬中断和软中断
+    interrupt: u64,
+    steal: u64,
+}
+
+impl CpuTimes {
+    /// 根据从 `start` 到此时的 CPU 时间之差计算 CPU 时间的细分。如果两者之间没有经过任何时间，则返回“None”。
+    ///
+    /// # 参数
+    /// * `start` - 较早读取的 CPU 时间
+    fn breakdown_since(&self, start: &CpuTimes) -> Option<CpuLoadBreakdown> {
+        let user = self.user.saturating_sub(start.user);
+        let nice = self.nice.saturating_sub(start.nice);
+        let system = self.system.saturating_sub(start.system);
+        let idle = self.idle.saturating_sub(start.idle);
+        let iowait = self.iowait.saturating_sub(start.iowait);
+        let interrupt = self.interrupt.saturating_sub(start.interrupt);
+        let steal = self.steal.saturating_sub(start.steal);
+        let total = user + nice + system + idle + iowait + interrupt + steal;
+        if total == 0 {
+            return None;
         }
+
+        let percent = |x: u64| x as f32 / total as f32 * 100.0;
+        Some(CpuLoadBreakdown {
+            user_percent: percent(user),
+            nice_percent: percent(nice),
+            system_percent: percent(system),
+            interrupt_percent: percent(interrupt),
+            idle_percent: percent(idle),
+            iowait_percent: Some(percent(iowait)),
+            steal_percent: Some(percent(steal)),
+        })
+    }
+}
+
+/// CPU 时间计数器在某一时刻的值。保存一次读取的结果，之后可以用 `CpuStats::since` 在不阻塞线程的情况下计算这段时间内的 CPU 负载。
+#[derive(Clone)]
+pub struct CpuCounters {
+    /// 所有 CPU 的累计时间
+    aggregate: CpuTimes,
+    /// 每个逻辑 CPU 的累计时间
+    per_logical_cpu: Vec<CpuTimes>,
+}
+
+impl CpuCounters {
+    /// 从 `/proc/stat` 读取 CPU 时间计数器。如果无法读取，则返回“None”。
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn read() -> Option<CpuCounters> {
+        let contents = match std::fs::read_to_string("/proc/stat") {
+            Ok(x) => x,
+            Err(e) => {
+                log("读取 CPU 时间时出错: ", e);
+                return None;
+            }
+        };
+        // 第一行是所有 CPU 的合计，之后每个逻辑 CPU 一行，格式为：cpu[N] user nice system idle iowait irq softirq steal ...
+        let mut aggregate = None;
+        let mut per_logical_cpu = Vec::new();
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let name = match fields.next() {
+                Some(x) if x.starts_with("cpu") => x,
+                _ => continue,
+            };
+            let times = parse_cpu_times(fields);
+            if name == "cpu" {
+                aggregate = Some(times);
+            } else {
+                per_logical_cpu.push(times);
+            }
+        }
+
+        Some(CpuCounters {
+            aggregate: aggregate?,
+            per_logical_cpu,
+        })
+    }
+
+    /// 读取 CPU 时间计数器。此平台不提供这些信息。
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn read() -> Option<CpuCounters> {
+        None
+    }
+}
+
+/// 解析 `/proc/stat` 中一个 CPU 的行在名称之后的字段。
+///
+/// # 参数
+/// * `fields` - 名称之后以空白分隔的字段
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_cpu_times<'a>(fields: impl Iterator<Item = &'a str>) -> CpuTimes {
+    let fields: Vec<u64> = fields.map(|x| x.parse().unwrap_or(0)).collect();
+    // 较旧的内核没有后面的字段
+    let field = |i: usize| fields.get(i).copied().unwrap_or(0);
+    CpuTimes {
+        user: field(0),
+        nice: field(1),
+        system: field(2),
+        idle: field(3),
+        iowait: field(4),
+        interrupt: field(5) + field(6),
+        steal: field(7),
     }
 }
 
-/// 内存统计
+/// 从平台的详细 CPU 负载中获取等待 I/O 的时间比例。
+#[cfg(target_os = "linux")]
+fn platform_iowait(platform: &PlatformCpuLoad) -> Option<f32> {
+    Some(platform.iowait)
+}
+
+/// 从平台的详细 CPU 负载中获取等待 I/O 的时间比例。此平台不提供此信息。
+#[cfg(not(target_os = "linux"))]
+fn platform_iowait(_platform: &PlatformCpuLoad) -> Option<f32> {
+    None
+}
+
+/// 内存统计。以字节为单位保存，这样合并时不会因为反复四舍五入到MB而丢失小于 1MB 的变化。
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(from = "SerializedMemoryStats", into = "SerializedMemoryStats")]
 pub struct MemoryStats {
-    /// 使用的内存，以MB为单位
-    pub used_mb: u64,
-    /// 总内存兆字节，以MB为单位
-    pub total_mb: u64,
+    /// 使用的内存，以字节为单位
+    pub used_bytes: u64,
+    /// 总内存，以字节为单位
+    pub total_bytes: u64,
+    /// 可用内存（包括可回收的缓存），以字节为单位。如果平台不提供此信息，则为“None”。
+    pub available_bytes: Option<u64>,
+    /// 用作页面缓存的内存，以字节为单位。如果平台不提供此信息，则为“None”。
+    pub cached_bytes: Option<u64>,
+    /// 用作缓冲区的内存，以字节为单位。如果平台不提供此信息，则为“None”。
+    pub buffers_bytes: Option<u64>,
+    /// 合并的统计信息中实际使用的内存的最小值，以字节为单位。只有合并后的统计信息才有此值。
+    pub min_effective_used_bytes: Option<u64>,
+    /// 合并的统计信息中实际使用的内存的最大值，以字节为单位。只有合并后的统计信息才有此值。
+    pub max_effective_used_bytes: Option<u64>,
 }
 
 impl MemoryStats {
     /// 获取所提供系统的内存统计信息。如果发生错误，则返回“None”。
-    pub fn from(sys: &System) -> Option<MemoryStats> {
+    pub fn from(sys: &impl SystemSource) -> Option<MemoryStats> {
         match sys.memory() {
             Ok(mem) => {
                 let used_mem = saturating_sub_bytes(mem.total, mem.free);
+                let (available, cached, buffers) = memory_breakdown(&mem.platform_memory);
                 Some(MemoryStats {
-                    used_mb: bytes_to_mb(used_mem),
-                    total_mb: bytes_to_mb(mem.total),
+                    used_bytes: used_mem.as_u64(),
+                    total_bytes: mem.total.as_u64(),
+                    available_bytes: available.map(|x| x.as_u64()),
+                    cached_bytes: cached.map(|x| x.as_u64()),
+                    buffers_bytes: buffers.map(|x| x.as_u64()),
+                    min_effective_used_bytes: None,
+                    max_effective_used_bytes: None,
                 })
             }
             Err(e) => {
@@ -204,6 +1071,111 @@ impl MemoryStats {
             }
         }
     }
+
+    /// 实际使用的内存（总内存减去可用内存），以字节为单位。如果没有可用内存的信息，则返回 `used_bytes`。
+    pub fn effective_used_bytes(&self) -> u64 {
+        match self.available_bytes {
+            Some(available) => self.total_bytes.saturating_sub(available),
+            None => self.used_bytes,
+        }
+    }
+
+    /// 总内存，以MB为单位，不四舍五入
+    pub fn total_mb(&self) -> f64 {
+        bytes_as_mb(self.total_bytes)
+    }
+
+    /// 实际使用的内存，以MB为单位，不四舍五入。详见 `effective_used_bytes`。
+    pub fn effective_used_mb(&self) -> f64 {
+        bytes_as_mb(self.effective_used_bytes())
+    }
+}
+
+/// `MemoryStats` 的序列化格式。为了与之前的 API 和历史文件兼容，仍然包含四舍五入到MB的字段，另外包含以字节为单位的字段。
+/// 反序列化时优先使用以字节为单位的字段；较旧的历史文件或远程代理没有这些字段时，从以MB为单位的字段换算。
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedMemoryStats {
+    used_mb: u64,
+    total_mb: u64,
+    available_mb: Option<u64>,
+    cached_mb: Option<u64>,
+    buffers_mb: Option<u64>,
+    #[serde(default)]
+    min_effective_used_mb: Option<u64>,
+    #[serde(default)]
+    max_effective_used_mb: Option<u64>,
+    #[serde(default)]
+    used_bytes: Option<u64>,
+    #[serde(default)]
+    total_bytes: Option<u64>,
+    #[serde(default)]
+    available_bytes: Option<u64>,
+    #[serde(default)]
+    cached_bytes: Option<u64>,
+    #[serde(default)]
+    buffers_bytes: Option<u64>,
+    #[serde(default)]
+    min_effective_used_bytes: Option<u64>,
+    #[serde(default)]
+    max_effective_used_bytes: Option<u64>,
+}
+
+impl From<MemoryStats> for SerializedMemoryStats {
+    fn from(x: MemoryStats) -> SerializedMemoryStats {
+        SerializedMemoryStats {
+            used_mb: round_bytes_to_mb(x.used_bytes),
+            total_mb: round_bytes_to_mb(x.total_bytes),
+            available_mb: x.available_bytes.map(round_bytes_to_mb),
+            cached_mb: x.cached_bytes.map(round_bytes_to_mb),
+            buffers_mb: x.buffers_bytes.map(round_bytes_to_mb),
+            min_effective_used_mb: x.min_effective_used_bytes.map(round_bytes_to_mb),
+            max_effective_used_mb: x.max_effective_used_bytes.map(round_bytes_to_mb),
+            used_bytes: Some(x.used_bytes),
+            total_bytes: Some(x.total_bytes),
+            available_bytes: x.available_bytes,
+            cached_bytes: x.cached_bytes,
+            buffers_bytes: x.buffers_bytes,
+            min_effective_used_bytes: x.min_effective_used_bytes,
+            max_effective_used_bytes: x.max_effective_used_bytes,
+        }
+    }
+}
+
+impl From<SerializedMemoryStats> for MemoryStats {
+    fn from(x: SerializedMemoryStats) -> MemoryStats {
+        let bytes = |bytes: Option<u64>, mb: Option<u64>| bytes.or_else(|| mb.map(mb_to_bytes));
+        MemoryStats {
+            used_bytes: bytes(x.used_bytes, Some(x.used_mb)).unwrap_or_default(),
+            total_bytes: bytes(x.total_bytes, Some(x.total_mb)).unwrap_or_default(),
+            available_bytes: bytes(x.available_bytes, x.available_mb),
+            cached_bytes: bytes(x.cached_bytes, x.cached_mb),
+            buffers_bytes: bytes(x.buffers_bytes, x.buffers_mb),
+            min_effective_used_bytes: bytes(x.min_effective_used_bytes, x.min_effective_used_mb),
+            max_effective_used_bytes: bytes(x.max_effective_used_bytes, x.max_effective_used_mb),
+        }
+    }
+}
+
+/// 从平台的详细内存信息中获取可用、缓存和缓冲区内存。
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn memory_breakdown(
+    platform_memory: &PlatformMemory,
+) -> (Option<ByteSize>, Option<ByteSize>, Option<ByteSize>) {
+    let meminfo = &platform_memory.meminfo;
+    (
+        meminfo.get("MemAvailable").copied(),
+        meminfo.get("Cached").copied(),
+        meminfo.get("Buffers").copied(),
+    )
+}
+
+/// 从平台的详细内存信息中获取可用、缓存和缓冲区内存。此平台不提供这些信息。
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn memory_breakdown(
+    _platform_memory: &PlatformMemory,
+) -> (Option<ByteSize>, Option<ByteSize>, Option<ByteSize>) {
+    (None, None, None)
 }
 
 /// 已挂载文件系统的统计信息
@@ -218,39 +1190,144 @@ pub struct MountStats {
     pub mounted_on: String,
     /// 此挂载使用的空间（以兆字节为单位）
     pub used_mb: u64,
-    /// 此挂载的总空间（以 MB 为单位）
+    /// 此挂载的总空间（以 MB 为单位）。不足 0.5 MB 的挂载（以及 `/proc` 等虚拟文件系统）为 0，使用者需要处理这种情况。
     pub total_mb: u64,
+    /// 此挂载是否为只读。如果平台不提供挂载选项，则为 `false`。
+    #[serde(default)]
+    pub read_only: bool,
+    /// 此挂载的原始挂载选项（例如 `rw,relatime`）。如果平台不提供此信息，则为“None”。
+    pub mount_options: Option<String>,
 }
 
 impl MountStats {
     /// 获取所提供系统的挂载统计信息列表。仅包含总空间超过 0 字节的挂载。如果发生错误，则返回“None”。
-    pub fn from(sys: &System) -> Option<Vec<MountStats>> {
+    pub fn from(sys: &impl SystemSource) -> Option<Vec<MountStats>> {
         match sys.mounts() {
-            Ok(mounts) => Some(
-                mounts
-                    .into_iter()
-                    .filter_map(|mount| {
-                        if mount.total.as_u64() == 0 {
-                            None
-                        } else {
-                            let used = saturating_sub_bytes(mount.total, mount.avail);
-                            Some(MountStats {
-                                fs_type: mount.fs_type,
-                                mounted_from: mount.fs_mounted_from,
-                                mounted_on: mount.fs_mounted_on,
-                                used_mb: bytes_to_mb(used),
-                                total_mb: bytes_to_mb(mount.total),
-                            })
-                        }
-                    })
-                    .collect(),
-            ),
+            Ok(mounts) => {
+                let mut options_by_mount_point = sys.mount_options();
+                Some(
+                    mounts
+                        .into_iter()
+                        .filter_map(|mount| {
+                            if mount.total.as_u64() == 0 {
+                                None
+                            } else {
+                                let used = saturating_sub_bytes(mount.total, mount.avail);
+                                let mount_options =
+                                    options_by_mount_point.remove(&mount.fs_mounted_on);
+                                let read_only = match &mount_options {
+                                    Some(options) => options.split(',').any(|x| x == "ro"),
+                                    None => false,
+                                };
+                                Some(MountStats {
+                                    fs_type: mount.fs_type,
+                                    mounted_from: mount.fs_mounted_from,
+                                    mounted_on: mount.fs_mounted_on,
+                                    used_mb: bytes_to_mb(used),
+                                    total_mb: bytes_to_mb(mount.total),
+                                    read_only,
+                                    mount_options,
+                                })
+                            }
+                        })
+                        .collect(),
+                )
+            }
             Err(e) => {
                 log("获取挂载信息时出错: ", e);
                 None
             }
         }
     }
+
+    /// 此挂载的可用空间，以字节为单位。由兆字节数换算而来，因此精确到兆字节。
+    pub fn free_bytes(&self) -> u64 {
+        self.total_mb.saturating_sub(self.used_mb) * BYTES_PER_MB
+    }
+
+    /// 此挂载的使用百分比。总大小为 0 时为 0。
+    pub fn used_percent(&self) -> f64 {
+        if self.total_mb == 0 {
+            return 0.0;
+        }
+        ((self.used_mb as f64) / (self.total_mb as f64)) * 100.0
+    }
+}
+
+/// 获取挂载信息的状态。记录超时后仍在获取挂载信息的线程，用于接收它的结果。
+///
+/// 每个收集者应使用自己的 `MountCollection`，这样一个收集者的阻塞线程不会让其他收集者也跳过获取挂载信息。
+#[derive(Default)]
+pub struct MountCollection {
+    /// 超时后仍在获取挂载信息的线程的结果。最多只有一个这样的线程，以免没有响应的挂载使阻塞的线程越来越多。
+    pending: Mutex<Option<mpsc::Receiver<Option<Vec<MountStats>>>>>,
+}
+
+impl MountCollection {
+    /// 在单独的线程中获取挂载统计信息列表，最多等待 `timeout`。
+    ///
+    /// 如果某个挂载（例如 NFS）没有响应，获取挂载信息可能会阻塞很长时间。超时后返回“None”，
+    /// 阻塞的线程会在获取完成后自行退出。在此之前不会再启动新的线程，而是直接返回“None”，因此最多只有一个阻塞的线程。
+    ///
+    /// # 参数
+    /// * `sys` - 指定需要获取信息的系统
+    /// * `timeout` - 最长等待时间
+    pub fn collect(&self, sys: &impl SystemSource, timeout: Duration) -> Option<Vec<MountStats>> {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(receiver) = pending.take() {
+            // 之前超时的线程已经完成时丢弃它的结果，重新获取最新的挂载信息
+            if let Err(TryRecvError::Empty) = receiver.try_recv() {
+                *pending = Some(receiver);
+                //TODO use actual logging once https://github.com/SergioBenitez/Rocket/issues/21 is done
+                println!("之前获取挂载信息的线程仍未完成，已跳过");
+                return None;
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let sys = sys.detached();
+        thread::spawn(move || {
+            // 超时后接收方可能已被丢弃，发送失败可以忽略
+            let _ = sender.send(MountStats::from(&sys));
+        });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(x) => x,
+            Err(_) => {
+                *pending = Some(receiver);
+                //TODO use actual logging once https://github.com/SergioBenitez/Rocket/issues/21 is done
+                println!("获取挂载信息超过 {} 毫秒，已跳过", timeout.as_millis());
+                None
+            }
+        }
+    }
+}
+
+/// 获取每个挂载点的挂载选项，以挂载点为键。如果无法读取，则返回空映射。
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn mount_options() -> HashMap<String, String> {
+    match std::fs::read_to_string("/proc/mounts") {
+        // 每行的格式为：设备 挂载点 类型 选项 dump pass
+        Ok(contents) => contents
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace().skip(1);
+                let mounted_on = fields.next()?;
+                let options = fields.nth(1)?;
+                Some((mounted_on.to_string(), options.to_string()))
+            })
+            .collect(),
+        Err(e) => {
+            log("获取挂载选项时出错: ", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// 获取每个挂载点的挂载选项，以挂载点为键。此平台不提供这些信息。
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn mount_options() -> HashMap<String, String> {
+    HashMap::new()
 }
 
 /// 网络统计
@@ -265,10 +1342,52 @@ pub struct NetworkStats {
 
 impl NetworkStats {
     /// 获取所提供系统的网络统计信息。
-    pub fn from(sys: &System) -> NetworkStats {
+    ///
+    /// # 参数
+    /// * `sys` - 指定需要获取信息的系统
+    /// * `collectors` - 要使用的收集器
+    pub fn from(sys: &impl SystemSource, collectors: &CollectorConfig) -> NetworkStats {
+        let sockets = if collectors.is_enabled(Collector::Sockets) {
+            SocketStats::from(sys)
+        } else {
+            None
+        };
         NetworkStats {
             interfaces: NetworkInterfaceStats::from(sys),
-            sockets: SocketStats::from(sys),
+            sockets,
+        }
+    }
+
+    /// 根据上一次收集的网络统计信息计算每个接口的发送和接收速率。
+    ///
+    /// # 参数
+    /// * `previous` - 上一次收集的网络统计信息。
+    /// * `elapsed_seconds` - 自上一次收集以来经过的秒数。
+    pub fn update_rates(&mut self, previous: &NetworkStats, elapsed_seconds: f64) {
+        if elapsed_seconds <= 0.0 {
+            return;
+        }
+
+        if let (Some(interfaces), Some(previous_interfaces)) =
+            (&mut self.interfaces, &previous.interfaces)
+        {
+            for interface in interfaces {
+                if let Some(previous_interface) = previous_interfaces
+                    .iter()
+                    .find(|x| x.name == interface.name)
+                {
+                    interface.sent_mbps = rate_mbps(
+                        previous_interface.sent_bytes,
+                        interface.sent_bytes,
+                        elapsed_seconds,
+                    );
+                    interface.received_mbps = rate_mbps(
+                        previous_interface.received_bytes,
+                        interface.received_bytes,
+                        elapsed_seconds,
+                    );
+                }
+            }
         }
     }
 }
@@ -281,6 +1400,12 @@ pub struct NetworkInterfaceStats {
     pub name: String,
     /// 与此接口关联的 IP 地址
     pub addresses: Vec<String>,
+    /// 与此接口关联的 IP 地址及其地址族。与 `addresses` 包含相同的地址，但可以区分 IPv4 和 IPv6。
+    #[serde(default)]
+    pub interface_addresses: Vec<InterfaceAddress>,
+    /// 此接口是否为环回接口，即是否有环回地址（例如 `127.0.0.1` 或 `::1`）
+    #[serde(default)]
+    pub loopback: bool,
     /// 通过此接口发送的总兆字节
     pub sent_mb: u64,
     /// 通过此接口接收的总兆字节
@@ -293,31 +1418,56 @@ pub struct NetworkInterfaceStats {
     pub send_errors: u64,
     /// 通过该接口接收数据时发生的错误总数
     pub receive_errors: u64,
+    /// 当前通过此接口发送的速率，以兆比特每秒为单位。如果没有上一次收集的数据，则为“None”。
+    pub sent_mbps: Option<f32>,
+    /// 当前通过此接口接收的速率，以兆比特每秒为单位。如果没有上一次收集的数据，则为“None”。
+    pub received_mbps: Option<f32>,
+    /// 此接口的链路速率，以兆比特每秒为单位。如果平台不提供此信息或速率未知（例如虚拟接口或未连接的接口），则为“None”。
+    #[serde(default)]
+    pub link_speed_mbps: Option<u64>,
+    /// 通过此接口发送的总字节数。仅用于计算速率，不会被序列化。
+    #[serde(skip)]
+    sent_bytes: u64,
+    /// 通过此接口接收的总字节数。仅用于计算速率，不会被序列化。
+    #[serde(skip)]
+    received_bytes: u64,
 }
 
 impl NetworkInterfaceStats {
     /// 获取所提供系统的网络接口统计信息列表。如果发生错误，则返回“None”。
-    pub fn from(sys: &System) -> Option<Vec<NetworkInterfaceStats>> {
+    pub fn from(sys: &impl SystemSource) -> Option<Vec<NetworkInterfaceStats>> {
         match sys.networks() {
             Ok(interfaces) => Some(
                 interfaces
                     .into_iter()
                     .filter_map(|(_, interface)| match sys.network_stats(&interface.name) {
                         Ok(stats) => {
-                            let addresses = interface
+                            let loopback = interface.addrs.iter().any(|x| is_loopback(&x.addr));
+                            let link_speed = link_speed_mbps(&interface.name);
+                            let interface_addresses: Vec<InterfaceAddress> = interface
                                 .addrs
                                 .into_iter()
-                                .filter_map(address_to_string)
+                                .filter_map(InterfaceAddress::from)
                                 .collect();
                             Some(NetworkInterfaceStats {
                                 name: interface.name,
-                                addresses,
+                                addresses: interface_addresses
+                                    .iter()
+                                    .map(|x| x.address.clone())
+                                    .collect(),
+                                interface_addresses,
+                                loopback,
                                 sent_mb: bytes_to_mb(stats.tx_bytes),
                                 received_mb: bytes_to_mb(stats.rx_bytes),
                                 sent_packets: stats.tx_packets,
                                 received_packets: stats.rx_packets,
                                 send_errors: stats.tx_errors,
                                 receive_errors: stats.rx_errors,
+                                sent_mbps: None,
+                                received_mbps: None,
+                                link_speed_mbps: link_speed,
+                                sent_bytes: stats.tx_bytes.as_u64(),
+                                received_bytes: stats.rx_bytes.as_u64(),
                             })
                         }
                         Err(e) => {
@@ -338,6 +1488,53 @@ impl NetworkInterfaceStats {
     }
 }
 
+impl NetworkInterfaceStats {
+    /// 当前发送和接收速率占链路速率的百分比。发送和接收是独立的方向，因此分别计算。
+    /// 如果链路速率未知，则返回“None”；如果某个方向的速率未知，则该方向为“None”。
+    pub fn utilization_percent(&self) -> Option<(Option<f32>, Option<f32>)> {
+        let link_speed = match self.link_speed_mbps {
+            Some(x) if x > 0 => x as f32,
+            _ => return None,
+        };
+        Some((
+            self.sent_mbps.map(|x| x / link_speed * 100.0),
+            self.received_mbps.map(|x| x / link_speed * 100.0),
+        ))
+    }
+}
+
+/// 获取网络接口的链路速率，以兆比特每秒为单位。如果速率未知或无法读取，则返回“None”。
+///
+/// # 参数
+/// * `interface` - 接口名称
+#[cfg(target_os = "linux")]
+fn link_speed_mbps(interface: &str) -> Option<u64> {
+    // 虚拟接口读取时会出错，未连接的接口为 -1
+    let speed = std::fs::read_to_string(format!("/sys/class/net/{}/speed", interface)).ok()?;
+    match speed.trim().parse::<i64>() {
+        Ok(x) if x > 0 => Some(x as u64),
+        _ => None,
+    }
+}
+
+/// 获取网络接口的链路速率。此平台不提供此信息。
+#[cfg(not(target_os = "linux"))]
+fn link_speed_mbps(_interface: &str) -> Option<u64> {
+    None
+}
+
+/// 地址是否为环回地址
+///
+/// # 参数
+/// * `address` - 网络接口的地址
+fn is_loopback(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(x) => x.is_loopback(),
+        IpAddr::V6(x) => x.is_loopback(),
+        _ => false,
+    }
+}
+
 /// 套接字的统计信息
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -356,7 +1553,7 @@ pub struct SocketStats {
 
 impl SocketStats {
     /// 获取所提供系统的套接字统计信息。如果发生错误，则返回“None”。
-    pub fn from(sys: &System) -> Option<SocketStats> {
+    pub fn from(sys: &impl SystemSource) -> Option<SocketStats> {
         match sys.socket_stats() {
             Ok(stats) => Some(SocketStats {
                 tcp_in_use: stats.tcp_sockets_in_use,
@@ -373,25 +1570,249 @@ impl SocketStats {
     }
 }
 
-/// 记录错误消息。如果错误是针对不受支持的统计信息，以调试级别记录。否则以错误级别记录。
+/// 记录不受支持的统计信息，以便每种不受支持的统计信息只记录一次
+struct UnsupportedLog {
+    /// 是否只记录一次每种不受支持的统计信息
+    log_once: bool,
+    /// 已经记录过的不受支持的统计信息的消息
+    reported: BTreeSet<String>,
+    /// 本次收集中不受支持的统计信息的消息
+    reported_this_collection: BTreeSet<String>,
+}
+
+impl UnsupportedLog {
+    /// 记下不受支持的统计信息，并返回是否应该记录它
+    ///
+    /// # 参数
+    /// * `message` - 标识统计信息的消息
+    fn should_log(&mut self, message: &str) -> bool {
+        self.reported_this_collection.insert(message.to_string());
+        !self.log_once || self.reported.insert(message.to_string())
+    }
+
+    /// 结束一次收集。本次收集中没有再报告为不受支持的统计信息已经收集成功，下次不受支持时会再次记录。
+    fn finish_collection(&mut self) {
+        let reported_this_collection = &self.reported_this_collection;
+        self.reported
+            .retain(|message| reported_this_collection.contains(message));
+        self.reported_this_collection.clear();
+    }
+}
+
+/// 记录错误消息。如果错误是针对不受支持的统计信息，以调试级别记录，并且启用 `log_unsupported_once` 时每种只记录一次。
+/// 否则以错误级别记录。
 fn log(message: &str, e: Error) {
     if e.to_string() == "Not supported" {
-        debug!("{}{}", message, e);
+        if UNSUPPORTED_LOG.lock().unwrap().should_log(message) {
+            debug!("{}{}", message, e);
+        }
     } else {
         error!("{}{}", message, e)
     }
 }
 
-/// 获取由提供的 `ByteSize` 表示的兆字节数。
+/// 获取由提供的 `ByteSize` 表示的兆字节数，四舍五入到最接近的整数，这样不足 1 MB 的值（例如 0.5 MB）不会被截断为 0。
 fn bytes_to_mb(byte_size: ByteSize) -> u64 {
-    byte_size.as_u64() / BYTES_PER_MB
+    round_bytes_to_mb(byte_size.as_u64())
 }
 
-/// 获取 `NetworkAddrs` 的字符串表示形式。如果地址不是 IPv4 或 IPv6，则返回“None”。
-fn address_to_string(address: NetworkAddrs) -> Option<String> {
-    match address.addr {
-        IpAddr::V4(x) => Some(x.to_string()),
-        IpAddr::V6(x) => Some(x.to_string()),
-        _ => None,
+/// 将字节数四舍五入为兆字节数
+pub fn round_bytes_to_mb(bytes: u64) -> u64 {
+    bytes.saturating_add(BYTES_PER_MB / 2) / BYTES_PER_MB
+}
+
+/// 将字节数换算为兆字节数，不四舍五入
+pub fn bytes_as_mb(bytes: u64) -> f64 {
+    bytes as f64 / BYTES_PER_MB as f64
+}
+
+/// 将兆字节数换算为字节数
+fn mb_to_bytes(mb: u64) -> u64 {
+    mb.saturating_mul(BYTES_PER_MB)
+}
+
+/// 根据两次读取的字节计数器计算速率，以兆比特每秒为单位。如果计数器被重置或经过的时间不是正数，则返回“None”。
+fn rate_mbps(previous_bytes: u64, current_bytes: u64, elapsed_seconds: f64) -> Option<f32> {
+    if current_bytes < previous_bytes || elapsed_seconds <= 0.0 {
+        None
+    } else {
+        // 以浮点数计算，以免字节数乘以 8 时溢出
+        let bits = (current_bytes - previous_bytes) as f64 * 8.0;
+        Some((bits / BITS_PER_MEGABIT / elapsed_seconds) as f32)
+    }
+}
+
+/// IP 地址的地址族
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// IPv4
+    V4,
+    /// IPv6
+    V6,
+}
+
+/// 与网络接口关联的 IP 地址
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InterfaceAddress {
+    /// 地址族
+    pub family: AddressFamily,
+    /// 地址的字符串表示形式
+    pub address: String,
+}
+
+impl InterfaceAddress {
+    /// 从 `NetworkAddrs` 创建一个 `InterfaceAddress`。如果地址不是 IPv4 或 IPv6，则返回“None”。
+    ///
+    /// # 参数
+    /// * `address` - 网络接口的地址
+    fn from(address: NetworkAddrs) -> Option<InterfaceAddress> {
+        let (family, address) = match address.addr {
+            IpAddr::V4(x) => (AddressFamily::V4, x.to_string()),
+            IpAddr::V6(x) => (AddressFamily::V6, x.to_string()),
+            _ => return None,
+        };
+        Some(InterfaceAddress { family, address })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_source::fixed::FixedSystem;
+
+    #[test]
+    fn all_stats_are_collected_from_system_source() {
+        // 挂载信息在单独的测试中获取，以免受到其他测试中没有完成的线程的影响
+        let collectors = CollectorConfig {
+            disabled_collectors: vec![Collector::Mounts],
+            mount_collection_timeout: Duration::from_secs(5),
+            log_unsupported_once: true,
+            concurrent_collection: false,
+        };
+        let stats = AllStats::from(
+            &FixedSystem::default(),
+            Duration::ZERO,
+            &collectors,
+            &MountCollection::default(),
+        );
+
+        assert_eq!(stats.general.uptime_seconds, Some(3600));
+        assert_eq!(stats.general.boot_timestamp, Some(1_700_000_000));
+        let load_averages = stats.general.load_averages.unwrap();
+        assert_eq!(load_averages.one_minute, 1.5);
+        assert_eq!(load_averages.fifteen_minutes, 0.5);
+        assert_eq!(stats.general.entropy_available, Some(256));
+
+        assert_eq!(stats.cpu.aggregate_load_percent, Some(50.0));
+        assert_eq!(
+            stats.cpu.per_logical_cpu_load_percent,
+            Some(vec![25.0, 75.0])
+        );
+        assert_eq!(stats.cpu.temp_celsius, Some(42.5));
+        // 无法读取 CPU 时间计数器时使用采样得到的负载
+        let load_breakdown = stats.cpu.load_breakdown.unwrap();
+        assert_eq!(load_breakdown.user_percent, 25.0);
+        assert_eq!(load_breakdown.steal_percent, None);
+
+        let memory = stats.memory.unwrap();
+        assert_eq!(memory.total_bytes, 8 * 1024 * 1024 * 1024);
+        assert_eq!(memory.used_bytes, 6 * 1024 * 1024 * 1024);
+        assert_eq!(memory.effective_used_bytes(), 5 * 1024 * 1024 * 1024);
+
+        assert!(stats.filesystems.is_none());
+        let filesystems = MountStats::from(&FixedSystem::default()).unwrap();
+        assert_eq!(filesystems.len(), 1);
+        assert_eq!(filesystems[0].mounted_on, "/");
+        assert_eq!(filesystems[0].total_mb, 100_000);
+        assert_eq!(filesystems[0].used_mb, 60_000);
+        assert!(filesystems[0].read_only);
+
+        assert_eq!(stats.network.interfaces.map(|x| x.len()), Some(0));
+        assert!(stats.network.sockets.is_none());
+    }
+
+    #[test]
+    fn only_one_mount_collection_is_pending() {
+        let (release, gate) = mpsc::channel();
+        let sys = FixedSystem {
+            mount_gate: Some(std::sync::Arc::new(Mutex::new(gate))),
+        };
+        let collection = MountCollection::default();
+        assert!(collection
+            .collect(&sys, Duration::from_millis(50))
+            .is_none());
+        // 之前的线程仍在获取挂载信息时不启动新的线程，即使这次不会超时
+        assert!(collection
+            .collect(&FixedSystem::default(), Duration::from_secs(5))
+            .is_none());
+        // 其他收集者不受阻塞的线程影响
+        let other =
+            MountCollection::default().collect(&FixedSystem::default(), Duration::from_secs(5));
+        assert_eq!(other.map(|x| x.len()), Some(1));
+
+        release.send(()).unwrap();
+        // 等待阻塞的线程发送结果并退出
+        {
+            let pending = collection.pending.lock().unwrap();
+            let receiver = pending.as_ref().unwrap();
+            assert_eq!(receiver.recv().unwrap().map(|x| x.len()), Some(1));
+            assert!(receiver.recv().is_err());
+        }
+        let filesystems = collection.collect(&FixedSystem::default(), Duration::from_secs(5));
+        assert_eq!(filesystems.map(|x| x.len()), Some(1));
+    }
+
+    #[test]
+    fn rates_do_not_overflow_and_require_elapsed_time() {
+        assert_eq!(Some(8.0), rate_mbps(0, 1_000_000, 1.0));
+        assert!(rate_mbps(0, u64::MAX, 1.0).is_some_and(|x| x > 0.0));
+        // 计数器被重置
+        assert_eq!(None, rate_mbps(1_000_000, 0, 1.0));
+        assert_eq!(None, rate_mbps(0, 1_000_000, 0.0));
+        assert_eq!(None, rate_mbps(0, 1_000_000, -1.0));
+    }
+
+    #[test]
+    fn bytes_are_rounded_to_the_nearest_mb() {
+        assert_eq!(0, bytes_to_mb(ByteSize::b(0)));
+        assert_eq!(0, bytes_to_mb(ByteSize::b(499_999)));
+        // 0.5 MB 向上舍入，而不是截断为 0
+        assert_eq!(1, bytes_to_mb(ByteSize::b(500_000)));
+        assert_eq!(1, bytes_to_mb(ByteSize::b(1_499_999)));
+        assert_eq!(2, bytes_to_mb(ByteSize::b(1_500_000)));
+        // 不会溢出
+        assert_eq!(u64::MAX / BYTES_PER_MB, bytes_to_mb(ByteSize::b(u64::MAX)));
+    }
+
+    #[test]
+    fn cpu_stats_since_requires_cpu_counters() {
+        let collectors = CollectorConfig {
+            disabled_collectors: Vec::new(),
+            mount_collection_timeout: Duration::from_secs(5),
+            log_unsupported_once: true,
+            concurrent_collection: false,
+        };
+        let previous = CpuCounters {
+            aggregate: CpuTimes {
+                user: 0,
+                nice: 0,
+                system: 0,
+                idle: 0,
+                iowait: 0,
+                interrupt: 0,
+                steal: 0,
+            },
+            per_logical_cpu: Vec::new(),
+        };
+
+        assert!(AllStats::since(
+            &FixedSystem::default(),
+            &previous,
+            &collectors,
+            &MountCollection::default()
+        )
+        .is_none());
     }
 }
@@ -0,0 +1,114 @@
+//! 两次收集的统计信息之间的差异
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::stats::{round_bytes_to_mb, AllStats};
+
+/// 两次收集的统计信息之间的差异。每个差异都是 `to` 中的值减去 `from` 中的值；如果任一方缺少该值，则为 `None`。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsDiff {
+    /// 较早的统计信息的收集时间
+    pub from_time: DateTime<Local>,
+    /// 较晚的统计信息的收集时间
+    pub to_time: DateTime<Local>,
+    /// 两次收集之间经过的秒数
+    pub elapsed_seconds: i64,
+    /// CPU 总负载百分比的差异
+    pub cpu_load_percent: Option<f32>,
+    /// CPU 温度的差异，以摄氏度为单位
+    pub temp_celsius: Option<f32>,
+    /// 1 分钟平均负载的差异
+    pub load_average_one_minute: Option<f32>,
+    /// 已用内存的差异，以 MB 为单位
+    pub mem_used_mb: Option<i64>,
+    /// 所有网络接口发送的数据量的差异，以 MB 为单位
+    pub sent_mb: Option<i64>,
+    /// 所有网络接口接收的数据量的差异，以 MB 为单位
+    pub received_mb: Option<i64>,
+    /// 进程数的差异
+    pub process_count: Option<i64>,
+    /// 线程数的差异
+    pub thread_count: Option<i64>,
+    /// 正在使用的 TCP 套接字数的差异
+    pub tcp_in_use: Option<i64>,
+}
+
+impl StatsDiff {
+    /// 计算两次收集的统计信息之间的差异。
+    ///
+    /// # 参数
+    /// * `from` - 较早的统计信息
+    /// * `to` - 较晚的统计信息
+    pub fn between(from: &AllStats, to: &AllStats) -> StatsDiff {
+        StatsDiff {
+            from_time: from.collection_time,
+            to_time: to.collection_time,
+            elapsed_seconds: (to.collection_time - from.collection_time).num_seconds(),
+            cpu_load_percent: float_delta(
+                from.cpu.aggregate_load_percent,
+                to.cpu.aggregate_load_percent,
+            ),
+            temp_celsius: float_delta(from.cpu.temp_celsius, to.cpu.temp_celsius),
+            load_average_one_minute: float_delta(
+                from.general.load_averages.as_ref().map(|x| x.one_minute),
+                to.general.load_averages.as_ref().map(|x| x.one_minute),
+            ),
+            mem_used_mb: int_delta(
+                from.memory
+                    .as_ref()
+                    .map(|x| round_bytes_to_mb(x.used_bytes)),
+                to.memory.as_ref().map(|x| round_bytes_to_mb(x.used_bytes)),
+            ),
+            sent_mb: int_delta(total_sent_mb(from), total_sent_mb(to)),
+            received_mb: int_delta(total_received_mb(from), total_received_mb(to)),
+            process_count: int_delta(
+                from.general.process_count.map(|x| x as u64),
+                to.general.process_count.map(|x| x as u64),
+            ),
+            thread_count: int_delta(
+                from.general.thread_count.map(|x| x as u64),
+                to.general.thread_count.map(|x| x as u64),
+            ),
+            tcp_in_use: int_delta(
+                from.network.sockets.as_ref().map(|x| x.tcp_in_use as u64),
+                to.network.sockets.as_ref().map(|x| x.tcp_in_use as u64),
+            ),
+        }
+    }
+}
+
+/// 所有网络接口发送的数据总量，以 MB 为单位
+fn total_sent_mb(stats: &AllStats) -> Option<u64> {
+    stats
+        .network
+        .interfaces
+        .as_ref()
+        .map(|x| x.iter().map(|i| i.sent_mb).sum())
+}
+
+/// 所有网络接口接收的数据总量，以 MB 为单位
+fn total_received_mb(stats: &AllStats) -> Option<u64> {
+    stats
+        .network
+        .interfaces
+        .as_ref()
+        .map(|x| x.iter().map(|i| i.received_mb).sum())
+}
+
+/// 计算两个整数值之间的差异。如果任一值缺失，则返回 `None`。
+fn int_delta(from: Option<u64>, to: Option<u64>) -> Option<i64> {
+    match (from, to) {
+        (Some(from), Some(to)) => Some(to as i64 - from as i64),
+        _ => None,
+    }
+}
+
+/// 计算两个浮点值之间的差异。如果任一值缺失，则返回 `None`。
+fn float_delta(from: Option<f32>, to: Option<f32>) -> Option<f32> {
+    match (from, to) {
+        (Some(from), Some(to)) => Some(to - from),
+        _ => None,
+    }
+}
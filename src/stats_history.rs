@@ -1,25 +1,62 @@
 //! 统计历史
 
+use arc_swap::ArcSwapOption;
+use chrono::{DateTime, Local};
+use rocket::tokio::sync::broadcast;
+use serde::{Deserialize, Serialize};
 use systemstat::System;
 use thread::JoinHandle;
 
+use crate::high_water_marks::{HighWaterMarks, Records};
+use crate::quiet_hours::{current_update_frequency, QuietHours};
+use crate::remote_agent::RemoteAgent;
 use crate::stats::*;
+use crate::system_source::SystemSource;
 use std::{
-    fs::{create_dir_all, File},
-    io::{BufRead, BufReader, Write},
-};
-use std::{
-    fs::{rename, OpenOptions},
-    io,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs::{remove_file, rename, OpenOptions},
+    io, mem,
     num::NonZeroUsize,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
+};
+use std::{
+    fs::{DirBuilder, File},
+    io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
 };
 
 const CURRENT_HISTORY_FILE_NAME: &str = "current_stats.txt";
 const OLD_HISTORY_FILE_NAME: &str = "old_stats.txt";
+const CURRENT_BINARY_HISTORY_FILE_NAME: &str = "current_stats.bin";
+const OLD_BINARY_HISTORY_FILE_NAME: &str = "old_stats.bin";
+const SNAPSHOT_HISTORY_FILE_NAME: &str = "snapshot_stats.json";
+const SNAPSHOT_BINARY_HISTORY_FILE_NAME: &str = "snapshot_stats.bin";
+/// 新收集的统计信息的广播通道能缓存的条目数。接收者落后超过此数量时会跳过最旧的条目。
+const NEW_STATS_CHANNEL_CAPACITY: usize = 16;
+/// JSON 历史文件中每行条目的格式版本。`AllStats` 的格式发生不兼容的变化时递增，并在 `parse_json_line` 中迁移旧版本的条目。
+const JSON_LINE_SCHEMA_VERSION: u32 = 1;
+/// JSON 快照文件的格式版本。快照是一个包含 `version` 和 `stats` 字段的对象；只有条目数组的快照是加入版本之前写入的，视为版本 0。
+const JSON_SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+/// 二进制历史文件和快照文件开头的标记，后面是 4 字节（小端序）的格式版本。没有这个标记的文件是加入版本之前写入的，视为版本 0。
+const BINARY_FILE_MAGIC: &[u8; 4] = b"SSDB";
+/// 二进制历史文件和快照文件的格式版本。从版本 1 开始，每个条目前面有 8 字节（小端序）的长度，因此可以跳过无法解码的条目。
+/// 版本 2 的条目加入了 `after_filtered_entries`，之前版本的条目以 `AllStatsV1` 解码。
+const BINARY_FILE_FORMAT_VERSION: u32 = 2;
+/// 二进制文件开头的标记和格式版本的总长度
+const BINARY_FILE_HEADER_LEN: u64 = 8;
+/// 限制权限时创建的统计历史目录的权限：只有所有者可以读取、写入和进入
+#[cfg(unix)]
+const HISTORY_DIR_MODE: u32 = 0o700;
+/// 限制权限时创建的统计历史文件的权限：只有所有者可以读取和写入
+#[cfg(unix)]
+const HISTORY_FILE_MODE: u32 = 0o600;
+/// 索引文件的扩展名。每个历史文件旁边都有一个索引文件，记录每个条目的字节偏移量和收集时间。
+const INDEX_FILE_EXTENSION: &str = "idx";
 
 /// 定期更新统计历史记录
 pub struct UpdatingStatsHistory {
@@ -27,6 +64,348 @@ pub struct UpdatingStatsHistory {
     _update_thread: JoinHandle<()>,
     /// 统计历史
     pub stats_history: Arc<Mutex<StatsHistory>>,
+    /// 最近收集的统计信息。读取时不需要获取锁，因此读取者不会阻塞更新线程。
+    most_recent_stats: Arc<ArcSwapOption<AllStats>>,
+    /// 是否暂停收集统计信息
+    paused: Arc<AtomicBool>,
+    /// 更新线程和 `sample_now` 共用的收集状态
+    collection: Arc<Mutex<StatsCollection>>,
+    /// 更新线程的健康状况，每次收集后更新
+    collector_health: Arc<Mutex<CollectorHealth>>,
+    /// 每次收集到新的统计信息时，通过此通道发送
+    new_stats_sender: broadcast::Sender<Arc<AllStats>>,
+    /// 历史最高值
+    high_water_marks: Arc<HighWaterMarks>,
+    /// 最近收集的未合并的统计信息，从旧到新排列
+    raw_samples: Arc<Mutex<VecDeque<AllStats>>>,
+}
+
+/// 收集统计信息并更新统计历史所需的状态。由更新线程和 `UpdatingStatsHistory::sample_now` 共用。
+struct StatsCollection {
+    /// 统计信息的来源
+    source: StatsSource,
+    /// 采样 CPU 负载所需的时间
+    cpu_sample_duration: Duration,
+    /// 收集统计信息的频率。也用作从远程代理获取统计信息的超时时间。
+    update_frequency: Duration,
+    /// 何时合并统计数据并将其添加到历史记录
+    consolidation_trigger: ConsolidationTrigger,
+    /// 上一次合并的时间。还没有合并过时为创建的时间。
+    last_consolidation: Instant,
+    /// 将历史记录保存到磁盘的配置
+    persistence_config: HistoryPersistenceConfig,
+    /// 收集统计信息时使用的收集器
+    collectors: CollectorConfig,
+    /// 获取挂载信息的状态，不与其他收集者共用
+    mount_collection: MountCollection,
+    /// 历史最高值，每次收集后更新
+    high_water_marks: Arc<HighWaterMarks>,
+    /// 最近收集的未合并的统计信息，从旧到新排列，用于短期的高分辨率调试
+    raw_samples: Arc<Mutex<VecDeque<AllStats>>>,
+    /// `raw_samples` 中最多保留的条目数。为 0 时不保留。
+    raw_history_size: usize,
+    /// 尚未合并的统计信息
+    recent_stats: Vec<AllStats>,
+    /// 尚未进行长期合并的已合并统计信息
+    long_term_stats: Vec<AllStats>,
+    /// 上一次收集的网络统计信息及其收集时间，用于计算速率
+    previous_network_stats: Option<(NetworkStats, DateTime<Local>)>,
+    /// 上一次从本地系统收集结束时读取的 CPU 时间计数器，用于不阻塞地计算 CPU 负载
+    previous_cpu_counters: Option<CpuCounters>,
+    /// 上一次以追加方式持久化的条目，用于 `PersistenceFilter`
+    last_persisted_stats: Option<AllStats>,
+    /// 上一次以追加方式持久化之后，是否有被 `PersistenceFilter` 跳过的条目
+    filtered_since_last_persist: bool,
+    /// 写入统计历史目录失败、等待重试的条目
+    pending_writes: PendingWrites,
+    /// 统计历史的最后一个条目是否是启动时从快照加载的已合并条目。此时下一次收集需要添加新条目，而不是替换最后一个条目。
+    loaded_from_snapshot: bool,
+    /// 写入长期统计历史目录失败、等待重试的条目
+    pending_long_term_writes: PendingWrites,
+    /// 统计历史
+    stats_history: Arc<Mutex<StatsHistory>>,
+    /// 最近收集的统计信息
+    most_recent_stats: Arc<ArcSwapOption<AllStats>>,
+    /// 每次收集到新的统计信息时，通过此通道发送
+    new_stats_sender: broadcast::Sender<Arc<AllStats>>,
+}
+
+impl StatsCollection {
+    /// 收集一次统计信息，更新统计历史，并在需要时合并和持久化统计信息。返回新收集的统计信息。
+    ///
+    /// # 参数
+    /// * `wait_for_cpu_sample` - 是否阻塞 `cpu_sample_duration` 采样 CPU 负载。为 `false` 时根据上一次收集以来的
+    ///   CPU 时间计数器之差计算 CPU 负载，并立即返回；如果还没有收集过或此平台无法读取计数器，则仍然阻塞采样。
+    fn collect(&mut self, wait_for_cpu_sample: bool) -> io::Result<AllStats> {
+        let mut new_stats = match &self.source {
+            StatsSource::Local(system) => {
+                let stats = match &self.previous_cpu_counters {
+                    Some(previous) if !wait_for_cpu_sample => {
+                        AllStats::since(system, previous, &self.collectors, &self.mount_collection)
+                    }
+                    _ => None,
+                };
+                let stats = stats.unwrap_or_else(|| {
+                    AllStats::from(
+                        system,
+                        self.cpu_sample_duration,
+                        &self.collectors,
+                        &self.mount_collection,
+                    )
+                });
+                self.previous_cpu_counters = system.cpu_counters();
+                stats
+            }
+            StatsSource::Remote(agent) => {
+                agent.fetch_stats(self.update_frequency).map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("从 {} 获取统计信息时出错: {}", agent.name(), e),
+                    )
+                })?
+            }
+        };
+        // 远程代理已经根据它自己的收集间隔计算了速率，这里按获取的间隔重新计算反而会受到网络延迟的影响
+        if let StatsSource::Local(_) = &self.source {
+            if let Some((previous_network, previous_time)) = &self.previous_network_stats {
+                let elapsed = new_stats.collection_time - *previous_time;
+                new_stats
+                    .network
+                    .update_rates(previous_network, elapsed.num_milliseconds() as f64 / 1000.0);
+            }
+            self.previous_network_stats =
+                Some((new_stats.network.clone(), new_stats.collection_time));
+        }
+        self.high_water_marks.update(&new_stats);
+        if self.raw_history_size > 0 {
+            let mut raw_samples = self.raw_samples.lock().unwrap();
+            if raw_samples.len() >= self.raw_history_size {
+                raw_samples.pop_front();
+            }
+            raw_samples.push_back(new_stats.clone());
+        }
+        let shared_new_stats = Arc::new(new_stats.clone());
+        self.most_recent_stats
+            .store(Some(Arc::clone(&shared_new_stats)));
+        // 没有订阅者时发送会失败，可以忽略
+        let _ = self.new_stats_sender.send(shared_new_stats);
+        self.recent_stats.push(new_stats.clone());
+
+        let should_consolidate = match self.consolidation_trigger {
+            ConsolidationTrigger::Count(count) => self.recent_stats.len() >= count.get(),
+            ConsolidationTrigger::Interval(interval) => {
+                self.last_consolidation.elapsed() >= interval
+            }
+        };
+        if should_consolidate {
+            self.last_consolidation = Instant::now();
+            let recent_stats = mem::take(&mut self.recent_stats);
+            let consolidated_stats = consolidate_all_stats(recent_stats);
+            if let HistoryPersistenceConfig::Enabled {
+                dir,
+                size_limit,
+                format,
+                mode,
+                long_term,
+                filter,
+                restrict_permissions,
+                file_count,
+                ..
+            } = &self.persistence_config
+            {
+                let should_persist = match filter {
+                    Some(filter) => filter
+                        .should_persist(self.last_persisted_stats.as_ref(), &consolidated_stats),
+                    None => true,
+                };
+                if *mode == PersistenceMode::Append {
+                    if !should_persist {
+                        self.filtered_since_last_persist = true;
+                    } else if self.pending_writes.persist(
+                        AllStats {
+                            after_filtered_entries: self.filtered_since_last_persist,
+                            ..consolidated_stats.clone()
+                        },
+                        dir,
+                        *size_limit,
+                        *file_count,
+                        *format,
+                        *restrict_permissions,
+                    ) {
+                        self.last_persisted_stats = Some(consolidated_stats.clone());
+                        self.filtered_since_last_persist = false;
+                    }
+                }
+
+                if let Some(long_term) = long_term {
+                    self.long_term_stats.push(consolidated_stats.clone());
+                    if self.long_term_stats.len() >= long_term.consolidation_factor.get() {
+                        let long_term_consolidated_stats =
+                            consolidate_all_stats(mem::take(&mut self.long_term_stats));
+                        self.pending_long_term_writes.persist(
+                            long_term_consolidated_stats,
+                            &long_term.dir,
+                            *size_limit,
+                            *file_count,
+                            *format,
+                            *restrict_permissions,
+                        );
+                    }
+                }
+            }
+
+            let snapshot = {
+                let mut history = self.stats_history.lock().unwrap();
+                if mem::take(&mut self.loaded_from_snapshot) {
+                    history.push(consolidated_stats);
+                } else {
+                    history.update_most_recent_stats(consolidated_stats);
+                }
+                // 在添加下一个尚未合并的条目之前获取快照，使快照只包含已合并的条目
+                let snapshot = match &self.persistence_config {
+                    HistoryPersistenceConfig::Enabled {
+                        mode: PersistenceMode::Snapshot,
+                        ..
+                    } => Some((&*history).into_iter().cloned().collect::<Vec<_>>()),
+                    _ => None,
+                };
+                history.push(new_stats.clone());
+                snapshot
+            };
+            if let (
+                Some(snapshot),
+                HistoryPersistenceConfig::Enabled {
+                    dir,
+                    format,
+                    restrict_permissions,
+                    ..
+                },
+            ) = (snapshot, &self.persistence_config)
+            {
+                if let Err(e) = persist_snapshot(&snapshot, dir, *format, *restrict_permissions) {
+                    println!("将统计信息持久保存到 {:?}: {}", dir, e);
+                }
+            }
+        } else {
+            let mut history = self.stats_history.lock().unwrap();
+            if mem::take(&mut self.loaded_from_snapshot) {
+                history.push(new_stats.clone());
+            } else {
+                history.update_most_recent_stats(new_stats.clone());
+            }
+        }
+
+        Ok(new_stats)
+    }
+}
+
+/// 统计信息收集的状态
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionStatus {
+    /// 是否暂停收集统计信息
+    pub paused: bool,
+}
+
+/// 后台收集线程的健康状况，用于发现卡住或持续失败的收集
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectorHealth {
+    /// 最近一次成功收集的时间。还没有成功收集过时为 `None`。
+    pub last_success_time: Option<DateTime<Local>>,
+    /// 自最近一次成功收集以来连续失败的次数
+    pub consecutive_failures: u64,
+    /// 成功收集的总次数
+    pub total_samples: u64,
+}
+
+impl CollectorHealth {
+    /// 记录一次成功的收集。
+    ///
+    /// # 参数
+    /// * `time` - 收集的时间
+    fn record_success(&mut self, time: DateTime<Local>) {
+        self.last_success_time = Some(time);
+        self.consecutive_failures = 0;
+        self.total_samples += 1;
+    }
+
+    /// 记录一次失败的收集。
+    fn record_failure(&mut self) {
+        self.consecutive_failures += 1;
+    }
+}
+
+/// 持久化的统计历史的概况。字段名使用蛇形命名，便于外部监控读取。
+#[derive(Serialize)]
+pub struct HistoryInfo {
+    /// 统计历史文件（包括索引文件）当前占用的字节数
+    pub bytes_used: u64,
+    /// 统计历史目录的大小限制，以字节为单位
+    pub bytes_limit: u64,
+    /// 持久化的条目数
+    pub entry_count: usize,
+    /// 最旧条目的收集时间。没有条目时为 `None`。
+    pub oldest_sample_time: Option<DateTime<Local>>,
+    /// 最新条目的收集时间。没有条目时为 `None`。
+    pub newest_sample_time: Option<DateTime<Local>>,
+}
+
+impl HistoryInfo {
+    /// 读取目录中持久化的统计历史，获取其概况。
+    ///
+    /// # 参数
+    /// * `dir` - 统计历史目录
+    /// * `size_limit` - 统计历史目录的大小限制，以字节为单位
+    pub fn load(dir: &Path, size_limit: u64) -> io::Result<HistoryInfo> {
+        let history = StatsHistory::load_from(dir)?;
+        let (entry_count, oldest_sample_time) = match history.get_most_recent_stats() {
+            Some(_) => (
+                history.stats.len(),
+                history.into_iter().next().map(|x| x.collection_time),
+            ),
+            None => (0, None),
+        };
+
+        Ok(HistoryInfo {
+            bytes_used: history_files_size(dir)?,
+            bytes_limit: size_limit,
+            entry_count,
+            oldest_sample_time,
+            newest_sample_time: history.get_most_recent_stats().map(|x| x.collection_time),
+        })
+    }
+}
+
+/// 统计信息的来源
+pub enum StatsSource {
+    /// 从本机收集统计信息
+    Local(System),
+    /// 从远程代理获取统计信息
+    Remote(RemoteAgent),
+}
+
+/// 何时合并最近收集的统计信息并将其添加到历史记录
+#[derive(Clone, Copy, Debug)]
+pub enum ConsolidationTrigger {
+    /// 收集了这么多次统计信息后合并
+    Count(NonZeroUsize),
+    /// 距离上一次合并经过了这么长时间后合并，无论收集了多少次
+    Interval(Duration),
+}
+
+impl ConsolidationTrigger {
+    /// 历史记录中相邻两个条目之间的预期间隔
+    ///
+    /// # 参数
+    /// * `update_frequency` - 收集统计信息的频率
+    pub fn expected_entry_interval(&self, update_frequency: Duration) -> Duration {
+        match self {
+            ConsolidationTrigger::Count(count) => update_frequency * count.get() as u32,
+            // 每次收集后才检查是否需要合并，因此间隔不会短于收集频率
+            ConsolidationTrigger::Interval(interval) => (*interval).max(update_frequency),
+        }
+    }
 }
 
 /// 统计历史持久化的配置
@@ -40,65 +419,418 @@ pub enum HistoryPersistenceConfig {
         dir: PathBuf,
         /// 允许保存的统计历史目录增长到的最大大小，以字节为单位
         size_limit: u64,
+        /// 持久化统计信息所用的文件格式
+        format: PersistenceFormat,
+        /// 持久化统计信息的写入方式
+        mode: PersistenceMode,
+        /// 长期保存统计历史的配置。如果为 `None`，则只保存一层统计历史。
+        long_term: Option<LongTermPersistenceConfig>,
+        /// 只在统计信息有明显变化时才以追加方式持久化的过滤条件。如果为 `None`，则持久化每个合并后的条目。
+        filter: Option<PersistenceFilter>,
+        /// 是否只允许当前用户访问创建的目录和文件（目录权限为 0700，文件权限为 0600）。只在 Unix 上有效。
+        restrict_permissions: bool,
+        /// 以追加方式持久化时轮换的历史文件数量。目录的大小限制平均分配给每个文件。
+        file_count: NonZeroUsize,
+        /// 以追加方式持久化时，写入失败的条目最多在内存中缓冲多少个，以便在下一次写入时重试。为 0 时不重试。
+        retry_buffer_size: usize,
     },
 }
 
+/// 决定是否以追加方式持久化一个合并后的条目的过滤条件，用于压缩长时间空闲期间几乎不变的统计历史。
+/// 内存中的统计历史不受影响。
+#[derive(Clone)]
+pub struct PersistenceFilter {
+    /// CPU 总负载、内存使用率或任何文件系统的使用率与上一次持久化的条目相比变化超过多少个百分点时才持久化
+    pub change_threshold_percent: f32,
+    /// 距离上一次持久化的条目超过此时间时，即使没有明显变化也持久化，以确保持久化的历史不会中断太久
+    pub max_gap: Duration,
+}
+
+impl PersistenceFilter {
+    /// 判断是否应该持久化 `new`。
+    ///
+    /// # 参数
+    /// * `last_persisted` - 上一次持久化的条目。还没有持久化过时为 `None`。
+    /// * `new` - 新的合并后的条目
+    fn should_persist(&self, last_persisted: Option<&AllStats>, new: &AllStats) -> bool {
+        let last_persisted = match last_persisted {
+            Some(x) => x,
+            None => return true,
+        };
+        match (new.collection_time - last_persisted.collection_time).to_std() {
+            Ok(elapsed) if elapsed < self.max_gap => (),
+            // 时间倒退时也持久化，避免一直不写入
+            _ => return true,
+        }
+
+        let changed = |previous: Option<f64>, new: Option<f64>| match (previous, new) {
+            (Some(previous), Some(new)) => {
+                (new - previous).abs() > self.change_threshold_percent as f64
+            }
+            (None, None) => false,
+            _ => true,
+        };
+        let memory_used_percent = |stats: &AllStats| {
+            stats
+                .memory
+                .as_ref()
+                .filter(|x| x.total_bytes > 0)
+                .map(|x| x.effective_used_bytes() as f64 / x.total_bytes as f64 * 100.0)
+        };
+        let filesystem_used_percents = |stats: &AllStats| -> BTreeMap<String, f64> {
+            stats
+                .filesystems
+                .iter()
+                .flatten()
+                .map(|x| (x.mounted_on.clone(), x.used_percent()))
+                .collect()
+        };
+
+        let previous_filesystems = filesystem_used_percents(last_persisted);
+        let new_filesystems = filesystem_used_percents(new);
+        changed(
+            last_persisted.cpu.aggregate_load_percent.map(f64::from),
+            new.cpu.aggregate_load_percent.map(f64::from),
+        ) || changed(
+            memory_used_percent(last_persisted),
+            memory_used_percent(new),
+        ) || previous_filesystems.len() != new_filesystems.len()
+            || new_filesystems.iter().any(|(mount, used_percent)| {
+                changed(
+                    previous_filesystems.get(mount).copied(),
+                    Some(*used_percent),
+                )
+            })
+    }
+}
+
+/// 长期统计历史持久化的配置。长期历史以更低的分辨率保存更长时间的统计信息。
+#[derive(Clone)]
+pub struct LongTermPersistenceConfig {
+    /// 将长期统计历史记录保存到的目录。大小限制和文件格式与主目录相同。
+    pub dir: PathBuf,
+    /// 在合并统计数据并将其保存到长期历史之前收集的已合并统计数据的数量
+    pub consolidation_factor: NonZeroUsize,
+}
+
+/// 持久化统计信息的文件格式
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PersistenceFormat {
+    /// 每行一个 JSON 条目
+    Json,
+    /// 连续写入的 bincode 条目，比 JSON 更紧凑
+    Bincode,
+}
+
+/// 持久化统计信息的写入方式
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PersistenceMode {
+    /// 每次合并后将新条目追加到文件末尾，文件达到大小限制的一半时轮换
+    Append,
+    /// 每次合并后将内存中的整个统计历史写入一个文件，覆盖之前的内容。磁盘占用由 `history_size` 精确限定，但每次写入的数据更多。
+    Snapshot,
+}
+
+/// 分页查看统计历史时各页的顺序
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryPageOrder {
+    /// 第一页包含最旧的条目
+    Oldest,
+    /// 第一页包含最新的条目
+    Newest,
+}
+
+impl PersistenceFormat {
+    /// 所有支持的格式
+    const ALL: [PersistenceFormat; 2] = [PersistenceFormat::Json, PersistenceFormat::Bincode];
+
+    /// 以此格式保存最新统计信息的文件名
+    fn current_file_name(self) -> &'static str {
+        match self {
+            PersistenceFormat::Json => CURRENT_HISTORY_FILE_NAME,
+            PersistenceFormat::Bincode => CURRENT_BINARY_HISTORY_FILE_NAME,
+        }
+    }
+
+    /// 以此格式保存较旧统计信息的文件名
+    fn old_file_name(self) -> &'static str {
+        match self {
+            PersistenceFormat::Json => OLD_HISTORY_FILE_NAME,
+            PersistenceFormat::Bincode => OLD_BINARY_HISTORY_FILE_NAME,
+        }
+    }
+
+    /// 以此格式保存的历史文件轮换中第 `index` 个文件的文件名。第 0 个文件保存最新的统计信息，编号越大越旧。
+    /// 前两个文件沿用只有两个文件时的文件名，因此更改文件数量后仍能读取之前的统计历史。
+    ///
+    /// # 参数
+    /// * `index` - 文件在轮换中的位置
+    fn ring_file_name(self, index: usize) -> String {
+        match index {
+            0 => self.current_file_name().to_string(),
+            1 => self.old_file_name().to_string(),
+            _ => {
+                let (stem, extension) = self
+                    .old_file_name()
+                    .rsplit_once('.')
+                    .expect("历史文件名应该有扩展名");
+                format!("{}.{}.{}", stem, index, extension)
+            }
+        }
+    }
+
+    /// 目录中以此格式保存的所有历史文件的路径，从旧到新排列。最新的文件即使不存在也包括在内。
+    ///
+    /// 从第 1 个文件开始查找，直到遇到不存在的文件为止，因此不需要知道配置的文件数量。轮换总是连续地移动文件，
+    /// 所以存在的文件编号不会有间隔。
+    ///
+    /// # 参数
+    /// * `dir` - 统计历史目录
+    fn ring_file_paths(self, dir: &Path) -> Vec<PathBuf> {
+        let mut paths = vec![dir.join(self.ring_file_name(0))];
+        for index in 1.. {
+            let path = dir.join(self.ring_file_name(index));
+            if !path.exists() {
+                break;
+            }
+            paths.push(path);
+        }
+        paths.reverse();
+        paths
+    }
+
+    /// 以此格式保存统计历史快照的文件名
+    fn snapshot_file_name(self) -> &'static str {
+        match self {
+            PersistenceFormat::Json => SNAPSHOT_HISTORY_FILE_NAME,
+            PersistenceFormat::Bincode => SNAPSHOT_BINARY_HISTORY_FILE_NAME,
+        }
+    }
+}
+
 impl UpdatingStatsHistory {
     /// 创建一个`UpdatingStatsHistory`。
     ///
     /// # 参数
-    /// * `system` - 待收集统计信息的系统。
-    /// * `cpu_sample_duration` - 采样 CPU 负载所需的时间。必须小于`update_frequency`。
+    /// * `source` - 统计信息的来源。
+    /// * `cpu_sample_duration` - 采样 CPU 负载所需的时间。必须小于`update_frequency`。从远程代理获取统计信息时不使用。
     /// * `update_frequency` - 应该多久收集一次新的统计数据。必须大于 `cpu_sample_duration`。
+    /// * `sample_jitter` - 每次收集之间的休眠时间随机增加的最大值。为零时按 `update_frequency` 精确收集。
+    /// * `quiet_hours` - 以较低的频率收集统计信息的安静时段。为 `None` 时始终按 `update_frequency` 收集。
     /// * `history_size` - 保留在历史记录中的最大条目数。
-    /// * `consolidation_limit` - 在合并统计数据并将其添加到历史记录之前收集统计数据的次数。
+    /// * `raw_history_size` - 在内存中另外保留的最近未合并的统计信息的最大条目数。为 0 时不保留。
+    /// * `consolidation_trigger` - 何时合并统计数据并将其添加到历史记录。
     /// * `persistence_config` - 将历史记录保存到磁盘的配置。
+    /// * `collectors` - 收集统计信息时使用的收集器。
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        system: System,
+        source: StatsSource,
         cpu_sample_duration: Duration,
         update_frequency: Duration,
+        sample_jitter: Duration,
+        quiet_hours: Option<QuietHours>,
         history_size: NonZeroUsize,
-        consolidation_limit: NonZeroUsize,
+        raw_history_size: usize,
+        consolidation_trigger: ConsolidationTrigger,
         persistence_config: HistoryPersistenceConfig,
+        collectors: CollectorConfig,
     ) -> UpdatingStatsHistory {
-        //TODO instead of maintaining this list, keep a single moving average?
-        let mut recent_stats = Vec::with_capacity(consolidation_limit.get());
-        let shared_stats_history = Arc::new(Mutex::new(StatsHistory::new(history_size)));
-        let update_thread_stats_history = Arc::clone(&shared_stats_history);
-        let update_thread = thread::spawn(move || loop {
-            let new_stats = AllStats::from(&system, cpu_sample_duration);
-            recent_stats.push(new_stats.clone());
-
-            if recent_stats.len() >= consolidation_limit.get() {
-                let consolidated_stats = consolidate_all_stats(recent_stats);
-                if let HistoryPersistenceConfig::Enabled { dir, size_limit } = &persistence_config {
-                    if let Err(e) = persist_stats(&consolidated_stats, dir, *size_limit) {
-                        //TODO use actual logging once https://github.com/SergioBenitez/Rocket/issues/21 is done
-                        println!("将统计信息持久保存到 {:?}: {}", dir, e);
-                    }
+        let mut stats_history = StatsHistory::new(history_size);
+        // 快照模式下每次写入都会覆盖快照文件，因此先加载之前的快照，以免重启后的第一次写入丢失之前持久化的历史
+        let loaded_from_snapshot = match &persistence_config {
+            HistoryPersistenceConfig::Enabled {
+                dir,
+                format,
+                mode: PersistenceMode::Snapshot,
+                ..
+            } => {
+                let mut snapshot = Vec::new();
+                let path = dir.join(format.snapshot_file_name());
+                if let Err(e) = add_stats_from_snapshot(path.clone(), *format, &mut snapshot) {
+                    println!("从 {:?} 加载统计历史快照时出错: {}", path, e);
                 }
-
-                {
-                    let mut history = update_thread_stats_history.lock().unwrap();
-                    history.update_most_recent_stats(consolidated_stats);
-                    history.push(new_stats);
+                snapshot.sort_by_key(|x| x.collection_time);
+                let loaded = !snapshot.is_empty();
+                for stats in snapshot {
+                    stats_history.push(stats);
                 }
-                recent_stats = Vec::with_capacity(consolidation_limit.get());
-            } else {
-                let mut history = update_thread_stats_history.lock().unwrap();
-                history.update_most_recent_stats(new_stats);
+                loaded
+            }
+            _ => false,
+        };
+        let shared_stats_history = Arc::new(Mutex::new(stats_history));
+        let most_recent_stats = Arc::new(ArcSwapOption::empty());
+        let (new_stats_sender, _) = broadcast::channel(NEW_STATS_CHANNEL_CAPACITY);
+        // 启用持久化时，最高值保存在统计历史目录中
+        let high_water_marks = Arc::new(match &persistence_config {
+            HistoryPersistenceConfig::Enabled {
+                dir,
+                restrict_permissions,
+                ..
+            } => HighWaterMarks::new(Some(dir), *restrict_permissions),
+            HistoryPersistenceConfig::Disabled => HighWaterMarks::new(None, false),
+        });
+        let raw_samples = Arc::new(Mutex::new(VecDeque::with_capacity(raw_history_size)));
+        let retry_buffer_size = match &persistence_config {
+            HistoryPersistenceConfig::Enabled {
+                retry_buffer_size, ..
+            } => *retry_buffer_size,
+            HistoryPersistenceConfig::Disabled => 0,
+        };
+        let collection = Arc::new(Mutex::new(StatsCollection {
+            source,
+            cpu_sample_duration,
+            update_frequency,
+            consolidation_trigger,
+            last_consolidation: Instant::now(),
+            persistence_config,
+            collectors,
+            mount_collection: MountCollection::default(),
+            high_water_marks: Arc::clone(&high_water_marks),
+            raw_samples: Arc::clone(&raw_samples),
+            raw_history_size,
+            //TODO instead of maintaining this list, keep a single moving average?
+            recent_stats: Vec::new(),
+            long_term_stats: Vec::new(),
+            previous_network_stats: None,
+            previous_cpu_counters: None,
+            last_persisted_stats: None,
+            filtered_since_last_persist: false,
+            pending_writes: PendingWrites::new(retry_buffer_size),
+            loaded_from_snapshot,
+            pending_long_term_writes: PendingWrites::new(retry_buffer_size),
+            stats_history: Arc::clone(&shared_stats_history),
+            most_recent_stats: Arc::clone(&most_recent_stats),
+            new_stats_sender: new_stats_sender.clone(),
+        }));
+        let update_thread_collection = Arc::clone(&collection);
+        let paused = Arc::new(AtomicBool::new(false));
+        let update_thread_paused = Arc::clone(&paused);
+        let collector_health = Arc::new(Mutex::new(CollectorHealth::default()));
+        let update_thread_collector_health = Arc::clone(&collector_health);
+        let update_thread = thread::spawn(move || loop {
+            // 每次迭代都根据当前时间重新决定频率，以便进入或离开安静时段时切换
+            let (update_frequency, max_sleep) =
+                current_update_frequency(quiet_hours.as_ref(), update_frequency);
+            if update_thread_paused.load(Ordering::Relaxed) {
+                thread::sleep(update_frequency.min(max_sleep));
+                continue;
+            }
+
+            let iteration_start = Instant::now();
+            // 收集期间持有锁，这样 `sample_now` 不会与更新线程同时收集
+            let result = update_thread_collection.lock().unwrap().collect(true);
+            match &result {
+                Ok(x) => update_thread_collector_health
+                    .lock()
+                    .unwrap()
+                    .record_success(x.collection_time),
+                Err(_) => update_thread_collector_health
+                    .lock()
+                    .unwrap()
+                    .record_failure(),
+            }
+            if let Err(e) = result {
+                println!("{}", e);
+                thread::sleep(
+                    update_frequency
+                        .saturating_sub(iteration_start.elapsed())
+                        .min(max_sleep),
+                );
+                continue;
             }
 
-            thread::sleep(update_frequency - cpu_sample_duration);
+            // 减去本次迭代实际花费的时间（采样、收集和持久化），使收集周期保持为 `update_frequency`
+            thread::sleep(sleep_duration_with_jitter(
+                update_frequency
+                    .saturating_sub(iteration_start.elapsed())
+                    .min(max_sleep),
+                sample_jitter,
+            ));
         });
 
         UpdatingStatsHistory {
             _update_thread: update_thread,
             stats_history: shared_stats_history,
+            most_recent_stats,
+            paused,
+            collection,
+            collector_health,
+            new_stats_sender,
+            high_water_marks,
+            raw_samples,
+        }
+    }
+
+    /// 立即收集一次统计信息并更新历史记录，而不等待更新线程的下一次收集。返回新收集的统计信息。
+    /// 如果更新线程正在收集，则等待它完成后再收集。
+    ///
+    /// # 参数
+    /// * `wait_for_cpu_sample` - 是否阻塞采样 CPU 负载。为 `false` 时不阻塞，而是返回上一次收集以来的平均 CPU 负载，
+    ///   其准确性取决于距离上一次收集经过的时间。
+    pub fn sample_now(&self, wait_for_cpu_sample: bool) -> io::Result<AllStats> {
+        self.collection.lock().unwrap().collect(wait_for_cpu_sample)
+    }
+
+    /// 获取最近收集的统计信息。如果还没有收集到任何统计信息，则返回“None”。
+    pub fn get_most_recent_stats(&self) -> Option<Arc<AllStats>> {
+        self.most_recent_stats.load_full()
+    }
+
+    /// 订阅之后收集的统计信息。每次收集到新的统计信息时，返回的接收者都会收到它。
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<AllStats>> {
+        self.new_stats_sender.subscribe()
+    }
+
+    /// 暂停收集统计信息。暂停期间，更新线程只会休眠而不会采样。
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// 恢复收集统计信息。
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// 获取内存中保留的最近未合并的统计信息，从旧到新排列。
+    pub fn raw_samples(&self) -> Vec<AllStats> {
+        self.raw_samples.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// 获取各项统计信息的历史最高值。
+    pub fn records(&self) -> Records {
+        self.high_water_marks.get()
+    }
+
+    /// 获取更新线程的健康状况。
+    pub fn collector_health(&self) -> CollectorHealth {
+        self.collector_health.lock().unwrap().clone()
+    }
+
+    /// 获取统计信息收集的当前状态。
+    pub fn collection_status(&self) -> CollectionStatus {
+        CollectionStatus {
+            paused: self.paused.load(Ordering::Relaxed),
         }
     }
 }
 
+/// 计算两次收集之间的休眠时间。
+///
+/// # 参数
+/// * `base` - 不带抖动的休眠时间。
+/// * `jitter` - 抖动的范围。休眠时间会随机增加 0 到 `jitter`，因此平均间隔增加 `jitter / 2`。
+fn sleep_duration_with_jitter(base: Duration, jitter: Duration) -> Duration {
+    if jitter.is_zero() {
+        return base;
+    }
+
+    base + Duration::from_millis(fastrand::u64(0..=jitter.as_millis() as u64))
+}
+
 /// 合并所有统计数据
 ///
 /// # 参数
@@ -113,18 +845,36 @@ fn consolidate_all_stats(mut stats_list: Vec<AllStats>) -> AllStats {
     let mut average_five_min_load_average = 0.0;
     let mut average_fifteen_min_load_average = 0.0;
 
+    let mut average_process_count = 0.0;
+    let mut process_count_count = 0;
+    let mut average_thread_count = 0.0;
+    let mut thread_count_count = 0;
+
     let mut average_per_logical_cpu_loads = Vec::new();
-    let mut average_aggregate_cpu_load = 0.0;
+    let mut aggregate_cpu_load = Aggregate::default();
     let mut average_temp = 0.0;
+    let mut peak_temp: Option<f32> = None;
+    let mut load_breakdowns = Vec::new();
 
-    let mut average_mem_used = 0.0;
+    let mut average_mem_used = 0.0_f64;
     let mut max_total_mem = 0;
+    let mut min_effective_mem_used: Option<u64> = None;
+    let mut max_effective_mem_used: Option<u64> = None;
+    let mut average_mem_available = 0.0_f64;
+    let mut mem_available_count = 0;
+    let mut average_mem_cached = 0.0_f64;
+    let mut mem_cached_count = 0;
+    let mut average_mem_buffers = 0.0_f64;
+    let mut mem_buffers_count = 0;
+
+    let mut average_collection_duration = 0.0;
 
     let mut average_tcp_used = 0.0;
     let mut average_tcp_orphaned = 0.0;
     let mut average_udp_used = 0.0;
     let mut average_tcp6_used = 0.0;
     let mut average_udp6_used = 0.0;
+    let mut socket_stats_count = 0;
 
     for (i, all_stats) in stats_list.iter().enumerate() {
         // 更新平均负载
@@ -137,119 +887,861 @@ fn consolidate_all_stats(mut stats_list: Vec<AllStats>) -> AllStats {
                 .updated_average(load_averages.fifteen_minutes, i + 1);
         }
 
+        // 更新进程和线程数量
+        if let Some(process_count) = all_stats.general.process_count {
+            process_count_count += 1;
+            average_process_count =
+                average_process_count.updated_average(process_count as f32, process_count_count);
+        }
+        if let Some(thread_count) = all_stats.general.thread_count {
+            thread_count_count += 1;
+            average_thread_count =
+                average_thread_count.updated_average(thread_count as f32, thread_count_count);
+        }
+
         // 更新每个CPU的平均负载
         if let Some(loads) = &all_stats.cpu.per_logical_cpu_load_percent {
             average_per_logical_cpu_loads.update_averages(loads, i + 1);
         }
 
         // 更新CPU整体负载
-        if let Some(aggregate) = &all_stats.cpu.aggregate_load_percent {
-            average_aggregate_cpu_load =
-                average_aggregate_cpu_load.updated_average(*aggregate, i + 1);
+        if let Some(aggregate) = all_stats.cpu.aggregate_load_percent {
+            // 再次合并已合并的统计信息时，使用它们的峰值和最小值而不是平均值
+            aggregate_cpu_load.add_with_range(
+                aggregate,
+                all_stats
+                    .cpu
+                    .min_aggregate_load_percent
+                    .unwrap_or(aggregate),
+                all_stats
+                    .cpu
+                    .peak_aggregate_load_percent
+                    .unwrap_or(aggregate),
+            );
+        }
+
+        if let Some(breakdown) = &all_stats.cpu.load_breakdown {
+            load_breakdowns.push(breakdown.clone());
         }
 
         // 更新每个CPU的平均温度
         if let Some(temp) = &all_stats.cpu.temp_celsius {
             average_temp = average_temp.updated_average(*temp, i + 1);
+            let peak = all_stats.cpu.peak_temp_celsius.unwrap_or(*temp);
+            peak_temp = Some(peak_temp.map_or(peak, |x| x.max(peak)));
         }
 
         // 更新内存使用情况
         if let Some(memory_stats) = &all_stats.memory {
-            average_mem_used = average_mem_used.updated_average(memory_stats.used_mb as f32, i + 1);
-            if memory_stats.total_mb > max_total_mem {
-                max_total_mem = memory_stats.total_mb;
+            average_mem_used =
+                average_mem_used.updated_average(memory_stats.used_bytes as f64, i + 1);
+            if memory_stats.total_bytes > max_total_mem {
+                max_total_mem = memory_stats.total_bytes;
+            }
+            // 与峰值一样，再次合并时使用已合并的统计信息的最小值和最大值
+            let effective_used = memory_stats.effective_used_bytes();
+            let min = memory_stats
+                .min_effective_used_bytes
+                .unwrap_or(effective_used);
+            min_effective_mem_used = Some(min_effective_mem_used.map_or(min, |x| x.min(min)));
+            let max = memory_stats
+                .max_effective_used_bytes
+                .unwrap_or(effective_used);
+            max_effective_mem_used = Some(max_effective_mem_used.map_or(max, |x| x.max(max)));
+            if let Some(available) = memory_stats.available_bytes {
+                mem_available_count += 1;
+                average_mem_available =
+                    average_mem_available.updated_average(available as f64, mem_available_count);
+            }
+            if let Some(cached) = memory_stats.cached_bytes {
+                mem_cached_count += 1;
+                average_mem_cached =
+                    average_mem_cached.updated_average(cached as f64, mem_cached_count);
+            }
+            if let Some(buffers) = memory_stats.buffers_bytes {
+                mem_buffers_count += 1;
+                average_mem_buffers =
+                    average_mem_buffers.updated_average(buffers as f64, mem_buffers_count);
+            }
+        }
+
+        // 更新收集耗时
+        average_collection_duration = average_collection_duration
+            .updated_average(all_stats.collection_duration_ms as f32, i + 1);
+
+        // 更新网络使用信息
+        if let Some(socket_stats) = &all_stats.network.sockets {
+            socket_stats_count += 1;
+            average_tcp_used = average_tcp_used
+                .updated_average(socket_stats.tcp_in_use as f32, socket_stats_count);
+            average_tcp_orphaned = average_tcp_orphaned
+                .updated_average(socket_stats.tcp_orphaned as f32, socket_stats_count);
+            average_udp_used = average_udp_used
+                .updated_average(socket_stats.udp_in_use as f32, socket_stats_count);
+            average_tcp6_used = average_tcp6_used
+                .updated_average(socket_stats.tcp6_in_use as f32, socket_stats_count);
+            average_udp6_used = average_udp6_used
+                .updated_average(socket_stats.udp6_in_use as f32, socket_stats_count);
+        }
+    }
+
+    // 熵不足时可能只持续很短的时间，使用最小值以免被合并掩盖
+    let min_entropy_available = stats_list
+        .iter()
+        .filter_map(|x| x.general.entropy_available)
+        .min();
+
+    // 登录用户突然增加可能意味着入侵或失控的自动化任务，使用最大值以免被合并掩盖
+    let max_logged_in_users = stats_list
+        .iter()
+        .filter_map(|x| x.general.logged_in_users)
+        .max();
+    let max_user_sessions = stats_list
+        .iter()
+        .filter_map(|x| x.general.user_sessions)
+        .max();
+
+    // 更新系统信息
+    let after_filtered_entries = stats_list.iter().any(|x| x.after_filtered_entries);
+    let last_stats = stats_list.pop().unwrap(); // 这不应该panic，因为如果 stats_list 为空，我们将无法到达这里
+    let general = GeneralStats {
+        uptime_seconds: last_stats.general.uptime_seconds,
+        boot_timestamp: last_stats.general.boot_timestamp,
+        load_averages: Some(LoadAverages {
+            one_minute: average_one_min_load_average,
+            five_minutes: average_five_min_load_average,
+            fifteen_minutes: average_fifteen_min_load_average,
+        }),
+        process_count: rounded_average(average_process_count, process_count_count)
+            .map(|x| x as usize),
+        thread_count: rounded_average(average_thread_count, thread_count_count).map(|x| x as usize),
+        entropy_available: min_entropy_available,
+        logged_in_users: max_logged_in_users,
+        user_sessions: max_user_sessions,
+    };
+
+    // 更新文件系统信息
+    let filesystems = last_stats.filesystems;
+
+    // 更新网络接口信息
+    let network = NetworkStats {
+        interfaces: last_stats.network.interfaces,
+        // 禁用套接字收集时，所有条目都没有套接字统计信息
+        sockets: if socket_stats_count > 0 {
+            Some(SocketStats {
+                tcp_in_use: average_tcp_used.round() as usize,
+                tcp_orphaned: average_tcp_orphaned.round() as usize,
+                udp_in_use: average_udp_used.round() as usize,
+                tcp6_in_use: average_tcp6_used.round() as usize,
+                udp6_in_use: average_udp6_used.round() as usize,
+            })
+        } else {
+            None
+        },
+    };
+
+    let collection_time = last_stats.collection_time;
+
+    AllStats {
+        general,
+        cpu: CpuStats {
+            per_logical_cpu_load_percent: Some(average_per_logical_cpu_loads),
+            aggregate_load_percent: Some(aggregate_cpu_load.average.unwrap_or(0.0)),
+            temp_celsius: Some(average_temp),
+            peak_aggregate_load_percent: aggregate_cpu_load.max,
+            peak_temp_celsius: peak_temp,
+            min_aggregate_load_percent: aggregate_cpu_load.min,
+            load_breakdown: average_load_breakdown(&load_breakdowns),
+        },
+        memory: Some(MemoryStats {
+            used_bytes: average_mem_used.round() as u64,
+            total_bytes: max_total_mem,
+            available_bytes: rounded_average(average_mem_available, mem_available_count),
+            cached_bytes: rounded_average(average_mem_cached, mem_cached_count),
+            buffers_bytes: rounded_average(average_mem_buffers, mem_buffers_count),
+            min_effective_used_bytes: min_effective_mem_used,
+            max_effective_used_bytes: max_effective_mem_used,
+        }),
+        filesystems,
+        network,
+        collection_time,
+        collection_duration_ms: average_collection_duration.round() as u64,
+        source: last_stats.source,
+        after_filtered_entries,
+    }
+}
+
+/// 计算多个 CPU 时间细分的平均值。如果没有任何细分，则返回 `None`。只有部分细分有的值只在这些细分之间求平均。
+///
+/// # 参数
+/// * `breakdowns` - 要求平均值的细分
+fn average_load_breakdown(breakdowns: &[CpuLoadBreakdown]) -> Option<CpuLoadBreakdown> {
+    if breakdowns.is_empty() {
+        return None;
+    }
+    let average = |values: Vec<f32>| {
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f32>() / values.len() as f32)
+        }
+    };
+    let n = breakdowns.len() as f32;
+
+    Some(CpuLoadBreakdown {
+        user_percent: breakdowns.iter().map(|x| x.user_percent).sum::<f32>() / n,
+        nice_percent: breakdowns.iter().map(|x| x.nice_percent).sum::<f32>() / n,
+        system_percent: breakdowns.iter().map(|x| x.system_percent).sum::<f32>() / n,
+        interrupt_percent: breakdowns.iter().map(|x| x.interrupt_percent).sum::<f32>() / n,
+        idle_percent: breakdowns.iter().map(|x| x.idle_percent).sum::<f32>() / n,
+        iowait_percent: average(breakdowns.iter().filter_map(|x| x.iowait_percent).collect()),
+        steal_percent: average(breakdowns.iter().filter_map(|x| x.steal_percent).collect()),
+    })
+}
+
+/// 将平均值四舍五入为整数。如果平均值中没有任何值，则返回 `None`。
+///
+/// # 参数
+/// * `average` - 平均值。
+/// * `n` - 平均值中值的数量。
+fn rounded_average(average: impl Into<f64>, n: usize) -> Option<u64> {
+    if n == 0 {
+        None
+    } else {
+        Some(average.into().round() as u64)
+    }
+}
+
+/// 以追加方式持久化到一个目录时，写入失败、等待重试的统计信息。这样短暂的磁盘错误（例如磁盘暂时写满）不会在持久化的历史中留下永久的空缺。
+struct PendingWrites {
+    /// 等待写入的统计信息，从旧到新排列
+    stats: VecDeque<AllStats>,
+    /// 最多缓冲的条目数。超过时丢弃最旧的条目。
+    capacity: usize,
+}
+
+impl PendingWrites {
+    /// 创建一个空的缓冲区。
+    ///
+    /// # 参数
+    /// * `capacity` - 最多缓冲的条目数
+    fn new(capacity: usize) -> PendingWrites {
+        PendingWrites {
+            stats: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// 持久化统计信息。先按顺序重试之前写入失败的条目，使文件中的条目保持时间顺序。
+    /// 写入失败时，剩余的条目留在缓冲区中，在下一次持久化时重试；缓冲区已满时丢弃最旧的条目。
+    /// 返回提供的统计信息是否已经写入。
+    ///
+    /// # 参数
+    /// * `stats` - 要持久化的统计信息
+    /// * `dir` - 要保存到的目录
+    /// * `dir_size_limit_bytes` - 文件大小限制，以字节为单位
+    /// * `file_count` - 轮换的历史文件数量
+    /// * `format` - 写入文件所用的格式
+    /// * `restrict_permissions` - 是否只允许当前用户访问创建的目录和文件
+    fn persist(
+        &mut self,
+        stats: AllStats,
+        dir: &Path,
+        dir_size_limit_bytes: u64,
+        file_count: NonZeroUsize,
+        format: PersistenceFormat,
+        restrict_permissions: bool,
+    ) -> bool {
+        let retried_count = self.stats.len();
+        self.stats.push_back(stats);
+        let mut error = None;
+        while let Some(next) = self.stats.front() {
+            match persist_stats(
+                next,
+                dir,
+                dir_size_limit_bytes,
+                file_count,
+                format,
+                restrict_permissions,
+            ) {
+                Ok(()) => {
+                    self.stats.pop_front();
+                }
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        let dropped_count = self.stats.len().saturating_sub(self.capacity);
+        self.stats.drain(..dropped_count);
+        match error {
+            Some(e) => {
+                //TODO use actual logging once https://github.com/SergioBenitez/Rocket/issues/21 is done
+                println!(
+                    "将统计信息持久保存到 {:?}: {}（{} 个条目等待重试，丢弃了 {} 个条目）",
+                    dir,
+                    e,
+                    self.stats.len(),
+                    dropped_count
+                );
+                false
+            }
+            None => {
+                if retried_count > 0 {
+                    println!(
+                        "已将之前写入失败的 {} 个条目持久保存到 {:?}",
+                        retried_count, dir
+                    );
+                }
+                true
+            }
+        }
+    }
+}
+
+/// 持久化统计数据
+///
+/// # 参数
+/// * `stats` - 统计信息。
+/// * `dir` - 要保存到的目录。
+/// * `dir_size_limit_bytes` - 文件大小限制，以比特为单位。
+/// * `file_count` - 轮换的历史文件数量。
+/// * `format` - 写入文件所用的格式。
+/// * `restrict_permissions` - 是否只允许当前用户访问创建的目录和文件。
+fn persist_stats(
+    stats: &AllStats,
+    dir: &Path,
+    dir_size_limit_bytes: u64,
+    file_count: NonZeroUsize,
+    format: PersistenceFormat,
+    restrict_permissions: bool,
+) -> io::Result<()> {
+    if !dir.exists() {
+        create_history_dir(dir, restrict_permissions)?;
+    }
+
+    let current_stats_path = dir.join(format.current_file_name());
+
+    let current_len = if current_stats_path.exists() {
+        current_stats_path.metadata()?.len()
+    } else {
+        0
+    };
+    // 将大小限制平均分配给轮换的每个文件。以其他格式版本写入的二进制文件不能追加当前格式的条目，因此也要先轮换。
+    if current_len >= (dir_size_limit_bytes / file_count.get() as u64)
+        || (format == PersistenceFormat::Bincode
+            && current_len > 0
+            && read_binary_version(&mut File::open(&current_stats_path)?)?
+                != BINARY_FILE_FORMAT_VERSION)
+    {
+        rotate_history_files(dir, file_count, format)?;
+    }
+
+    let mut current_stats_file = history_file_options(restrict_permissions)
+        .append(true)
+        .open(&current_stats_path)?;
+    let original_len = current_stats_file.metadata()?.len();
+    if let Err(e) = append_stats(
+        stats,
+        &mut current_stats_file,
+        &current_stats_path,
+        original_len,
+        format,
+        restrict_permissions,
+    ) {
+        // 撤销写入了一部分的条目，这样重试时不会重复写入条目，也不会在文件中留下无法读取的数据
+        if let Err(truncate_error) = current_stats_file.set_len(original_len) {
+            println!(
+                "撤销写入 {:?} 的条目时出错: {}",
+                current_stats_path, truncate_error
+            );
+        }
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// 将统计信息追加到历史文件，然后将其偏移量和收集时间追加到索引文件。索引文件写入失败时，撤销写入索引文件的内容。
+///
+/// # 参数
+/// * `stats` - 统计信息
+/// * `file` - 以追加模式打开的历史文件
+/// * `path` - 历史文件的路径
+/// * `offset` - 历史文件当前的长度
+/// * `format` - 写入文件所用的格式
+/// * `restrict_permissions` - 是否只允许当前用户访问创建的文件
+fn append_stats(
+    stats: &AllStats,
+    file: &mut File,
+    path: &Path,
+    mut offset: u64,
+    format: PersistenceFormat,
+    restrict_permissions: bool,
+) -> io::Result<()> {
+    match format {
+        PersistenceFormat::Json => writeln!(
+            file,
+            "v{} {}",
+            JSON_LINE_SCHEMA_VERSION,
+            serde_json::to_string(stats)?
+        )?,
+        PersistenceFormat::Bincode => {
+            if offset == 0 {
+                write_binary_header(file)?;
+                offset = BINARY_FILE_HEADER_LEN;
+            }
+            write_binary_record(file, stats)?;
+        }
+    }
+
+    let mut index_file = history_file_options(restrict_permissions)
+        .append(true)
+        .open(index_path(path))?;
+    let index_len = index_file.metadata()?.len();
+    if let Err(e) = writeln!(
+        index_file,
+        "{} {}",
+        offset,
+        stats.collection_time.timestamp_millis()
+    ) {
+        let _ = index_file.set_len(index_len);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// 轮换历史文件：丢弃最旧的文件，然后将其余每个文件（及其索引文件）移动到下一个位置，空出保存最新统计信息的位置。
+///
+/// # 参数
+/// * `dir` - 统计历史目录。
+/// * `file_count` - 轮换的历史文件数量。
+/// * `format` - 历史文件的格式。
+fn rotate_history_files(
+    dir: &Path,
+    file_count: NonZeroUsize,
+    format: PersistenceFormat,
+) -> io::Result<()> {
+    let path = |index| dir.join(format.ring_file_name(index));
+
+    // 除了最旧的文件，还要删除减少文件数量之前留下的更旧的文件
+    let mut index = file_count.get() - 1;
+    while path(index).exists() {
+        remove_file(path(index))?;
+        let discarded_index_path = index_path(&path(index));
+        if discarded_index_path.exists() {
+            remove_file(discarded_index_path)?;
+        }
+        index += 1;
+    }
+
+    for index in (0..file_count.get() - 1).rev() {
+        let from = path(index);
+        if !from.exists() {
+            continue;
+        }
+        let to = path(index + 1);
+        rename(&from, &to)?;
+        let from_index_path = index_path(&from);
+        let to_index_path = index_path(&to);
+        if from_index_path.exists() {
+            rename(from_index_path, to_index_path)?;
+        } else if to_index_path.exists() {
+            // 旧索引属于被替换掉的文件
+            remove_file(to_index_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// 将统计历史快照写入文件，覆盖之前的快照。
+///
+/// # 参数
+/// * `stats` - 统计历史中的所有条目，从旧到新。
+/// * `dir` - 要保存到的目录。
+/// * `format` - 写入文件所用的格式。
+/// * `restrict_permissions` - 是否只允许当前用户访问创建的目录和文件。
+fn persist_snapshot(
+    stats: &[AllStats],
+    dir: &Path,
+    format: PersistenceFormat,
+    restrict_permissions: bool,
+) -> io::Result<()> {
+    if !dir.exists() {
+        create_history_dir(dir, restrict_permissions)?;
+    }
+
+    // 先写入临时文件再重命名，避免写入途中出错时留下不完整的快照
+    let snapshot_path = dir.join(format.snapshot_file_name());
+    let temp_path = dir.join(format!("{}.tmp", format.snapshot_file_name()));
+    let temp_file = history_file_options(restrict_permissions)
+        .write(true)
+        .truncate(true)
+        .open(&temp_path)?;
+    let mut writer = BufWriter::new(temp_file);
+    match format {
+        PersistenceFormat::Json => serde_json::to_writer(
+            &mut writer,
+            &JsonSnapshot {
+                version: JSON_SNAPSHOT_SCHEMA_VERSION,
+                stats,
+            },
+        )?,
+        PersistenceFormat::Bincode => {
+            write_binary_header(&mut writer)?;
+            for entry in stats {
+                write_binary_record(&mut writer, entry)?;
+            }
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+
+    rename(temp_path, snapshot_path)
+}
+
+/// 写入 JSON 快照文件的内容
+#[derive(Serialize)]
+struct JsonSnapshot<'a> {
+    version: u32,
+    stats: &'a [AllStats],
+}
+
+/// 写入二进制文件开头的标记和格式版本
+///
+/// # 参数
+/// * `writer` - 要写入的文件
+fn write_binary_header(writer: &mut impl Write) -> io::Result<()> {
+    let mut header = BINARY_FILE_MAGIC.to_vec();
+    header.extend_from_slice(&BINARY_FILE_FORMAT_VERSION.to_le_bytes());
+    writer.write_all(&header)
+}
+
+/// 将一个条目及其长度写入二进制文件。一次写入整个条目，减少写入途中出错时留下的不完整数据。
+///
+/// # 参数
+/// * `writer` - 要写入的文件
+/// * `stats` - 要写入的条目
+fn write_binary_record(writer: &mut impl Write, stats: &AllStats) -> io::Result<()> {
+    let bytes = bincode::serialize(stats).map_err(io::Error::other)?;
+    let mut record = (bytes.len() as u64).to_le_bytes().to_vec();
+    record.extend_from_slice(&bytes);
+    writer.write_all(&record)
+}
+
+/// 读取二进制文件开头的格式版本。读取之后，文件位于第一个条目的开头。没有标记的文件（包括空文件）返回版本 0。
+///
+/// # 参数
+/// * `file` - 位于开头的文件
+fn read_binary_version(file: &mut (impl Read + Seek)) -> io::Result<u32> {
+    let mut header = [0; BINARY_FILE_HEADER_LEN as usize];
+    match file.read_exact(&mut header) {
+        Ok(()) if header[..BINARY_FILE_MAGIC.len()] == BINARY_FILE_MAGIC[..] => {
+            let mut version = [0; 4];
+            version.copy_from_slice(&header[BINARY_FILE_MAGIC.len()..]);
+            Ok(u32::from_le_bytes(version))
+        }
+        Ok(()) => {
+            file.seek(SeekFrom::Start(0))?;
+            Ok(0)
+        }
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            file.seek(SeekFrom::Start(0))?;
+            Ok(0)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 获取历史文件中第一个条目的字节偏移量，即文件开头的格式版本之后的位置
+///
+/// # 参数
+/// * `path` - 历史文件的路径
+/// * `format` - 历史文件的格式
+fn first_record_offset(path: &Path, format: PersistenceFormat) -> io::Result<u64> {
+    match format {
+        PersistenceFormat::Json => Ok(0),
+        PersistenceFormat::Bincode => match read_binary_version(&mut File::open(path)?)? {
+            0 => Ok(0),
+            _ => Ok(BINARY_FILE_HEADER_LEN),
+        },
+    }
+}
+
+/// 版本 2 之前的二进制文件中的条目。bincode 按顺序解码所有字段，没有之后加入的字段的条目无法直接解码为 `AllStats`。
+#[derive(Deserialize)]
+struct AllStatsV1 {
+    general: GeneralStats,
+    cpu: CpuStats,
+    memory: Option<MemoryStats>,
+    filesystems: Option<Vec<MountStats>>,
+    network: NetworkStats,
+    collection_time: DateTime<Local>,
+    collection_duration_ms: u64,
+    source: Option<SampleSource>,
+}
+
+impl From<AllStatsV1> for AllStats {
+    fn from(stats: AllStatsV1) -> AllStats {
+        AllStats {
+            general: stats.general,
+            cpu: stats.cpu,
+            memory: stats.memory,
+            filesystems: stats.filesystems,
+            network: stats.network,
+            collection_time: stats.collection_time,
+            collection_duration_ms: stats.collection_duration_ms,
+            source: stats.source,
+            after_filtered_entries: false,
+        }
+    }
+}
+
+/// 读取二进制文件中的条目直到文件末尾，添加到提供的统计信息列表。跳过无法解码的条目并打印日志，而不是让整个加载失败。
+///
+/// # 参数
+/// * `reader` - 位于某个条目开头的文件
+/// * `version` - 文件的格式版本
+/// * `path` - 文件的路径，用于日志
+/// * `stats` - 要添加到的统计信息列表
+fn read_binary_records(
+    reader: &mut impl Read,
+    version: u32,
+    path: &Path,
+    stats: &mut Vec<AllStats>,
+) -> io::Result<()> {
+    match version {
+        // 没有长度的旧格式：遇到无法解码的条目后无法找到下一个条目的开头，因此跳过文件的其余部分
+        0 => loop {
+            match bincode::deserialize_from::<_, AllStatsV1>(&mut *reader) {
+                Ok(x) => stats.push(x.into()),
+                Err(e) => {
+                    match *e {
+                        // 到达文件末尾（或最后一个条目未写完）
+                        bincode::ErrorKind::Io(ref io_error)
+                            if io_error.kind() == io::ErrorKind::UnexpectedEof => {}
+                        _ => println!("跳过了 {:?} 中无法读取的条目及其之后的内容: {}", path, e),
+                    }
+                    break;
+                }
+            }
+        },
+        1..=BINARY_FILE_FORMAT_VERSION => {
+            let mut skipped_count = 0;
+            let mut first_error = None;
+            loop {
+                let mut len = [0; 8];
+                match reader.read_exact(&mut len) {
+                    Ok(()) => (),
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(e),
+                }
+                let len = u64::from_le_bytes(len);
+                let mut bytes = Vec::new();
+                reader.take(len).read_to_end(&mut bytes)?;
+                if (bytes.len() as u64) < len {
+                    // 最后一个条目未写完
+                    break;
+                }
+                let decoded = if version == 1 {
+                    bincode::deserialize::<AllStatsV1>(&bytes).map(Into::into)
+                } else {
+                    bincode::deserialize(&bytes)
+                };
+                match decoded {
+                    Ok(x) => stats.push(x),
+                    Err(e) => {
+                        skipped_count += 1;
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            if let Some(first_error) = first_error {
+                println!(
+                    "跳过了 {:?} 中 {} 个无法读取的条目（第一个错误: {}）",
+                    path, skipped_count, first_error
+                );
             }
         }
-
-        // 更新网络使用信息
-        if let Some(socket_stats) = &all_stats.network.sockets {
-            average_tcp_used =
-                average_tcp_used.updated_average(socket_stats.tcp_in_use as f32, i + 1);
-            average_tcp_orphaned =
-                average_tcp_orphaned.updated_average(socket_stats.tcp_orphaned as f32, i + 1);
-            average_udp_used =
-                average_udp_used.updated_average(socket_stats.udp_in_use as f32, i + 1);
-            average_tcp6_used =
-                average_tcp6_used.updated_average(socket_stats.tcp6_in_use as f32, i + 1);
-            average_udp6_used =
-                average_udp6_used.updated_average(socket_stats.udp6_in_use as f32, i + 1);
-        }
+        _ => println!(
+            "跳过 {:?}: 不支持的格式版本 {}（支持的最高版本为 {}）",
+            path, version, BINARY_FILE_FORMAT_VERSION
+        ),
     }
 
-    // 更新系统信息
-    let last_stats = stats_list.pop().unwrap(); // 这不应该panic，因为如果 stats_list 为空，我们将无法到达这里
-    let general = GeneralStats {
-        uptime_seconds: last_stats.general.uptime_seconds,
-        boot_timestamp: last_stats.general.boot_timestamp,
-        load_averages: Some(LoadAverages {
-            one_minute: average_one_min_load_average,
-            five_minutes: average_five_min_load_average,
-            fifteen_minutes: average_fifteen_min_load_average,
-        }),
-    };
+    Ok(())
+}
 
-    // 更新文件系统信息
-    let filesystems = last_stats.filesystems;
+/// 创建保存统计历史的目录，包括所有不存在的上级目录。
+///
+/// # 参数
+/// * `dir` - 要创建的目录。
+/// * `restrict_permissions` - 是否只允许当前用户访问创建的目录。
+#[cfg(unix)]
+pub(crate) fn create_history_dir(dir: &Path, restrict_permissions: bool) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
 
-    // 更新网络接口信息
-    let network = NetworkStats {
-        interfaces: last_stats.network.interfaces,
-        sockets: Some(SocketStats {
-            tcp_in_use: average_tcp_used.round() as usize,
-            tcp_orphaned: average_tcp_orphaned.round() as usize,
-            udp_in_use: average_udp_used.round() as usize,
-            tcp6_in_use: average_tcp6_used.round() as usize,
-            udp6_in_use: average_udp6_used.round() as usize,
-        }),
-    };
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    if restrict_permissions {
+        builder.mode(HISTORY_DIR_MODE);
+    }
+    builder.create(dir)
+}
 
-    let collection_time = last_stats.collection_time;
+/// 创建保存统计历史的目录，包括所有不存在的上级目录。此平台不支持限制权限。
+#[cfg(not(unix))]
+pub(crate) fn create_history_dir(dir: &Path, _restrict_permissions: bool) -> io::Result<()> {
+    DirBuilder::new().recursive(true).create(dir)
+}
 
-    AllStats {
-        general,
-        cpu: CpuStats {
-            per_logical_cpu_load_percent: Some(average_per_logical_cpu_loads),
-            aggregate_load_percent: Some(average_aggregate_cpu_load),
-            temp_celsius: Some(average_temp),
-        },
-        memory: Some(MemoryStats {
-            used_mb: average_mem_used.round() as u64,
-            total_mb: max_total_mem,
-        }),
-        filesystems,
-        network,
-        collection_time,
+/// 获取用于打开统计历史文件的选项，文件不存在时会创建它。
+///
+/// # 参数
+/// * `restrict_permissions` - 是否只允许当前用户访问创建的文件。已存在的文件的权限不变。
+#[cfg(unix)]
+pub(crate) fn history_file_options(restrict_permissions: bool) -> OpenOptions {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut options = OpenOptions::new();
+    options.create(true);
+    if restrict_permissions {
+        options.mode(HISTORY_FILE_MODE);
     }
+    options
 }
 
-/// 持久化统计数据
+/// 获取用于打开统计历史文件的选项，文件不存在时会创建它。此平台不支持限制权限。
+#[cfg(not(unix))]
+pub(crate) fn history_file_options(_restrict_permissions: bool) -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.create(true);
+    options
+}
+
+/// 获取目录中所有格式的统计历史文件及其索引文件占用的总字节数。不存在的文件不计算在内。
 ///
 /// # 参数
-/// * `stats` - 统计信息。
-/// * `dir` - 要保存到的目录。
-/// * `dir_size_limit_bytes` - 文件大小限制，以比特为单位。
-fn persist_stats(stats: &AllStats, dir: &Path, dir_size_limit_bytes: u64) -> io::Result<()> {
-    if !dir.exists() {
-        create_dir_all(dir)?;
+/// * `dir` - 统计历史目录
+pub fn history_files_size(dir: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for format in PersistenceFormat::ALL {
+        let mut paths = format.ring_file_paths(dir);
+        paths.push(dir.join(format.snapshot_file_name()));
+        for path in paths {
+            for path in [index_path(&path), path] {
+                if path.exists() {
+                    total += path.metadata()?.len();
+                }
+            }
+        }
     }
+    Ok(total)
+}
 
-    let current_stats_path = dir.join(CURRENT_HISTORY_FILE_NAME);
-    let old_stats_path = dir.join(OLD_HISTORY_FILE_NAME);
+/// 获取历史文件对应的索引文件路径
+fn index_path(data_path: &Path) -> PathBuf {
+    let mut file_name = data_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(INDEX_FILE_EXTENSION);
+    data_path.with_file_name(file_name)
+}
 
-    // 将大小限制除以 2，因为这会在 2 个文件之间交换
-    if current_stats_path.exists()
-        && current_stats_path.metadata()?.len() >= (dir_size_limit_bytes / 2)
-    {
-        rename(&current_stats_path, &old_stats_path)?;
+/// 使用索引文件查找历史文件中第一个收集时间不早于 `from` 的条目的字节偏移量。
+///
+/// 如果索引文件不存在、无法解析或已过期（引用超出历史文件末尾的偏移量），则返回 `None`。
+///
+/// # 参数
+/// * `data_path` - 历史文件的路径
+/// * `format` - 历史文件的格式
+/// * `from` - 要查找的最早收集时间
+fn indexed_start_offset(
+    data_path: &Path,
+    format: PersistenceFormat,
+    from: DateTime<Local>,
+) -> Option<u64> {
+    let data_len = data_path.metadata().ok()?.len();
+    let expected_first_offset = first_record_offset(data_path, format).ok()?;
+    let index_file = File::open(index_path(data_path)).ok()?;
+
+    let from_millis = from.timestamp_millis();
+    let mut start_offset = None;
+    let mut first_offset = None;
+    let mut last_offset = None;
+    for line in BufReader::new(index_file).lines() {
+        let line = line.ok()?;
+        let (offset, millis) = line.trim().split_once(' ')?;
+        let offset: u64 = offset.parse().ok()?;
+        let millis: i64 = millis.parse().ok()?;
+        if start_offset.is_none() && millis >= from_millis {
+            start_offset = Some(offset);
+        }
+        first_offset.get_or_insert(offset);
+        last_offset = Some(offset);
     }
 
-    let mut current_stats_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(current_stats_path)?;
-    writeln!(current_stats_file, "{}", serde_json::to_string(stats)?)?;
+    match (first_offset, last_offset) {
+        // 索引没有从文件的第一个条目开始，或者偏移量超出了文件末尾，说明文件在索引之外被修改过
+        (Some(first_offset), Some(last_offset))
+            if first_offset != expected_first_offset || last_offset >= data_len =>
+        {
+            None
+        }
+        (Some(_), Some(_)) => Some(start_offset.unwrap_or(data_len)),
+        // 空索引只对空文件有效
+        _ if data_len == 0 => Some(0),
+        _ => None,
+    }
+}
 
-    Ok(())
+/// 一组值的最小值、最大值、平均值和最近的值。逐个加入值，不需要保留所有的值。
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Aggregate {
+    /// 最小值。没有加入任何值时为 `None`。
+    pub min: Option<f32>,
+    /// 最大值。没有加入任何值时为 `None`。
+    pub max: Option<f32>,
+    /// 平均值。没有加入任何值时为 `None`。
+    #[serde(rename = "avg")]
+    pub average: Option<f32>,
+    /// 最近加入的值。没有加入任何值时为 `None`。
+    pub current: Option<f32>,
+    /// 已加入的值的数量
+    #[serde(skip)]
+    count: usize,
+}
+
+impl Aggregate {
+    /// 加入一个值。
+    ///
+    /// # 参数
+    /// * `value` - 要加入的值
+    pub fn add(&mut self, value: f32) {
+        self.add_with_range(value, value, value);
+    }
+
+    /// 加入一个值，以及它所代表的值的范围。用于加入已合并的统计信息，这样结果的最小值和最大值不会因为合并而被平均掉。
+    ///
+    /// # 参数
+    /// * `value` - 要加入的值，用于计算平均值和最近的值
+    /// * `min` - 此值所代表的最小值
+    /// * `max` - 此值所代表的最大值
+    pub fn add_with_range(&mut self, value: f32, min: f32, max: f32) {
+        self.count += 1;
+        self.average = Some(
+            self.average
+                .unwrap_or(0.0)
+                .updated_average(value, self.count),
+        );
+        self.min = Some(self.min.map_or(min, |x| x.min(min)));
+        self.max = Some(self.max.map_or(max, |x| x.max(max)));
+        self.current = Some(value);
+    }
 }
 
 trait MovingAverage<T> {
@@ -269,6 +1761,12 @@ impl MovingAverage<f32> for f32 {
     }
 }
 
+impl MovingAverage<f64> for f64 {
+    fn updated_average(self, new_value: f64, n: usize) -> f64 {
+        self + ((new_value - self) / n as f64)
+    }
+}
+
 trait MovingAverageCollection<T> {
     /// 使用一组新值来更新平均值。
     ///
@@ -313,26 +1811,99 @@ impl StatsHistory {
         }
     }
 
-    /// 从提供的目录加载统计历史记录。
+    /// 从提供的目录加载统计历史记录。会读取以任意支持格式写入的文件，以及轮换中的所有历史文件（从旧到新）。
     ///
     /// # 参数
     /// * `dir` - 在其中查找持久统计历史文件的目录。
     pub fn load_from(dir: &Path) -> io::Result<StatsHistory> {
+        let stats = read_with_retry(dir, |stats| {
+            for format in PersistenceFormat::ALL {
+                for path in format.ring_file_paths(dir) {
+                    add_stats_from_file(path, format, stats)?;
+                }
+                add_stats_from_snapshot(dir.join(format.snapshot_file_name()), format, stats)?;
+            }
+            Ok(())
+        })?;
+        warn_if_mixed_sources(dir, &stats);
+
+        Ok(StatsHistory::from_stats(stats))
+    }
+
+    /// 从目录加载收集时间在 `from` 和 `to` 之间（含）的持久化统计信息。
+    ///
+    /// 使用每个历史文件旁边的索引文件跳过早于 `from` 的条目。如果索引文件不存在或已过期，则扫描整个文件。
+    ///
+    /// # 参数
+    /// * `dir` - 加载统计信息的目录
+    /// * `from` - 最早的收集时间
+    /// * `to` - 最晚的收集时间
+    pub fn load_range(
+        dir: &Path,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> io::Result<StatsHistory> {
+        let mut stats = read_with_retry(dir, |stats| {
+            for format in PersistenceFormat::ALL {
+                for path in format.ring_file_paths(dir) {
+                    let start_offset = indexed_start_offset(&path, format, from).unwrap_or(0);
+                    add_stats_from_file_at(path, format, start_offset, stats)?;
+                }
+                // 快照的大小是有限的，因此直接读取整个文件
+                add_stats_from_snapshot(dir.join(format.snapshot_file_name()), format, stats)?;
+            }
+            Ok(())
+        })?;
+
+        stats.retain(|x| x.collection_time >= from && x.collection_time <= to);
+
+        Ok(StatsHistory::from_stats(stats))
+    }
+
+    /// 将长期统计历史与此统计历史合并。只使用长期历史中早于此历史最早条目的条目，因此近期使用高分辨率的统计信息，更早的时间使用长期统计信息。
+    ///
+    /// # 参数
+    /// * `long_term` - 长期统计历史
+    pub fn merged_with_long_term(&self, long_term: &StatsHistory) -> StatsHistory {
         let mut stats = Vec::new();
+        let earliest = match self.get_most_recent_stats() {
+            Some(_) => self.into_iter().next().map(|x| x.collection_time),
+            None => None,
+        };
 
-        let old_stats_path = dir.join(OLD_HISTORY_FILE_NAME);
-        let current_stats_path = dir.join(CURRENT_HISTORY_FILE_NAME);
+        if long_term.get_most_recent_stats().is_some() {
+            stats.extend(
+                long_term
+                    .into_iter()
+                    .filter(|x| match earliest {
+                        Some(earliest) => x.collection_time < earliest,
+                        None => true,
+                    })
+                    .cloned(),
+            );
+        }
+        if earliest.is_some() {
+            stats.extend(self.into_iter().cloned());
+        }
+
+        StatsHistory::from_stats(stats)
+    }
 
-        add_stats_from_file(old_stats_path, &mut stats)?;
-        add_stats_from_file(current_stats_path, &mut stats)?;
+    /// 从已加载的统计信息创建一个刚好能容纳它们的统计历史。
+    ///
+    /// # 参数
+    /// * `stats` - 统计信息，顺序任意
+    pub(crate) fn from_stats(mut stats: Vec<AllStats>) -> StatsHistory {
+        // 如果切换过格式，不同格式的文件中的条目可能交错
+        stats.sort_by_key(|x| x.collection_time);
 
         match NonZeroUsize::new(stats.len()) {
-            Some(size) => Ok(StatsHistory {
+            Some(size) => StatsHistory {
                 max_size: size,
                 stats,
                 most_recent_index: size.get() - 1,
-            }),
-            None => Ok(StatsHistory::new(NonZeroUsize::new(1).unwrap())),
+            },
+            None => StatsHistory::new(NonZeroUsize::new(1).unwrap()),
         }
     }
 
@@ -373,22 +1944,356 @@ impl StatsHistory {
         }
     }
 
+    /// 获取收集时间最接近提供的时间的统计信息。如果历史记录为空，则返回“None”。
+    ///
+    /// # 参数
+    /// * `time` - 要查找的时间
+    pub fn get_stats_nearest_to(&self, time: DateTime<Local>) -> Option<&AllStats> {
+        self.get_most_recent_stats()?;
+        self.into_iter()
+            .min_by_key(|x| (x.collection_time - time).num_milliseconds().abs())
+    }
+
+    /// 获取已经开始丢弃旧条目时，仍保留的最旧条目的收集时间。历史记录未满时返回 `None`，因为还没有丢弃任何条目。
+    pub fn oldest_retained_time(&self) -> Option<DateTime<Local>> {
+        if self.stats.len() < self.max_size.get() {
+            return None;
+        }
+        self.into_iter().next().map(|x| x.collection_time)
+    }
+
+    /// 获取将此统计历史按 `page_size` 个条目分页后的页数
+    ///
+    /// # 参数
+    /// * `page_size` - 每页的条目数
+    pub fn page_count(&self, page_size: NonZeroUsize) -> usize {
+        self.stats.len().div_ceil(page_size.get())
+    }
+
+    /// 获取将此统计历史按 `page_size` 个条目分页后的一页。页中的条目始终按收集时间排列。如果页码超出范围，则返回空的统计历史。
+    ///
+    /// # 参数
+    /// * `page` - 页码，从 1 开始
+    /// * `page_size` - 每页的条目数
+    /// * `order` - 各页的顺序
+    pub fn page(
+        &self,
+        page: usize,
+        page_size: NonZeroUsize,
+        order: HistoryPageOrder,
+    ) -> StatsHistory {
+        if self.get_most_recent_stats().is_none() || page == 0 {
+            return StatsHistory::from_stats(Vec::new());
+        }
+
+        let stats: Vec<&AllStats> = self.into_iter().collect();
+        let skipped = (page - 1).saturating_mul(page_size.get());
+        let (start, end) = match order {
+            HistoryPageOrder::Oldest => (
+                skipped.min(stats.len()),
+                skipped.saturating_add(page_size.get()).min(stats.len()),
+            ),
+            HistoryPageOrder::Newest => (
+                stats
+                    .len()
+                    .saturating_sub(skipped.saturating_add(page_size.get())),
+                stats.len().saturating_sub(skipped),
+            ),
+        };
+
+        StatsHistory::from_stats(stats[start..end].iter().map(|x| (*x).clone()).collect())
+    }
+
     fn get_next_index(&self) -> usize {
         index_after(self.most_recent_index, self.max_size)
     }
 }
 
+/// 多个统计历史在同一时间点的统计信息
+pub struct AlignedStats<'a> {
+    /// 此时间点中最早的收集时间
+    pub time: DateTime<Local>,
+    /// 每个统计历史在此时间点的统计信息，顺序与提供的统计历史相同。没有条目的统计历史为 `None`。
+    pub stats: Vec<Option<&'a AllStats>>,
+}
+
+/// 按收集时间对齐多个统计历史，例如多个远程代理的统计历史。
+///
+/// 从最早的条目开始，收集时间与时间点最早的收集时间相差不超过 `interval` 的一半的条目视为同一时间点的条目，
+/// 这样收集时间没有同步的统计历史（例如一个在 :59.9 收集，另一个在 :00.1 收集）也会对齐到同一时间点。
+/// 如果一个统计历史在同一时间点有多个条目，则使用最新的条目。
+///
+/// # 参数
+/// * `histories` - 要对齐的统计历史
+/// * `interval` - 每个统计历史中相邻两个条目之间的预期间隔
+pub fn align_histories<'a>(
+    histories: &[&'a StatsHistory],
+    interval: Duration,
+) -> Vec<AlignedStats<'a>> {
+    let tolerance =
+        chrono::Duration::from_std(interval / 2).unwrap_or_else(|_| chrono::Duration::max_value());
+    let mut entries: Vec<(usize, &'a AllStats)> = histories
+        .iter()
+        .enumerate()
+        .filter(|(_, history)| history.get_most_recent_stats().is_some())
+        .flat_map(|(i, history)| history.into_iter().map(move |stats| (i, stats)))
+        .collect();
+    // 排序是稳定的，因此收集时间相同的条目保持每个统计历史中的顺序
+    entries.sort_by_key(|(_, stats)| stats.collection_time);
+
+    let mut aligned: Vec<AlignedStats<'a>> = Vec::new();
+    for (i, stats) in entries {
+        match aligned.last_mut() {
+            Some(point) if stats.collection_time - point.time <= tolerance => {
+                point.stats[i] = Some(stats);
+            }
+            _ => {
+                let mut point = AlignedStats {
+                    time: stats.collection_time,
+                    stats: vec![None; histories.len()],
+                };
+                point.stats[i] = Some(stats);
+                aligned.push(point);
+            }
+        }
+    }
+
+    aligned
+}
+
 /// 从提供的路径（如果存在）的文件中添加统计信息到提供的统计信息列表
-fn add_stats_from_file(path: PathBuf, stats: &mut Vec<AllStats>) -> io::Result<()> {
-    if path.exists() {
-        let file = File::open(path)?;
-        for line in BufReader::new(file).lines() {
-            let line = line?;
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
+fn add_stats_from_file(
+    path: PathBuf,
+    format: PersistenceFormat,
+    stats: &mut Vec<AllStats>,
+) -> io::Result<()> {
+    add_stats_from_file_at(path, format, 0, stats)
+}
+
+/// 读取目录中持久化的统计信息。写入方可能在读取旧文件和当前文件之间轮换历史文件，使条目从当前文件移到旧文件中而被漏读，
+/// 因此如果读取期间旧文件发生了变化，或者读取出错（例如暂时的错误），则重试一次。
+///
+/// # 参数
+/// * `dir` - 统计历史目录
+/// * `read` - 将目录中的统计信息添加到提供的列表中
+fn read_with_retry(
+    dir: &Path,
+    read: impl Fn(&mut Vec<AllStats>) -> io::Result<()>,
+) -> io::Result<Vec<AllStats>> {
+    let attempt = || -> io::Result<(Vec<AllStats>, bool)> {
+        let before = old_file_modified_times(dir);
+        let mut stats = Vec::new();
+        read(&mut stats)?;
+        Ok((stats, old_file_modified_times(dir) != before))
+    };
+
+    match attempt() {
+        Ok((stats, false)) => Ok(stats),
+        Ok((_, true)) => attempt().map(|(stats, _)| stats),
+        Err(e) => {
+            println!("读取 {:?} 中持久化的统计信息时出错，重试一次: {}", dir, e);
+            attempt().map(|(stats, _)| stats)
+        }
+    }
+}
+
+/// 如果加载的统计信息来自不同的平台或使用了不同的单位，则打印警告。这通常意味着历史目录是从其他机器复制来的，
+/// 混合的条目可能无法直接比较。只有版本不同（例如升级后）不会警告；没有来源信息的旧条目不参与比较。
+///
+/// # 参数
+/// * `dir` - 加载统计信息的目录
+/// * `stats` - 加载的统计信息
+fn warn_if_mixed_sources(dir: &Path, stats: &[AllStats]) {
+    let tags: BTreeSet<(&str, Option<&str>)> = stats
+        .iter()
+        .filter_map(|x| x.source.as_ref())
+        .map(|x| (x.platform.as_str(), x.units.as_deref()))
+        .collect();
+    if tags.len() > 1 {
+        let tags = tags
+            .iter()
+            .map(|(platform, units)| format!("{} ({})", platform, units.unwrap_or("未知单位")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "警告: {:?} 中的统计历史混合了不同平台或单位收集的条目: {}",
+            dir, tags
+        );
+    }
+}
+
+/// 获取每种格式的旧历史文件的修改时间，用于检测文件是否被轮换。文件不存在时为 `None`。
+///
+/// # 参数
+/// * `dir` - 统计历史目录
+fn old_file_modified_times(dir: &Path) -> Vec<Option<SystemTime>> {
+    PersistenceFormat::ALL
+        .iter()
+        .map(|format| {
+            dir.join(format.old_file_name())
+                .metadata()
+                .and_then(|x| x.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// 打开要读取的历史文件。文件不存在时（包括检查之后被写入方重命名的情况）返回 `None`，视为空文件。
+///
+/// # 参数
+/// * `path` - 文件的路径
+fn open_if_exists(path: &Path) -> io::Result<Option<File>> {
+    match File::open(path) {
+        Ok(x) => Ok(Some(x)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// 从提供的路径（如果存在）的快照文件中添加统计信息到提供的统计信息列表。跳过无法解码的条目并打印日志。
+fn add_stats_from_snapshot(
+    path: PathBuf,
+    format: PersistenceFormat,
+    stats: &mut Vec<AllStats>,
+) -> io::Result<()> {
+    if let Some(mut file) = open_if_exists(&path)? {
+        match format {
+            PersistenceFormat::Json => {
+                let snapshot: serde_json::Value =
+                    match serde_json::from_reader(BufReader::new(file)) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            println!("跳过无法读取的快照 {:?}: {}", path, e);
+                            return Ok(());
+                        }
+                    };
+                let (version, entries) = match snapshot {
+                    serde_json::Value::Array(entries) => (0, entries),
+                    serde_json::Value::Object(mut snapshot) => (
+                        snapshot
+                            .get("version")
+                            .and_then(|x| x.as_u64())
+                            .unwrap_or(0),
+                        match snapshot.remove("stats") {
+                            Some(serde_json::Value::Array(entries)) => entries,
+                            _ => Vec::new(),
+                        },
+                    ),
+                    _ => (0, Vec::new()),
+                };
+                if version > JSON_SNAPSHOT_SCHEMA_VERSION as u64 {
+                    println!(
+                        "跳过 {:?}: 不支持的格式版本 {}（支持的最高版本为 {}）",
+                        path, version, JSON_SNAPSHOT_SCHEMA_VERSION
+                    );
+                    return Ok(());
+                }
+
+                let mut skipped_count = 0;
+                let mut first_error = None;
+                for entry in entries {
+                    match serde_json::from_value(entry) {
+                        Ok(x) => stats.push(x),
+                        Err(e) => {
+                            skipped_count += 1;
+                            first_error.get_or_insert(e);
+                        }
+                    }
+                }
+                if let Some(first_error) = first_error {
+                    println!(
+                        "跳过了 {:?} 中 {} 个无法读取的条目（第一个错误: {}）",
+                        path, skipped_count, first_error
+                    );
+                }
+            }
+            PersistenceFormat::Bincode => match read_binary_version(&mut file)? {
+                // 旧格式的快照是整个条目数组
+                0 => match bincode::deserialize_from::<_, Vec<AllStatsV1>>(BufReader::new(file)) {
+                    Ok(snapshot) => stats.extend(snapshot.into_iter().map(Into::into)),
+                    Err(e) => println!("跳过无法读取的快照 {:?}: {}", path, e),
+                },
+                version => read_binary_records(&mut BufReader::new(file), version, &path, stats)?,
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// 解析 JSON 历史文件中的一行条目。每行以 `v<版本> ` 开头，后面是条目的 JSON。没有版本前缀的行是加入版本之前写入的，
+/// 视为版本 0。如果版本不受支持或无法解析，则返回错误消息。
+///
+/// # 参数
+/// * `line` - 去掉首尾空白的一行
+fn parse_json_line(line: &str) -> Result<AllStats, String> {
+    let (version, json) = match line.strip_prefix('v') {
+        Some(rest) => {
+            let (version, json) = rest
+                .split_once(' ')
+                .ok_or_else(|| "缺少版本之后的内容".to_string())?;
+            let version: u32 = version
+                .parse()
+                .map_err(|e| format!("无效的版本 '{}': {}", version, e))?;
+            (version, json)
+        }
+        None => (0, line),
+    };
+
+    match version {
+        // 版本 0 与版本 1 的格式相同，之后新增的字段都有默认值
+        0 | JSON_LINE_SCHEMA_VERSION => serde_json::from_str(json).map_err(|e| e.to_string()),
+        _ => Err(format!(
+            "不支持的格式版本 {}（支持的最高版本为 {}）",
+            version, JSON_LINE_SCHEMA_VERSION
+        )),
+    }
+}
+
+/// 从提供的路径（如果存在）的文件中指定的字节偏移量开始添加统计信息到提供的统计信息列表
+fn add_stats_from_file_at(
+    path: PathBuf,
+    format: PersistenceFormat,
+    start_offset: u64,
+    stats: &mut Vec<AllStats>,
+) -> io::Result<()> {
+    if let Some(mut file) = open_if_exists(&path)? {
+        file.seek(SeekFrom::Start(start_offset))?;
+        match format {
+            PersistenceFormat::Json => {
+                let mut skipped_count = 0;
+                let mut first_error = None;
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    match parse_json_line(trimmed) {
+                        Ok(x) => stats.push(x),
+                        Err(e) => {
+                            skipped_count += 1;
+                            first_error.get_or_insert(e);
+                        }
+                    }
+                }
+                if let Some(first_error) = first_error {
+                    println!(
+                        "跳过了 {:?} 中 {} 个无法读取的条目（第一个错误: {}）",
+                        path, skipped_count, first_error
+                    );
+                }
+            }
+            PersistenceFormat::Bincode => {
+                file.seek(SeekFrom::Start(0))?;
+                let version = read_binary_version(&mut file)?;
+                if version != 0 {
+                    file.seek(SeekFrom::Start(start_offset.max(BINARY_FILE_HEADER_LEN)))?;
+                } else {
+                    file.seek(SeekFrom::Start(start_offset))?;
+                }
+                read_binary_records(&mut BufReader::new(file), version, &path, stats)?;
             }
-            stats.push(serde_json::from_str(trimmed)?);
         }
     }
 
@@ -444,3 +2349,386 @@ impl<'a> Iterator for StatsHistoryIterator<'a> {
         Some(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use systemstat::Platform;
+
+    /// 创建一个空的临时目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "system-stats-dashboard-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 收集用于测试的统计信息
+    fn test_stats() -> AllStats {
+        let collectors = CollectorConfig {
+            disabled_collectors: vec![
+                Collector::Sockets,
+                Collector::Temperature,
+                Collector::Mounts,
+            ],
+            mount_collection_timeout: Duration::from_secs(1),
+            log_unsupported_once: true,
+            concurrent_collection: false,
+        };
+        AllStats::from(
+            &System::new(),
+            Duration::ZERO,
+            &collectors,
+            &MountCollection::default(),
+        )
+    }
+
+    /// 以版本 2 之前的二进制格式编码统计信息，即没有 `after_filtered_entries`
+    fn serialize_v1(stats: &AllStats) -> Vec<u8> {
+        bincode::serialize(&(
+            &stats.general,
+            &stats.cpu,
+            &stats.memory,
+            &stats.filesystems,
+            &stats.network,
+            &stats.collection_time,
+            stats.collection_duration_ms,
+            &stats.source,
+        ))
+        .unwrap()
+    }
+
+    /// 创建收集时间为 `start` 之后提供的毫秒数的统计历史
+    fn history_at(start: DateTime<Local>, offsets_ms: &[i64]) -> StatsHistory {
+        let stats = test_stats();
+        StatsHistory::from_stats(
+            offsets_ms
+                .iter()
+                .map(|x| AllStats {
+                    collection_time: start + chrono::Duration::milliseconds(*x),
+                    ..stats.clone()
+                })
+                .collect(),
+        )
+    }
+
+    /// 获取每个时间点中每个统计历史的条目相对于 `start` 的毫秒数
+    fn aligned_offsets(start: DateTime<Local>, aligned: &[AlignedStats]) -> Vec<Vec<Option<i64>>> {
+        aligned
+            .iter()
+            .map(|point| {
+                point
+                    .stats
+                    .iter()
+                    .map(|x| x.map(|x| (x.collection_time - start).num_milliseconds()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn align_histories_matches_unsynchronized_entries() {
+        let start = Local::now();
+        let a = history_at(start, &[59_900, 119_900, 179_900]);
+        let b = history_at(start, &[60_100, 120_100, 180_100]);
+        let aligned = align_histories(&[&a, &b], Duration::from_secs(60));
+
+        assert_eq!(
+            vec![
+                vec![Some(59_900), Some(60_100)],
+                vec![Some(119_900), Some(120_100)],
+                vec![Some(179_900), Some(180_100)],
+            ],
+            aligned_offsets(start, &aligned)
+        );
+        assert_eq!(
+            start + chrono::Duration::milliseconds(59_900),
+            aligned[0].time
+        );
+    }
+
+    #[test]
+    fn align_histories_leaves_missing_entries_empty() {
+        let start = Local::now();
+        let a = history_at(start, &[0, 60_000, 120_000]);
+        let b = history_at(start, &[1_000, 121_000]);
+        let empty = StatsHistory::new(NonZeroUsize::new(1).unwrap());
+        let aligned = align_histories(&[&a, &b, &empty], Duration::from_secs(60));
+
+        assert_eq!(
+            vec![
+                vec![Some(0), Some(1_000), None],
+                vec![Some(60_000), None, None],
+                vec![Some(120_000), Some(121_000), None],
+            ],
+            aligned_offsets(start, &aligned)
+        );
+    }
+
+    #[test]
+    fn align_histories_uses_newest_entry_within_a_point() {
+        let start = Local::now();
+        let a = history_at(start, &[0, 10_000]);
+        let b = history_at(start, &[5_000]);
+        let aligned = align_histories(&[&a, &b], Duration::from_secs(60));
+
+        assert_eq!(
+            vec![vec![Some(10_000), Some(5_000)]],
+            aligned_offsets(start, &aligned)
+        );
+    }
+
+    #[test]
+    fn binary_history_files_start_with_format_version() {
+        let dir = temp_dir("binary-version");
+        let limit = NonZeroUsize::new(2).unwrap();
+        for _ in 0..2 {
+            persist_stats(
+                &test_stats(),
+                &dir,
+                u64::MAX,
+                limit,
+                PersistenceFormat::Bincode,
+                false,
+            )
+            .unwrap();
+        }
+
+        let path = dir.join(PersistenceFormat::Bincode.current_file_name());
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(
+            BINARY_FILE_FORMAT_VERSION,
+            read_binary_version(&mut file).unwrap()
+        );
+        assert_eq!(
+            2,
+            StatsHistory::load_from(&dir).unwrap().into_iter().count()
+        );
+    }
+
+    #[test]
+    fn failed_index_writes_are_rolled_back() {
+        let dir = temp_dir("index-rollback");
+        let limit = NonZeroUsize::new(2).unwrap();
+        for format in PersistenceFormat::ALL {
+            let path = dir.join(format.current_file_name());
+            // 索引文件的路径被目录占用，因此无法写入索引
+            std::fs::create_dir(index_path(&path)).unwrap();
+            assert!(persist_stats(&test_stats(), &dir, u64::MAX, limit, format, false).is_err());
+            assert_eq!(0, path.metadata().unwrap().len());
+
+            std::fs::remove_dir(index_path(&path)).unwrap();
+            persist_stats(&test_stats(), &dir, u64::MAX, limit, format, false).unwrap();
+            let mut stats = Vec::new();
+            add_stats_from_file(path, format, &mut stats).unwrap();
+            assert_eq!(1, stats.len());
+        }
+    }
+
+    #[test]
+    fn pending_writes_are_kept_until_written() {
+        let dir = temp_dir("pending-writes");
+        let limit = NonZeroUsize::new(2).unwrap();
+        let format = PersistenceFormat::Json;
+        let index = index_path(&dir.join(format.current_file_name()));
+        let mut pending_writes = PendingWrites::new(10);
+        std::fs::create_dir(&index).unwrap();
+        assert!(!pending_writes.persist(test_stats(), &dir, u64::MAX, limit, format, false));
+        assert_eq!(1, pending_writes.stats.len());
+
+        std::fs::remove_dir(&index).unwrap();
+        assert!(pending_writes.persist(test_stats(), &dir, u64::MAX, limit, format, false));
+        assert!(pending_writes.stats.is_empty());
+        assert_eq!(
+            2,
+            StatsHistory::load_from(&dir).unwrap().into_iter().count()
+        );
+    }
+
+    #[test]
+    fn undecodable_binary_records_are_skipped() {
+        let dir = temp_dir("binary-skip");
+        let path = dir.join(PersistenceFormat::Bincode.current_file_name());
+        let mut file = File::create(&path).unwrap();
+        write_binary_header(&mut file).unwrap();
+        write_binary_record(&mut file, &test_stats()).unwrap();
+        file.write_all(&3u64.to_le_bytes()).unwrap();
+        file.write_all(&[0xff; 3]).unwrap();
+        write_binary_record(&mut file, &test_stats()).unwrap();
+        // 最后一个条目未写完
+        file.write_all(&100u64.to_le_bytes()).unwrap();
+        drop(file);
+
+        let mut stats = Vec::new();
+        add_stats_from_file(path, PersistenceFormat::Bincode, &mut stats).unwrap();
+        assert_eq!(2, stats.len());
+    }
+
+    #[test]
+    fn binary_files_without_format_version_are_still_read() {
+        let dir = temp_dir("binary-legacy");
+        let path = dir.join(PersistenceFormat::Bincode.current_file_name());
+        let mut file = File::create(&path).unwrap();
+        for _ in 0..2 {
+            file.write_all(&serialize_v1(&test_stats())).unwrap();
+        }
+        drop(file);
+
+        let mut stats = Vec::new();
+        add_stats_from_file(path, PersistenceFormat::Bincode, &mut stats).unwrap();
+        assert_eq!(2, stats.len());
+
+        // 新的条目写入新的文件，而不是追加到旧格式的文件
+        let limit = NonZeroUsize::new(2).unwrap();
+        persist_stats(
+            &test_stats(),
+            &dir,
+            u64::MAX,
+            limit,
+            PersistenceFormat::Bincode,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            3,
+            StatsHistory::load_from(&dir).unwrap().into_iter().count()
+        );
+    }
+
+    #[test]
+    fn binary_files_with_format_version_1_are_still_read() {
+        let dir = temp_dir("binary-v1");
+        let path = dir.join(PersistenceFormat::Bincode.current_file_name());
+        let mut file = File::create(&path).unwrap();
+        file.write_all(BINARY_FILE_MAGIC).unwrap();
+        file.write_all(&1u32.to_le_bytes()).unwrap();
+        let bytes = serialize_v1(&test_stats());
+        file.write_all(&(bytes.len() as u64).to_le_bytes()).unwrap();
+        file.write_all(&bytes).unwrap();
+        drop(file);
+
+        let mut stats = Vec::new();
+        add_stats_from_file(path.clone(), PersistenceFormat::Bincode, &mut stats).unwrap();
+        assert_eq!(1, stats.len());
+        assert!(!stats[0].after_filtered_entries);
+
+        // 当前格式的条目写入新的文件
+        let filtered_stats = AllStats {
+            after_filtered_entries: true,
+            ..test_stats()
+        };
+        let limit = NonZeroUsize::new(2).unwrap();
+        persist_stats(
+            &filtered_stats,
+            &dir,
+            u64::MAX,
+            limit,
+            PersistenceFormat::Bincode,
+            false,
+        )
+        .unwrap();
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(
+            BINARY_FILE_FORMAT_VERSION,
+            read_binary_version(&mut file).unwrap()
+        );
+        let loaded = StatsHistory::load_from(&dir).unwrap();
+        let flags: Vec<bool> = loaded
+            .into_iter()
+            .map(|x| x.after_filtered_entries)
+            .collect();
+        assert_eq!(vec![false, true], flags);
+    }
+
+    #[test]
+    fn snapshots_skip_undecodable_entries() {
+        let dir = temp_dir("snapshot-skip");
+        let stats = vec![test_stats(), test_stats()];
+        for format in PersistenceFormat::ALL {
+            persist_snapshot(&stats, &dir, format, false).unwrap();
+            let mut loaded = Vec::new();
+            add_stats_from_snapshot(dir.join(format.snapshot_file_name()), format, &mut loaded)
+                .unwrap();
+            assert_eq!(2, loaded.len());
+        }
+
+        let path = dir.join(PersistenceFormat::Json.snapshot_file_name());
+        let mut snapshot: serde_json::Value =
+            serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        assert_eq!(JSON_SNAPSHOT_SCHEMA_VERSION as u64, snapshot["version"]);
+        snapshot["stats"][0] = serde_json::json!({ "invalid": true });
+        serde_json::to_writer(File::create(&path).unwrap(), &snapshot).unwrap();
+        let mut loaded = Vec::new();
+        add_stats_from_snapshot(path, PersistenceFormat::Json, &mut loaded).unwrap();
+        assert_eq!(1, loaded.len());
+    }
+
+    #[test]
+    fn sleep_duration_jitter_stays_within_range() {
+        let base = Duration::from_secs(3);
+        let jitter = Duration::from_millis(1000);
+        let samples: Vec<_> = (0..1000)
+            .map(|_| sleep_duration_with_jitter(base, jitter))
+            .collect();
+        for x in &samples {
+            assert!(*x >= base && *x <= base + jitter);
+        }
+        // 抖动只会增加休眠时间，平均增加抖动范围的一半
+        let mean = samples.iter().sum::<Duration>() / samples.len() as u32;
+        let expected = base + jitter / 2;
+        assert!(
+            mean.abs_diff(expected) <= Duration::from_millis(50),
+            "{:?}",
+            mean
+        );
+
+        for _ in 0..1000 {
+            assert!(sleep_duration_with_jitter(Duration::ZERO, jitter) <= jitter);
+        }
+    }
+
+    #[test]
+    fn zero_jitter_keeps_base_sleep_duration() {
+        let base = Duration::from_millis(1234);
+        for _ in 0..100 {
+            assert_eq!(base, sleep_duration_with_jitter(base, Duration::ZERO));
+        }
+    }
+
+    #[test]
+    fn rotating_history_files_shifts_each_file() {
+        let dir = temp_dir("rotate");
+        let format = PersistenceFormat::Json;
+        let path = |index| dir.join(format.ring_file_name(index));
+        let read = |path: PathBuf| std::fs::read_to_string(path).ok();
+        std::fs::write(path(0), "a").unwrap();
+        std::fs::write(path(1), "b").unwrap();
+        std::fs::write(index_path(&path(1)), "b index").unwrap();
+        std::fs::write(path(2), "c").unwrap();
+        // 减少文件数量之前留下的文件
+        std::fs::write(path(3), "d").unwrap();
+        std::fs::write(index_path(&path(3)), "d index").unwrap();
+
+        let file_count = NonZeroUsize::new(3).unwrap();
+        rotate_history_files(&dir, file_count, format).unwrap();
+        assert_eq!(None, read(path(0)));
+        assert_eq!(Some("a".to_string()), read(path(1)));
+        assert_eq!(None, read(index_path(&path(1))));
+        assert_eq!(Some("b".to_string()), read(path(2)));
+        assert_eq!(Some("b index".to_string()), read(index_path(&path(2))));
+        assert_eq!(None, read(path(3)));
+        assert_eq!(None, read(index_path(&path(3))));
+
+        std::fs::write(path(0), "e").unwrap();
+        rotate_history_files(&dir, file_count, format).unwrap();
+        assert_eq!(None, read(path(0)));
+        assert_eq!(Some("e".to_string()), read(path(1)));
+        assert_eq!(Some("a".to_string()), read(path(2)));
+        // 被丢弃的文件的索引不会留给移动到此位置的文件
+        assert_eq!(None, read(index_path(&path(2))));
+    }
+}
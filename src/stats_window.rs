@@ -0,0 +1,106 @@
+//! 最近一段时间内的统计信息的最小值、最大值、平均值和当前值
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::stats::{bytes_as_mb, AllStats};
+use crate::stats_history::Aggregate;
+
+/// 时间窗口内的统计信息汇总
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowStats {
+    /// 时间窗口的长度，以分钟为单位
+    pub minutes: u32,
+    /// 时间窗口内最早的统计信息的收集时间
+    pub from: DateTime<Local>,
+    /// 时间窗口内最近的统计信息的收集时间
+    pub to: DateTime<Local>,
+    /// 时间窗口内的历史条目数量。较旧的条目可能是合并后的统计信息，代表多次收集。
+    pub sample_count: usize,
+    /// CPU 总负载百分比
+    pub cpu_load_percent: Aggregate,
+    /// 实际使用的内存，以MB为单位
+    pub memory_used_mb: Aggregate,
+    /// 1 分钟平均负载
+    pub load_average: Aggregate,
+    /// 所有网络接口发送的总兆字节
+    pub network_sent_mb: Aggregate,
+    /// 所有网络接口接收的总兆字节
+    pub network_received_mb: Aggregate,
+}
+
+impl WindowStats {
+    /// 汇总时间窗口内的统计信息。如果时间窗口内没有统计信息，则返回 `None`。
+    ///
+    /// # 参数
+    /// * `stats` - 按收集时间从旧到新排列的统计信息
+    /// * `minutes` - 时间窗口的长度，以分钟为单位。时间窗口截止到现在。
+    /// * `include_loopback` - 网络总量是否包括环回接口
+    pub fn from<'a>(
+        stats: impl IntoIterator<Item = &'a AllStats>,
+        minutes: u32,
+        include_loopback: bool,
+    ) -> Option<WindowStats> {
+        let since = Local::now() - chrono::Duration::minutes(minutes.into());
+
+        let mut from = None;
+        let mut to = None;
+        let mut sample_count = 0;
+        let mut cpu_load_percent = Aggregate::default();
+        let mut memory_used_mb = Aggregate::default();
+        let mut load_average = Aggregate::default();
+        let mut network_sent_mb = Aggregate::default();
+        let mut network_received_mb = Aggregate::default();
+
+        for stats in stats.into_iter().filter(|x| x.collection_time >= since) {
+            from.get_or_insert(stats.collection_time);
+            to = Some(stats.collection_time);
+            sample_count += 1;
+
+            if let Some(load) = stats.cpu.aggregate_load_percent {
+                cpu_load_percent.add_with_range(
+                    load,
+                    stats.cpu.min_aggregate_load_percent.unwrap_or(load),
+                    stats.cpu.peak_aggregate_load_percent.unwrap_or(load),
+                );
+            }
+
+            if let Some(memory) = &stats.memory {
+                let used = memory.effective_used_bytes();
+                memory_used_mb.add_with_range(
+                    bytes_as_mb(used) as f32,
+                    bytes_as_mb(memory.min_effective_used_bytes.unwrap_or(used)) as f32,
+                    bytes_as_mb(memory.max_effective_used_bytes.unwrap_or(used)) as f32,
+                );
+            }
+
+            if let Some(load_averages) = &stats.general.load_averages {
+                load_average.add(load_averages.one_minute);
+            }
+
+            if let Some(interfaces) = &stats.network.interfaces {
+                let interfaces = interfaces
+                    .iter()
+                    .filter(|x| include_loopback || !x.loopback);
+                let (sent, received) = interfaces.fold((0, 0), |(sent, received), x| {
+                    (sent + x.sent_mb, received + x.received_mb)
+                });
+                network_sent_mb.add(sent as f32);
+                network_received_mb.add(received as f32);
+            }
+        }
+
+        Some(WindowStats {
+            minutes,
+            from: from?,
+            to: to?,
+            sample_count,
+            cpu_load_percent,
+            memory_used_mb,
+            load_average,
+            network_sent_mb,
+            network_received_mb,
+        })
+    }
+}
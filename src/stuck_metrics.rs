@@ -0,0 +1,144 @@
+//! 检测长时间保持完全相同的值的指标，例如卡住的温度传感器或停止增长的计数器
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::stats::AllStats;
+
+/// 要检测的指标的名称，以及从统计信息中获取指标值的函数。正常情况下也可能长时间保持不变的值返回“None”，不做检测。
+type Metric = (&'static str, fn(&AllStats) -> Option<f64>);
+
+/// 要检测的指标。名称与 `/stats/capabilities` 中的相同。
+///
+/// 只检测取值连续的指标。进程数和平均负载（保留两位小数，空闲时一直为 0.00）这类离散的指标在正常情况下也经常保持不变，因此不做检测。
+const METRICS: &[Metric] = &[
+    // 完全空闲或满载时负载会一直保持在边界值
+    ("aggregateCpuLoad", |x| {
+        x.cpu
+            .aggregate_load_percent
+            .filter(|x| *x > 0.0 && *x < 100.0)
+            .map(f64::from)
+    }),
+    // 只提供整数读数的传感器在温度稳定时会一直报告相同的值
+    ("cpuTemp", |x| {
+        x.cpu
+            .temp_celsius
+            .filter(|x| x.fract() != 0.0)
+            .map(f64::from)
+    }),
+    // 使用未四舍五入的值，避免空闲时的微小变化被舍去
+    ("memory", |x| {
+        x.memory.as_ref().map(|x| x.effective_used_mb())
+    }),
+];
+
+/// 疑似卡住的指标
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StuckMetric {
+    /// 指标的名称
+    pub name: &'static str,
+    /// 一直保持的值
+    pub value: f64,
+    /// 最近连续多少个历史条目的值完全相同
+    pub entries: usize,
+    /// 这些条目中最早的收集时间
+    pub since: DateTime<Local>,
+}
+
+/// 查找最近至少 `min_entries` 个历史条目的值完全相同的指标。不可用的指标不做检测。
+///
+/// 历史条目是合并后的统计信息。检测的指标取值连续，正常情况下几乎不会在多个条目中保持完全相同的平均值，
+/// 而在正常情况下也可能保持不变的指标或值不做检测，详见 `METRICS`。
+///
+/// # 参数
+/// * `stats` - 按收集时间从旧到新排列的统计信息
+/// * `min_entries` - 值至少连续相同多少个条目时视为卡住
+pub fn find_stuck_metrics<'a>(
+    stats: impl IntoIterator<Item = &'a AllStats>,
+    min_entries: usize,
+) -> Vec<StuckMetric> {
+    let stats: Vec<&AllStats> = stats.into_iter().collect();
+    let mut stuck = Vec::new();
+    for (name, value_of) in METRICS {
+        let latest = match stats.last().and_then(|x| value_of(x)) {
+            Some(x) => x,
+            None => continue,
+        };
+        let run = stats
+            .iter()
+            .rev()
+            .take_while(|x| value_of(x) == Some(latest))
+            .count();
+        if run >= min_entries {
+            stuck.push(StuckMetric {
+                name,
+                value: latest,
+                entries: run,
+                since: stats[stats.len() - run].collection_time,
+            });
+        }
+    }
+
+    stuck
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::stats::{Collector, CollectorConfig, MountCollection};
+    use crate::system_source::fixed::FixedSystem;
+
+    /// 创建 `count` 个 CPU 负载和温度固定、可用内存逐个增加的统计信息
+    fn stats(count: usize, load_percent: f32, temp_celsius: f32) -> Vec<AllStats> {
+        let collectors = CollectorConfig {
+            disabled_collectors: vec![Collector::Mounts],
+            mount_collection_timeout: Duration::from_secs(1),
+            log_unsupported_once: true,
+            concurrent_collection: false,
+        };
+        let stats = AllStats::from(
+            &FixedSystem::default(),
+            Duration::ZERO,
+            &collectors,
+            &MountCollection::default(),
+        );
+        (0..count)
+            .map(|i| {
+                let mut x = stats.clone();
+                x.collection_time = x.collection_time + chrono::Duration::seconds(i as i64);
+                x.cpu.aggregate_load_percent = Some(load_percent);
+                x.cpu.temp_celsius = Some(temp_celsius);
+                let memory = x.memory.as_mut().unwrap();
+                memory.available_bytes = memory.available_bytes.map(|x| x + i as u64 * 4096);
+                x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_continuous_values_are_stuck() {
+        let mut stats = stats(4, 37.5, 41.5);
+        stats[0].cpu.aggregate_load_percent = Some(12.5);
+
+        let stuck = find_stuck_metrics(&stats, 3);
+        let names: Vec<&str> = stuck.iter().map(|x| x.name).collect();
+        assert_eq!(names, vec!["aggregateCpuLoad", "cpuTemp"]);
+        assert_eq!(stuck[0].value, 37.5);
+        assert_eq!(stuck[0].entries, 3);
+        assert_eq!(stuck[0].since, stats[1].collection_time);
+        assert_eq!(stuck[1].entries, 4);
+
+        // 值相同的条目不够多时不视为卡住
+        assert!(find_stuck_metrics(&stats, 5).is_empty());
+    }
+
+    #[test]
+    fn values_that_naturally_stay_the_same_are_not_stuck() {
+        // 空闲时的负载和整数温度读数
+        let stats = stats(4, 0.0, 42.0);
+        assert!(find_stuck_metrics(&stats, 3).is_empty());
+    }
+}
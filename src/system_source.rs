@@ -0,0 +1,277 @@
+//! 收集统计信息所用的系统信息来源
+
+use std::{collections::HashMap, io};
+
+use systemstat::{
+    BTreeMap, CPULoad, DelayedMeasurement, Duration, Filesystem, LoadAverage, Memory, Network,
+    OffsetDateTime, Platform, System,
+};
+
+use crate::stats::{self, CpuCounters};
+
+/// 收集统计信息所用的系统信息来源。只包含 `systemstat::Platform` 中收集统计信息时用到的方法，
+/// 因此除了真实的系统之外，也可以用返回固定数据的实现来收集统计信息。
+///
+/// 各方法的含义与 `systemstat::Platform` 中的同名方法相同。
+pub trait SystemSource: Sync + Sized + Send + 'static {
+    /// 获取每个逻辑 CPU 的负载。需要等待一段时间后再获取结果。
+    fn cpu_load(&self) -> io::Result<DelayedMeasurement<Vec<CPULoad>>>;
+
+    /// 获取所有 CPU 的总负载。需要等待一段时间后再获取结果。
+    fn cpu_load_aggregate(&self) -> io::Result<DelayedMeasurement<CPULoad>>;
+
+    /// 获取 CPU 的温度，以摄氏度为单位
+    fn cpu_temp(&self) -> io::Result<f32>;
+
+    /// 获取平均负载
+    fn load_average(&self) -> io::Result<LoadAverage>;
+
+    /// 获取内存信息
+    fn memory(&self) -> io::Result<Memory>;
+
+    /// 获取系统运行时间
+    fn uptime(&self) -> io::Result<Duration>;
+
+    /// 获取系统启动的时间
+    fn boot_time(&self) -> io::Result<OffsetDateTime>;
+
+    /// 获取所有挂载的文件系统
+    fn mounts(&self) -> io::Result<Vec<Filesystem>>;
+
+    /// 获取所有网络接口，以接口名称为键
+    fn networks(&self) -> io::Result<BTreeMap<String, Network>>;
+
+    /// 获取网络接口的统计信息
+    ///
+    /// # 参数
+    /// * `interface` - 接口名称
+    fn network_stats(&self, interface: &str) -> io::Result<systemstat::NetworkStats>;
+
+    /// 获取套接字的统计信息
+    fn socket_stats(&self) -> io::Result<systemstat::SocketStats>;
+
+    /// 读取 CPU 时间计数器。如果无法读取，则返回“None”。
+    fn cpu_counters(&self) -> Option<CpuCounters>;
+
+    /// 获取每个挂载点的挂载选项，以挂载点为键。如果无法读取，则返回空映射。
+    fn mount_options(&self) -> HashMap<String, String>;
+
+    /// 获取内核随机数熵池中可用的熵，以比特为单位。如果无法获取，则返回“None”。
+    fn entropy_available(&self) -> Option<u64>;
+
+    /// 创建一个相同来源的新实例，用于在单独的线程中获取可能长时间阻塞的信息（例如挂载信息）。
+    fn detached(&self) -> Self;
+}
+
+impl SystemSource for System {
+    fn cpu_load(&self) -> io::Result<DelayedMeasurement<Vec<CPULoad>>> {
+        Platform::cpu_load(self)
+    }
+
+    fn cpu_load_aggregate(&self) -> io::Result<DelayedMeasurement<CPULoad>> {
+        Platform::cpu_load_aggregate(self)
+    }
+
+    fn cpu_temp(&self) -> io::Result<f32> {
+        Platform::cpu_temp(self)
+    }
+
+    fn load_average(&self) -> io::Result<LoadAverage> {
+        Platform::load_average(self)
+    }
+
+    fn memory(&self) -> io::Result<Memory> {
+        Platform::memory(self)
+    }
+
+    fn uptime(&self) -> io::Result<Duration> {
+        Platform::uptime(self)
+    }
+
+    fn boot_time(&self) -> io::Result<OffsetDateTime> {
+        Platform::boot_time(self)
+    }
+
+    fn mounts(&self) -> io::Result<Vec<Filesystem>> {
+        Platform::mounts(self)
+    }
+
+    fn networks(&self) -> io::Result<BTreeMap<String, Network>> {
+        Platform::networks(self)
+    }
+
+    fn network_stats(&self, interface: &str) -> io::Result<systemstat::NetworkStats> {
+        Platform::network_stats(self, interface)
+    }
+
+    fn socket_stats(&self) -> io::Result<systemstat::SocketStats> {
+        Platform::socket_stats(self)
+    }
+
+    fn cpu_counters(&self) -> Option<CpuCounters> {
+        CpuCounters::read()
+    }
+
+    fn mount_options(&self) -> HashMap<String, String> {
+        stats::mount_options()
+    }
+
+    fn entropy_available(&self) -> Option<u64> {
+        stats::entropy_available()
+    }
+
+    fn detached(&self) -> System {
+        System::new()
+    }
+}
+
+/// 用于测试的系统信息来源
+#[cfg(test)]
+pub(crate) mod fixed {
+    use super::*;
+    use std::sync::{mpsc, Arc, Mutex};
+
+    /// 返回固定数据的系统信息来源，用于测试
+    #[derive(Default)]
+    pub struct FixedSystem {
+        /// 提供时，获取挂载信息前等待从中收到消息（或发送方被丢弃），用于模拟没有响应的挂载
+        pub mount_gate: Option<Arc<Mutex<mpsc::Receiver<()>>>>,
+    }
+
+    impl FixedSystem {
+        /// 空闲比例为 `idle`、其余部分由用户和系统平分的 CPU 负载
+        fn cpu(idle: f32) -> CPULoad {
+            CPULoad {
+                user: (1.0 - idle) / 2.0,
+                nice: 0.0,
+                system: (1.0 - idle) / 2.0,
+                interrupt: 0.0,
+                idle,
+                platform: cpu_platform(),
+            }
+        }
+    }
+
+    /// 没有任何负载的平台相关 CPU 负载
+    #[cfg(target_os = "linux")]
+    fn cpu_platform() -> systemstat::PlatformCpuLoad {
+        systemstat::PlatformCpuLoad { iowait: 0.0 }
+    }
+
+    /// 没有任何负载的平台相关 CPU 负载
+    #[cfg(not(target_os = "linux"))]
+    fn cpu_platform() -> systemstat::PlatformCpuLoad {
+        systemstat::PlatformCpuLoad {}
+    }
+
+    /// 此来源不提供的信息
+    fn unsupported<T>() -> io::Result<T> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    impl SystemSource for FixedSystem {
+        fn cpu_load(&self) -> io::Result<DelayedMeasurement<Vec<CPULoad>>> {
+            Ok(DelayedMeasurement::new(Box::new(|| {
+                Ok(vec![FixedSystem::cpu(0.75), FixedSystem::cpu(0.25)])
+            })))
+        }
+
+        fn cpu_load_aggregate(&self) -> io::Result<DelayedMeasurement<CPULoad>> {
+            Ok(DelayedMeasurement::new(Box::new(|| {
+                Ok(FixedSystem::cpu(0.5))
+            })))
+        }
+
+        fn cpu_temp(&self) -> io::Result<f32> {
+            Ok(42.5)
+        }
+
+        fn load_average(&self) -> io::Result<LoadAverage> {
+            Ok(LoadAverage {
+                one: 1.5,
+                five: 1.0,
+                fifteen: 0.5,
+            })
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        fn memory(&self) -> io::Result<Memory> {
+            let meminfo = [
+                ("MemAvailable", 3 * 1024 * 1024 * 1024),
+                ("Cached", 1024 * 1024 * 1024),
+                ("Buffers", 256 * 1024 * 1024),
+            ]
+            .into_iter()
+            .map(|(key, bytes)| (key.to_string(), systemstat::ByteSize::b(bytes)))
+            .collect();
+            Ok(Memory {
+                total: systemstat::ByteSize::gib(8),
+                free: systemstat::ByteSize::gib(2),
+                platform_memory: systemstat::PlatformMemory { meminfo },
+            })
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        fn memory(&self) -> io::Result<Memory> {
+            unsupported()
+        }
+
+        fn uptime(&self) -> io::Result<Duration> {
+            Ok(Duration::from_secs(3600))
+        }
+
+        fn boot_time(&self) -> io::Result<OffsetDateTime> {
+            Ok(OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap())
+        }
+
+        fn mounts(&self) -> io::Result<Vec<Filesystem>> {
+            if let Some(gate) = &self.mount_gate {
+                let _ = gate.lock().unwrap().recv();
+            }
+            let mount = |mounted_on: &str, total_gb: u64, avail_gb: u64| Filesystem {
+                files: 0,
+                files_total: 0,
+                files_avail: 0,
+                free: systemstat::ByteSize::gb(avail_gb),
+                avail: systemstat::ByteSize::gb(avail_gb),
+                total: systemstat::ByteSize::gb(total_gb),
+                name_max: 255,
+                fs_type: "ext4".to_string(),
+                fs_mounted_from: "/dev/sda1".to_string(),
+                fs_mounted_on: mounted_on.to_string(),
+            };
+            // 总空间为 0 字节的挂载应被忽略
+            Ok(vec![mount("/", 100, 40), mount("/proc", 0, 0)])
+        }
+
+        fn networks(&self) -> io::Result<BTreeMap<String, Network>> {
+            Ok(BTreeMap::new())
+        }
+
+        fn network_stats(&self, _: &str) -> io::Result<systemstat::NetworkStats> {
+            unsupported()
+        }
+
+        fn socket_stats(&self) -> io::Result<systemstat::SocketStats> {
+            unsupported()
+        }
+
+        fn cpu_counters(&self) -> Option<CpuCounters> {
+            None
+        }
+
+        fn mount_options(&self) -> HashMap<String, String> {
+            HashMap::from([("/".to_string(), "ro,relatime".to_string())])
+        }
+
+        fn entropy_available(&self) -> Option<u64> {
+            Some(256)
+        }
+
+        fn detached(&self) -> FixedSystem {
+            FixedSystem {
+                mount_gate: self.mount_gate.clone(),
+            }
+        }
+    }
+}
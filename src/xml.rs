@@ -0,0 +1,37 @@
+//! 供只能读取 XML 的监控系统使用的 XML 响应。只在启用 `xml` feature 时编译。
+
+use rocket::http::{ContentType, Status};
+use rocket::response::{self, Responder};
+use rocket::Request;
+use serde::Serialize;
+
+/// XML 的根元素的名称。根元素中的元素名与 JSON 的字段名相同。
+const ROOT_ELEMENT_NAME: &str = "stats";
+
+/// 添加在 XML 开头的声明
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+
+/// 一个 XML 响应。值按照与 JSON 相同的字段名序列化，放在 `<stats>` 根元素中；列表中的每一项都是一个同名的元素。
+pub struct Xml<T>(pub T);
+
+impl<'r, T: Serialize> Responder<'r, 'static> for Xml<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = quick_xml::se::to_string_with_root(ROOT_ELEMENT_NAME, &self.0).map_err(|e| {
+            //TODO use actual logging once https://github.com/SergioBenitez/Rocket/issues/21 is done
+            println!("XML 序列化失败: {}", e);
+            Status::InternalServerError
+        })?;
+        (ContentType::XML, format!("{}{}", XML_DECLARATION, body)).respond_to(request)
+    }
+}
+
+/// 请求的 `Accept` 头是否首选 XML，即 `application/xml` 或 `text/xml`
+///
+/// # 参数
+/// * `request` - 要检查的请求
+pub fn prefers_xml(request: &Request<'_>) -> bool {
+    match request.accept() {
+        Some(accept) => accept.preferred().media_type().sub() == "xml",
+        None => false,
+    }
+}